@@ -0,0 +1,18 @@
+// Import from `core` instead of from `std` since we are in no-std mode
+use core::result::Result;
+
+use ckb_std::debug;
+use woss::vectors::check_test_vectors;
+
+use crate::error::Error;
+
+// Checks commitment scheme conformance vectors in a `no_std` build, so
+// commitments computed on chain follow the scheme of the prover. Run by
+// contract tests instead of the step verifier lock, which would pay for them
+// on every spend.
+pub fn main() -> Result<(), Error> {
+    check_test_vectors().map_err(|name| {
+        debug!("test vector {} mismatch", name);
+        Error::TestVectorMismatch
+    })
+}
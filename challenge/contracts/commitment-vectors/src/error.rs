@@ -0,0 +1,5 @@
+/// Error
+#[repr(i8)]
+pub enum Error {
+    TestVectorMismatch = 1,
+}
@@ -10,41 +10,67 @@ use ckb_std::{
     high_level::{load_script, load_witness_args},
 };
 use woss::{
-    machine::StepCommitment,
+    ckb_vm::Register,
+    machine::{StepCommitment, StepProof},
     types::{
-        conversion::Unpack as WossUnpack,
-        packed::{Bytes32, StepProof32},
+        conversion::{TryUnpack, Unpack as WossUnpack},
+        packed::{Bytes32, StepProof32, StepProof64},
     },
     verifier::Verifier,
 };
 
 use crate::error::Error;
 
+// Lock args: step commitment (32 bytes) | next step commitment (32 bytes) | register bits (1 byte, optional)
+//
+// Register bits defaults to 32 if omitted.
+const COMMITMENTS_ARGS_LEN: usize = 64;
+const REGISTER_BITS_32: u8 = 32;
+const REGISTER_BITS_64: u8 = 64;
+
 // This lock is intented to demostrate that Verifer can execute step correctly.
 pub fn main() -> Result<(), Error> {
     let witness_args = load_witness_args(0, Source::GroupInput)?;
+    let proof: Bytes = witness_args
+        .lock()
+        .to_opt()
+        .ok_or(Error::InvalidWitness)?
+        .unpack();
 
-    let step_proof = {
-        let args: Bytes = witness_args
-            .lock()
-            .to_opt()
-            .ok_or(Error::InvalidWitness)?
-            .unpack();
-
-        StepProof32::from_slice(&args)
-            .map_err(|_| Error::InvalidStepProof)?
-            .unpack()
+    let args: Bytes = load_script()?.args().unpack();
+    let register_bits = match args.len() {
+        COMMITMENTS_ARGS_LEN => REGISTER_BITS_32,
+        len if len == COMMITMENTS_ARGS_LEN + 1 => args[COMMITMENTS_ARGS_LEN],
+        len => {
+            debug!("args len {}", len);
+            return Err(Error::InvalidLockArgs);
+        }
     };
-    debug!("step {}", step_proof.step_num);
 
-    let expected_steps = {
-        let script = load_script()?;
-        let args: Bytes = script.args().unpack();
-        if args.len() != 64 {
-            debug!("args len {}", args.len());
-            return Err(Error::InvalidLockArgs);
+    match register_bits {
+        REGISTER_BITS_32 => {
+            let step_proof = StepProof32::from_slice(&proof)
+                .map_err(|_| woss::Error::Decode)?
+                .try_unpack()?;
+            verify_step::<u32>(step_proof, &args)
+        }
+        REGISTER_BITS_64 => {
+            let step_proof = StepProof64::from_slice(&proof)
+                .map_err(|_| woss::Error::Decode)?
+                .try_unpack()?;
+            verify_step::<u64>(step_proof, &args)
         }
+        bits => {
+            debug!("unsupported register bits {}", bits);
+            Err(Error::InvalidLockArgs)
+        }
+    }
+}
+
+fn verify_step<R: Register + 'static>(step_proof: StepProof<R>, args: &Bytes) -> Result<(), Error> {
+    debug!("step {} register bits {}", step_proof.step_num, R::BITS);
 
+    let expected_steps = {
         let step_commitment = StepCommitment {
             step_num: step_proof.step_num,
             commitment: Bytes32::new_unchecked(args.slice(0..32)).unpack(),
@@ -52,7 +78,10 @@ pub fn main() -> Result<(), Error> {
         debug!("expect commitment {:?}", step_commitment.commitment);
 
         let next_step_commitment = StepCommitment {
-            step_num: step_proof.step_num + 1,
+            step_num: step_proof
+                .step_num
+                .checked_add(1)
+                .ok_or(woss::Error::StepOverflow)?,
             commitment: Bytes32::new_unchecked(args.slice(32..64)).unpack(),
         };
         debug!(
@@ -63,19 +92,10 @@ pub fn main() -> Result<(), Error> {
         (step_commitment, next_step_commitment)
     };
 
-    let mut verifier =
-        Verifier::<u32>::from_proof(step_proof).map_err(|_| Error::InvalidStepProof)?;
-    let commitment = verifier.commit_step().map_err(|_| Error::VerifierCommit)?;
-    if commitment != expected_steps.0 {
-        debug!("verifier commitment {:?}", commitment.commitment);
-        return Err(Error::MismatchStepCommitment);
-    }
-
-    verifier
-        .execute_next_step()
-        .map_err(|_| Error::ExecuteNextStep)?;
+    let mut verifier = Verifier::<R>::from_proof(step_proof)?;
+    verifier.check_commitment(&expected_steps.0)?;
 
-    let next_commitment = verifier.commit_step().map_err(|_| Error::VerifierCommit)?;
+    let next_commitment = verifier.execute_next_step()?;
     if next_commitment != expected_steps.1 {
         debug!("verifier next commitment {:?}", next_commitment.commitment);
         return Err(Error::MismatchNextStepCommitment);
@@ -1,20 +1,41 @@
 use ckb_std::error::SysError;
 
+/// Codes of woss errors are offset by this base
+pub const WOSS_ERROR_BASE: i8 = 32;
+
 /// Error
-#[repr(i8)]
 pub enum Error {
-    IndexOutOfBound = 1,
-    ItemMissing = 2,
-    LengthNotEnough = 3,
-    Encoding = 4,
+    IndexOutOfBound,
+    ItemMissing,
+    LengthNotEnough,
+    Encoding,
     // Add customized errors here...
-    InvalidWitness = 5,
-    InvalidStepProof = 6,
-    InvalidLockArgs = 7,
-    VerifierCommit = 8,
-    MismatchStepCommitment = 9,
-    ExecuteNextStep = 10,
-    MismatchNextStepCommitment = 11,
+    InvalidWitness,
+    InvalidLockArgs,
+    MismatchNextStepCommitment,
+    /// Errors of woss verifier
+    Woss(woss::Error),
+}
+
+impl Error {
+    /// Exit code of the script
+    pub fn code(&self) -> i8 {
+        match self {
+            Self::IndexOutOfBound => 1,
+            Self::ItemMissing => 2,
+            Self::LengthNotEnough => 3,
+            Self::Encoding => 4,
+            Self::InvalidWitness => 5,
+            // 6 was InvalidStepProof, replaced by errors of woss verifier
+            Self::InvalidLockArgs => 7,
+            // 8 was VerifierCommit, replaced by errors of woss verifier
+            // 9 was MismatchStepCommitment, replaced by woss `CommitmentMismatch`
+            // 10 was ExecuteNextStep, replaced by errors of woss verifier
+            Self::MismatchNextStepCommitment => 11,
+            // 12 was TestVectorMismatch, checked by commitment-vectors contract
+            Self::Woss(err) => WOSS_ERROR_BASE + err.code() as i8,
+        }
+    }
 }
 
 impl From<SysError> for Error {
@@ -29,3 +50,9 @@ impl From<SysError> for Error {
         }
     }
 }
+
+impl From<woss::Error> for Error {
+    fn from(err: woss::Error) -> Self {
+        Self::Woss(err)
+    }
+}
@@ -30,7 +30,7 @@ fn program_entry(_argc: u64, _argv: *const *const u8) -> i8 {
     // Call main function and return error code
     match entry::main() {
         Ok(_) => 0,
-        Err(err) => err as i8,
+        Err(err) => err.code(),
     }
 }
 
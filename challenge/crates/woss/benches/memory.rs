@@ -0,0 +1,88 @@
+use std::fs;
+
+use ckb_vm::{Bytes, FlatMemory, Memory, RISCV_MAX_MEMORY};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use woss::{
+    machine::Machine,
+    memory::{flat::LazySMT, prover::ProverSMT, shallow::ShallowProverSMT, SMTMemory},
+};
+
+const MEMORY_SIZE: usize = 4 << 20;
+const SIZES: [u64; 3] = [32, 4 << 10, 64 << 10];
+
+fn bench_memory<M: Memory<REG = u64>>(c: &mut Criterion, name: &str, memory_size: usize) {
+    let mut group = c.benchmark_group(name);
+
+    for size in SIZES {
+        let value = vec![0x5au8; size as usize];
+        group.bench_with_input(BenchmarkId::new("store_bytes", size), &size, |b, _| {
+            b.iter_batched(
+                || M::new(memory_size),
+                |mut mem| mem.store_bytes(0x1000, &value).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+
+        group.bench_with_input(BenchmarkId::new("store_byte", size), &size, |b, &size| {
+            b.iter_batched(
+                || M::new(memory_size),
+                |mut mem| mem.store_byte(0x1000, size, 0x5a).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+
+        let source = Bytes::from(value.clone());
+        group.bench_with_input(BenchmarkId::new("init_pages", size), &size, |b, &size| {
+            b.iter_batched(
+                || M::new(memory_size),
+                |mut mem| {
+                    mem.init_pages(0x1000, size, 0, Some(source.clone()), 0)
+                        .unwrap()
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_store(c: &mut Criterion) {
+    bench_memory::<FlatMemory<u64>>(c, "flat", RISCV_MAX_MEMORY);
+    bench_memory::<SMTMemory<u64, ProverSMT>>(c, "smt", RISCV_MAX_MEMORY);
+    bench_memory::<SMTMemory<u64, ShallowProverSMT>>(c, "shallow", RISCV_MAX_MEMORY);
+    bench_memory::<SMTMemory<u64, LazySMT<ProverSMT>>>(c, "lazy", RISCV_MAX_MEMORY);
+}
+
+fn bench_load_program(c: &mut Criterion) {
+    // Assembled from simple64.S
+    let program: Bytes = fs::read("../../../simple64").unwrap().into();
+
+    let mut group = c.benchmark_group("load_program");
+    group.bench_function("smt", |b| {
+        b.iter(|| {
+            let mut machine = Machine::<u64, ProverSMT>::new(MEMORY_SIZE);
+            machine.load_program(&program).unwrap()
+        })
+    });
+    group.bench_function("shallow", |b| {
+        b.iter(|| {
+            let mut machine = Machine::<u64, ShallowProverSMT>::new(MEMORY_SIZE);
+            machine.load_program(&program).unwrap()
+        })
+    });
+    group.bench_function("lazy", |b| {
+        b.iter(|| {
+            let mut machine = Machine::<u64, LazySMT<ProverSMT>>::new(MEMORY_SIZE);
+            machine.load_program(&program).unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_store, bench_load_program
+}
+criterion_main!(benches);
@@ -1,9 +1,15 @@
-use std::{fs, ops::Deref};
+use std::fs;
 
-use ckb_vm::RISCV_MAX_MEMORY;
+use ckb_vm::{Bytes, Register, RISCV_MAX_MEMORY};
 use rand::Rng;
 
-use woss::{machine::StepCommitment, dissection::StepDiffFinder, prover::Prover, verifier::Verifier};
+use woss::{
+    dissection::{Dissection, DissectionState, Party, StepDiffFinder},
+    machine::{StepCommitment, StepProof},
+    prover::Prover,
+    types::conversion::MoleculeSize,
+    verifier::Verifier,
+};
 
 fn forge_steps(mut steps: Vec<StepCommitment>, start_at: u64) -> Vec<StepCommitment> {
     let mut rng = rand::rngs::OsRng::default();
@@ -13,33 +19,32 @@ fn forge_steps(mut steps: Vec<StepCommitment>, start_at: u64) -> Vec<StepCommitm
     steps.to_vec()
 }
 
-enum Finder {
-    Challenger(StepDiffFinder),
-    Producer(StepDiffFinder),
-}
-
-impl Deref for Finder {
-    type Target = StepDiffFinder;
-
-    fn deref(&self) -> &Self::Target {
-        match self {
-            Finder::Challenger(ref f) => f,
-            Finder::Producer(ref f) => f,
-        }
-    }
-}
-
 fn main() {
-    let mut prover = Prover::<u32>::new(RISCV_MAX_MEMORY);
     // https://github.com/nervosnetwork/ckb-vm/blob/develop/tests/programs/simple
     let buffer = fs::read("./simple").unwrap().into();
-    prover.load_program(&buffer).unwrap();
+    challenge::<u32>(&buffer);
+
+    // Assembled from simple64.S
+    let buffer = fs::read("./simple64").unwrap().into();
+    challenge::<u64>(&buffer);
+}
+
+fn challenge<R: Register + 'static>(buffer: &Bytes)
+where
+    StepProof<R>: MoleculeSize,
+{
+    let mut prover = Prover::<R>::new(RISCV_MAX_MEMORY);
+    prover.load_program(buffer).unwrap();
 
     let result = prover.run().unwrap();
-    println!("step count: {}", result.step_count);
+    println!("rv{} step count: {}", R::BITS, result.step_count);
 
     prover.reset();
-    prover.load_program(&buffer).unwrap();
+    prover.load_program(buffer).unwrap();
+
+    // Both parties should agree on the initial state before dissection starts
+    let initial = prover.initial_commitment().unwrap();
+    assert_eq!(initial.step, result.step_commitments[0]);
 
     // Step 0 should not be challenged
     let random_diff_step = rand::rngs::OsRng::default().gen::<u64>() % (result.step_count - 1) + 1;
@@ -49,38 +54,41 @@ fn main() {
     assert_ne!(diff_step, correct_step);
     println!("random diff step {}", random_diff_step);
 
-    let producer = Finder::Producer(StepDiffFinder::new(forged_steps));
-    let challenger = Finder::Challenger(StepDiffFinder::new(result.step_commitments.clone()));
+    let mut producer = StepDiffFinder::new(forged_steps);
+    let mut challenger = StepDiffFinder::new(result.step_commitments.clone());
 
-    let mut steps_to_diff = producer.step_range(0, result.step_count as usize);
-    let mut next = &challenger;
-
-    while steps_to_diff.len() > 1 {
-        let (start, end) = next.diff_step_range(&steps_to_diff);
-        steps_to_diff = if start == end {
-            vec![*start]
-        } else {
-            next.step_range(start.step_num as usize, end.step_num as usize)
+    // Use round number as clock
+    let opening = producer.step_range(0, result.step_count as usize).unwrap();
+    let mut dissection = Dissection::new(initial.step, opening, 0, 1).unwrap();
+    while let Some(turn) = dissection.turn() {
+        let finder = match turn {
+            Party::Producer => &mut producer,
+            Party::Challenger => &mut challenger,
         };
-        next = if matches!(next, Finder::Challenger(_)) {
-            &producer
-        } else {
-            &challenger
-        }
+        let mv = finder.next_move(dissection.steps()).unwrap();
+        dissection.submit(turn, mv, dissection.round()).unwrap();
     }
-    assert_eq!(diff_step.step_num, steps_to_diff.first().unwrap().step_num);
+    println!("dissection resolved in {} rounds", dissection.round());
 
-    let last_same_step = *result
-        .step_commitments
-        .get((diff_step.step_num - 1) as usize)
-        .unwrap();
+    let last_same_step = match dissection.state() {
+        DissectionState::Resolved { agreed, disputed } => {
+            assert_eq!(*disputed, diff_step);
+            *agreed
+        }
+        state => panic!("unexpected dissection state {:?}", state),
+    };
+    assert_eq!(dissection.step_to_prove(), Some(last_same_step.step_num));
+    assert_eq!(
+        last_same_step,
+        result.step_commitments[(diff_step.step_num - 1) as usize]
+    );
     let result = prover.run_until_step(last_same_step.step_num).unwrap();
     assert_eq!(result.step_count, last_same_step.step_num);
 
     let proof = prover.prove_next_step().unwrap();
     // let json_proof = serde_json::to_vec(&proof).unwrap();
     // fs::write("./proof.json", json_proof).unwrap();
-    let mut verifier = Verifier::<u32>::from_proof(proof).unwrap();
+    let mut verifier = Verifier::<R>::from_proof(proof).unwrap();
     assert_eq!(last_same_step, verifier.commit_step().unwrap());
 
     verifier.execute_next_step().unwrap();
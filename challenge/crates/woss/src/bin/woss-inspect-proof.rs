@@ -0,0 +1,240 @@
+//! Print a step proof and verify it locally.
+//!
+//! Usage: woss-inspect-proof [--rv64] [--shallow | --smt] <proof>
+//!
+//! Proof is molecule `StepProof32` or `StepProof64`, or JSON serialized
+//! `StepProof`. Register width of JSON proof is given by `--rv64`, molecule
+//! proof is detected.
+//!
+//! Memory tree is detected by touched keys, `--shallow` or `--smt` overrides
+//! it. A proof touching no keys can't be told apart, one of them is required.
+
+use std::{collections::BTreeMap, convert::TryFrom, env, fs, process};
+
+use ckb_vm::{
+    instructions::{extract_opcode, instruction_opcode_name, tagged::TaggedInstruction},
+    Register,
+};
+use woss::{
+    machine::StepProof,
+    memory::{
+        hash_data_chunk_key, hash_flag_key, shallow::ShallowVerifierSMT, verifier::VerifierSMT,
+        KeyHint, SMTMemory, SMTOps, SMTRestore,
+    },
+    types::{
+        conversion::{Entity, TryUnpack},
+        packed::{StepProof32, StepProof64},
+        Bytes32,
+    },
+    verifier::Verifier,
+    Error,
+};
+
+const REGISTER_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+const DATA_CHUNK_SIZE: u64 = SMTMemory::<u64, VerifierSMT>::DATA_CHUNK_SIZE;
+
+const USAGE: &str = "usage: woss-inspect-proof [--rv64] [--shallow | --smt] <proof>";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tree {
+    /// Index keyed `ShallowVerifierSMT`
+    Shallow,
+    /// Hash keyed `VerifierSMT`
+    Smt,
+}
+
+fn main() {
+    let mut rv64 = false;
+    let mut tree = None;
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--rv64" => rv64 = true,
+            "--shallow" if tree.is_none() => tree = Some(Tree::Shallow),
+            "--smt" if tree.is_none() => tree = Some(Tree::Smt),
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        }
+    }
+    let path = path.unwrap_or_else(|| {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    });
+
+    let bytes = fs::read(&path).unwrap_or_else(|err| {
+        eprintln!("read {}: {}", path, err);
+        process::exit(2);
+    });
+
+    let ret = if bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+        if rv64 {
+            from_json::<u64>(&bytes).and_then(|proof| inspect(proof, tree))
+        } else {
+            from_json::<u32>(&bytes).and_then(|proof| inspect(proof, tree))
+        }
+    } else if let Ok(proof) = StepProof32::from_slice(&bytes) {
+        proof
+            .try_unpack()
+            .map_err(|err| err.to_string())
+            .and_then(|proof| inspect::<u32>(proof, tree))
+    } else if let Ok(proof) = StepProof64::from_slice(&bytes) {
+        proof
+            .try_unpack()
+            .map_err(|err| err.to_string())
+            .and_then(|proof| inspect::<u64>(proof, tree))
+    } else {
+        Err("neither molecule StepProof32, StepProof64 nor JSON".to_string())
+    };
+
+    if let Err(err) = ret {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn from_json<R: Register + serde::de::DeserializeOwned>(
+    bytes: &[u8],
+) -> Result<StepProof<R>, String> {
+    serde_json::from_slice(bytes).map_err(|err| format!("invalid JSON proof: {}", err))
+}
+
+fn inspect<R: Register + 'static>(proof: StepProof<R>, tree: Option<Tree>) -> Result<(), String> {
+    // Index keyed tree is detected by its keys
+    let tree = match tree {
+        Some(tree) => tree,
+        None if proof.memory.kvs.is_empty() => {
+            return Err("no touched memory keys to detect tree, pass --shallow or --smt".into())
+        }
+        None if proof
+            .memory
+            .kvs
+            .keys()
+            .all(|k| k[8..].iter().all(|b| *b == 0)) =>
+        {
+            Tree::Shallow
+        }
+        None => Tree::Smt,
+    };
+
+    println!("rv{} step {}", R::BITS, proof.step_num);
+    println!(
+        "pc {:#x} next_pc {:#x}",
+        proof.pc.to_u64(),
+        proof.next_pc.to_u64()
+    );
+    println!(
+        "cycles {} max_cycles {} running {} isa {:#x} version {}",
+        proof.cycles, proof.max_cycles, proof.running, proof.isa, proof.version
+    );
+
+    println!("registers");
+    for (idx, reg) in proof.registers.iter().enumerate() {
+        print!("  {:>4} {:#018x}", REGISTER_NAMES[idx], reg.to_u64());
+        if idx % 4 == 3 {
+            println!();
+        }
+    }
+
+    // Without hints, hashed keys are looked up among keys of every chunk and page
+    let hints: BTreeMap<Bytes32, KeyHint> = if proof.memory.hints.is_empty() {
+        key_hints(proof.memory.memory_size as u64, tree)
+    } else {
+        let keys = proof.memory.kvs.keys().cloned();
+        keys.zip(proof.memory.hints.iter().cloned()).collect()
+    };
+    println!(
+        "memory size {} root {}",
+        proof.memory.memory_size,
+        hex(&proof.memory.root)
+    );
+    println!("touched memory ({} keys)", proof.memory.kvs.len());
+    for (key, value) in proof.memory.kvs.iter() {
+        match hints.get(key) {
+            Some(KeyHint::DataChunk(chunk)) => {
+                println!("  data {:#010x} {}", chunk * DATA_CHUNK_SIZE, hex(value))
+            }
+            Some(KeyHint::Flag(page)) => println!("  flag page {} {:#04x}", page, value.to_u8()),
+            None => println!("  unknown key {} {}", hex(key), hex(value)),
+        }
+    }
+    println!("merkle proof {} bytes", proof.memory.merkle_proof.len());
+
+    for syscall in proof.syscalls.iter() {
+        println!(
+            "syscall {} commitment {}",
+            syscall.number,
+            hex(&syscall.commitment)
+        );
+    }
+    println!("syscall witness {} bytes", proof.syscall_witness.len());
+
+    let ret = match tree {
+        Tree::Shallow => verify::<R, ShallowVerifierSMT>(proof),
+        Tree::Smt => verify::<R, VerifierSMT>(proof),
+    };
+    ret.map_err(|err| format!("verify failed: error {}: {}", err.code(), err))
+}
+
+fn verify<R: Register + 'static, M: SMTRestore + SMTOps + 'static>(
+    proof: StepProof<R>,
+) -> Result<(), Error> {
+    let mut verifier = Verifier::<R, M>::from_proof(proof)?;
+
+    let instruction = verifier.current_instruction()?;
+    match TaggedInstruction::try_from(instruction) {
+        Ok(tagged) => println!("instruction {} {:#x}", tagged, instruction),
+        // Unknown opcode is left for verifier to reject
+        Err(_) => println!(
+            "instruction {} {:#x}",
+            instruction_opcode_name(extract_opcode(instruction)),
+            instruction
+        ),
+    }
+
+    let pre = verifier.commit_step()?;
+    println!(
+        "pre step {} commitment {}",
+        pre.step_num,
+        hex(&pre.commitment)
+    );
+    let post = verifier.execute_next_step()?;
+    println!(
+        "post step {} commitment {}",
+        post.step_num,
+        hex(&post.commitment)
+    );
+
+    Ok(())
+}
+
+type KeyFn = Box<dyn Fn(u64) -> Bytes32>;
+
+fn key_hints(memory_size: u64, tree: Tree) -> BTreeMap<Bytes32, KeyHint> {
+    let chunks = memory_size / DATA_CHUNK_SIZE;
+    let pages = memory_size / ckb_vm::RISCV_PAGESIZE as u64;
+
+    let (data_key, flag_key): (KeyFn, KeyFn) = match tree {
+        Tree::Shallow => (
+            Box::new(Bytes32::from_u64),
+            Box::new(move |page| Bytes32::from_u64(chunks + page)),
+        ),
+        Tree::Smt => (Box::new(hash_data_chunk_key), Box::new(hash_flag_key)),
+    };
+
+    let data = (0..chunks).map(|chunk| (data_key(chunk), KeyHint::DataChunk(chunk)));
+    let flags = (0..pages).map(|page| (flag_key(page), KeyHint::Flag(page)));
+    data.chain(flags).collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", hex)
+}
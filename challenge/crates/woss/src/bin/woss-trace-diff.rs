@@ -0,0 +1,87 @@
+//! Locate the first step where two execution traces differ.
+//!
+//! Usage: woss-trace-diff <trace> <trace>
+//!
+//! Traces are JSON lines written by `woss::trace::JsonLinesTracer`. Exits
+//! with 1 if traces diverge.
+
+use std::{env, fs::File, io::BufReader, process};
+
+use woss::trace::{diff_traces, StepTrace};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: {} <trace> <trace>", args[0]);
+        process::exit(2);
+    }
+
+    let open = |path: &str| match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(err) => {
+            eprintln!("open {}: {}", path, err);
+            process::exit(2);
+        }
+    };
+
+    let divergence = match diff_traces(open(&args[1]), open(&args[2])) {
+        Ok(divergence) => divergence,
+        Err(err) => {
+            eprintln!("read traces: {:?}", err);
+            process::exit(2);
+        }
+    };
+
+    let divergence = match divergence {
+        Some(divergence) => divergence,
+        None => {
+            println!("traces are identical");
+            return;
+        }
+    };
+
+    println!("traces diverge at record {}", divergence.index);
+    match (&divergence.left, &divergence.right) {
+        (Some(left), Some(right)) => print_diff(left, right),
+        (Some(_), None) => println!("{} ends before it", args[2]),
+        (None, Some(_)) => println!("{} ends before it", args[1]),
+        (None, None) => unreachable!("diverged traces"),
+    }
+    process::exit(1);
+}
+
+fn print_diff(left: &StepTrace, right: &StepTrace) {
+    let field = |name: &str, left: String, right: String| {
+        let mark = if left == right { " " } else { "*" };
+        println!("{} {:<16} {:<40} {}", mark, name, left, right);
+    };
+
+    field(
+        "step_num",
+        left.step_num.to_string(),
+        right.step_num.to_string(),
+    );
+    field("pc", format!("{:#x}", left.pc), format!("{:#x}", right.pc));
+    field("opcode", left.opcode.clone(), right.opcode.clone());
+    field(
+        "instruction",
+        format!("{:#x}", left.instruction),
+        format!("{:#x}", right.instruction),
+    );
+    field("cycles", left.cycles.to_string(), right.cycles.to_string());
+    field(
+        "register_writes",
+        format!("{:x?}", left.register_writes),
+        format!("{:x?}", right.register_writes),
+    );
+    field(
+        "memory_reads",
+        format!("{:x?}", left.memory_reads),
+        format!("{:x?}", right.memory_reads),
+    );
+    field(
+        "memory_writes",
+        format!("{:x?}", left.memory_writes),
+        format!("{:x?}", right.memory_writes),
+    );
+}
@@ -0,0 +1,139 @@
+//! Witness size and verifier cycle estimates of step proofs.
+//!
+//! Proofs are submitted as `WitnessArgs.lock` of the step verifier lock, a
+//! large `store_bytes` or syscall witness may push the transaction over CKB
+//! size or cycle limits. Estimates let prover flag or reject such steps
+//! before building a transaction.
+
+use ckb_vm::Register;
+
+use crate::{machine::StepProof, types::conversion::MoleculeSize, Error};
+
+// WitnessArgs table header (total size and 3 offsets) and lock Bytes length
+const WITNESS_ARGS_OVERHEAD: usize = 16 + 4;
+
+/// Estimated cost of submitting a step proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofEstimate {
+    /// Molecule size of `WitnessArgs` carrying the proof in lock field
+    pub witness_size: usize,
+    pub verifier_cycles: u64,
+}
+
+/// Linear model of step verifier lock cycles.
+///
+/// Defaults aren't fitted to measurements, they are rounded up guesses meant
+/// as upper bounds. `test_step_verifier_lock_cycle_model` in contract tests
+/// checks them against lock cycles of sampled steps, run it with
+/// `--nocapture` to print measured and estimated cycles when recalibrating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleModel {
+    /// Script load and machine setup
+    pub base: u64,
+    /// Merkle verification and root update of each proven key
+    pub per_memory_kv: u64,
+    pub per_merkle_proof_byte: u64,
+    /// Parsing and hashing of each witness byte
+    pub per_witness_byte: u64,
+}
+
+impl Default for CycleModel {
+    fn default() -> Self {
+        CycleModel {
+            base: 1_000_000,
+            per_memory_kv: 150_000,
+            per_merkle_proof_byte: 2_000,
+            per_witness_byte: 200,
+        }
+    }
+}
+
+/// Limits of a step proof, unset ones are unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProofBudget {
+    pub max_witness_size: Option<usize>,
+    pub max_verifier_cycles: Option<u64>,
+    pub cycle_model: CycleModel,
+}
+
+impl ProofBudget {
+    pub fn estimate<R: Register>(&self, proof: &StepProof<R>) -> ProofEstimate
+    where
+        StepProof<R>: MoleculeSize,
+    {
+        estimate_proof(proof, &self.cycle_model)
+    }
+
+    pub fn exceeded_by(&self, estimate: &ProofEstimate) -> bool {
+        self.check(estimate).is_err()
+    }
+
+    /// Fails with `BudgetExceeded` if estimate is above either limit
+    pub fn check(&self, estimate: &ProofEstimate) -> Result<(), Error> {
+        let witness_exceeded =
+            matches!(self.max_witness_size, Some(max) if estimate.witness_size > max);
+        let cycles_exceeded =
+            matches!(self.max_verifier_cycles, Some(max) if estimate.verifier_cycles > max);
+        if witness_exceeded || cycles_exceeded {
+            return Err(Error::BudgetExceeded(*estimate));
+        }
+        Ok(())
+    }
+}
+
+pub fn estimate_proof<R: Register>(proof: &StepProof<R>, model: &CycleModel) -> ProofEstimate
+where
+    StepProof<R>: MoleculeSize,
+{
+    let witness_size = WITNESS_ARGS_OVERHEAD + proof.molecule_size();
+
+    let verifier_cycles = model.base
+        + model.per_memory_kv * proof.memory.kvs.len() as u64
+        + model.per_merkle_proof_byte * proof.memory.merkle_proof.len() as u64
+        + model.per_witness_byte * witness_size as u64;
+
+    ProofEstimate {
+        witness_size,
+        verifier_cycles,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use ckb_vm::Bytes;
+
+    use super::ProofBudget;
+    use crate::{machine::Machine, memory::prover::ProverSMT, syscalls::LoadInput};
+
+    const MEMORY_SIZE: usize = 4 << 20;
+
+    #[test]
+    fn test_estimate_proof() {
+        let input = Bytes::from(vec![42u8; 1024]);
+        let program = fs::read("../../../load_input64").unwrap().into();
+        let mut prover = Machine::<u64, ProverSMT>::builder(MEMORY_SIZE)
+            .syscall(Box::new(LoadInput::new(input)))
+            .build()
+            .unwrap();
+        prover.load_program(&program).unwrap();
+        let proof = prover.prove_steps(u64::MAX).unwrap();
+
+        let budget = ProofBudget::default();
+        let estimate = budget.estimate(&proof);
+        assert!(!budget.exceeded_by(&estimate));
+
+        let budget = ProofBudget {
+            max_witness_size: Some(1024),
+            ..Default::default()
+        };
+        assert!(budget.exceeded_by(&estimate));
+
+        let budget = ProofBudget {
+            max_verifier_cycles: Some(estimate.verifier_cycles - 1),
+            ..Default::default()
+        };
+        assert!(budget.exceeded_by(&estimate));
+    }
+}
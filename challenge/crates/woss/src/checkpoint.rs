@@ -0,0 +1,381 @@
+//! Versioned on-disk format of machine checkpoints.
+//!
+//! ```text
+//! "WOSSCKPT" | version u32 | register bits u8
+//!     | checkpoint count u64 | (checkpoint snapshot | commitment)*
+//!     | current snapshot | current commitment
+//!
+//! snapshot: step | registers | pc | next_pc | cycles | max_cycles | running
+//!     | initial | memory root | branch delta | leaf delta
+//!
+//! delta: removed count u64 | key* | updated count u64 | (key | value)*
+//! ```
+//!
+//! Integers are little endian, registers are widened to u64. Memory of each
+//! snapshot is written as delta against the previous one, starting from an
+//! empty tree, so nodes shared by checkpoints are written once.
+
+use std::{
+    hash::Hash,
+    io::{self, Read, Write},
+};
+
+use ckb_vm::{Register, RISCV_GENERAL_REGISTER_NUMBER};
+use im::HashMap;
+use sparse_merkle_tree::{merge::MergeValue, BranchKey, BranchNode, H256};
+
+use crate::{
+    machine::{InitialCommitment, MachineSnapshot, StepCommitment},
+    memory::prover::{ProverSMT, Store},
+    types::Bytes32,
+    Error,
+};
+
+pub const CHECKPOINT_MAGIC: [u8; 8] = *b"WOSSCKPT";
+pub const CHECKPOINT_VERSION: u32 = 3;
+
+const MERGE_VALUE: u8 = 0;
+const MERGE_WITH_ZERO: u8 = 1;
+const SHORT_CUT: u8 = 2;
+
+/// Prover state persisted to resume after restart
+pub struct Checkpoint<R> {
+    pub current: MachineSnapshot<R>,
+    /// Commitment of `current`, checked against the machine it is restored to
+    pub commitment: Bytes32,
+    /// Earlier snapshots, each with its commitment checked the same way
+    pub checkpoints: Vec<(MachineSnapshot<R>, Bytes32)>,
+}
+
+pub fn write_checkpoint<R: Register, W: Write>(
+    writer: &mut W,
+    current: &MachineSnapshot<R>,
+    commitment: &Bytes32,
+    checkpoints: &[(&MachineSnapshot<R>, Bytes32)],
+) -> Result<(), Error> {
+    let mut write = || -> io::Result<()> {
+        writer.write_all(&CHECKPOINT_MAGIC)?;
+        writer.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+        writer.write_all(&[R::BITS])?;
+
+        let mut base = Store::default();
+        write_u64(writer, checkpoints.len() as u64)?;
+        for (snapshot, commitment) in checkpoints {
+            write_snapshot(writer, snapshot, &base)?;
+            writer.write_all(commitment)?;
+            base = snapshot.memory.store().clone();
+        }
+
+        write_snapshot(writer, current, &base)?;
+        writer.write_all(commitment)?;
+
+        writer.flush()
+    };
+
+    Ok(write()?)
+}
+
+pub fn read_checkpoint<R: Register, Rd: Read>(reader: &mut Rd) -> Result<Checkpoint<R>, Error> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if magic != CHECKPOINT_MAGIC {
+        return Err(invalid_data("not a checkpoint file").into());
+    }
+
+    let version = read_u32(reader)?;
+    if version != CHECKPOINT_VERSION {
+        let msg = format!("unsupported checkpoint version {}", version);
+        return Err(invalid_data(&msg).into());
+    }
+
+    let bits = read_u8(reader)?;
+    if bits != R::BITS {
+        let msg = format!("checkpoint register bits {}, expect {}", bits, R::BITS);
+        return Err(invalid_data(&msg).into());
+    }
+
+    let mut read = || -> io::Result<Checkpoint<R>> {
+        let mut base = Store::default();
+        let count = read_u64(reader)?;
+        let mut checkpoints = Vec::new();
+        for _ in 0..count {
+            let snapshot: MachineSnapshot<R> = read_snapshot(reader, &base)?;
+            let commitment = read_bytes32(reader)?;
+            base = snapshot.memory.store().clone();
+            checkpoints.push((snapshot, commitment));
+        }
+
+        let current = read_snapshot(reader, &base)?;
+        let commitment = read_bytes32(reader)?;
+
+        Ok(Checkpoint {
+            current,
+            commitment,
+            checkpoints,
+        })
+    };
+
+    Ok(read()?)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_snapshot<R: Register, W: Write>(
+    writer: &mut W,
+    snapshot: &MachineSnapshot<R>,
+    base: &Store,
+) -> io::Result<()> {
+    write_u64(writer, snapshot.step)?;
+    for reg in snapshot.registers.iter() {
+        write_u64(writer, reg.to_u64())?;
+    }
+    write_u64(writer, snapshot.pc.to_u64())?;
+    write_u64(writer, snapshot.next_pc.to_u64())?;
+    write_u64(writer, snapshot.cycles)?;
+    write_u64(writer, snapshot.max_cycles)?;
+    writer.write_all(&[snapshot.running as u8])?;
+
+    match snapshot.initial {
+        Some(initial) => {
+            writer.write_all(&[1])?;
+            writer.write_all(&initial.program_hash)?;
+            writer.write_all(&initial.args_hash)?;
+            write_u64(writer, initial.step.step_num)?;
+            writer.write_all(&initial.step.commitment)?;
+        }
+        None => writer.write_all(&[0])?,
+    }
+
+    write_smt(writer, &snapshot.memory, base)
+}
+
+fn read_snapshot<R: Register, Rd: Read>(
+    reader: &mut Rd,
+    base: &Store,
+) -> io::Result<MachineSnapshot<R>> {
+    let step = read_u64(reader)?;
+    let mut registers: [R; RISCV_GENERAL_REGISTER_NUMBER] = Default::default();
+    for reg in registers.iter_mut() {
+        *reg = R::from_u64(read_u64(reader)?);
+    }
+    let pc = R::from_u64(read_u64(reader)?);
+    let next_pc = R::from_u64(read_u64(reader)?);
+    let cycles = read_u64(reader)?;
+    let max_cycles = read_u64(reader)?;
+    let running = read_bool(reader)?;
+
+    let initial = match read_bool(reader)? {
+        true => Some(InitialCommitment {
+            program_hash: read_bytes32(reader)?,
+            args_hash: read_bytes32(reader)?,
+            step: StepCommitment {
+                step_num: read_u64(reader)?,
+                commitment: read_bytes32(reader)?,
+            },
+        }),
+        false => None,
+    };
+
+    let memory = read_smt(reader, base)?;
+
+    Ok(MachineSnapshot {
+        step,
+        registers,
+        pc,
+        next_pc,
+        cycles,
+        max_cycles,
+        running,
+        initial,
+        memory,
+    })
+}
+
+fn write_smt<W: Write>(writer: &mut W, smt: &ProverSMT, base: &Store) -> io::Result<()> {
+    writer.write_all(smt.root().as_slice())?;
+
+    let store = smt.store();
+    write_delta(
+        writer,
+        base.branches(),
+        store.branches(),
+        |writer, key| {
+            writer.write_all(&[key.height])?;
+            writer.write_all(key.node_key.as_slice())
+        },
+        |writer, node| {
+            write_merge_value(writer, &node.left)?;
+            write_merge_value(writer, &node.right)
+        },
+    )?;
+    write_delta(
+        writer,
+        base.leaves(),
+        store.leaves(),
+        |writer, key| writer.write_all(key.as_slice()),
+        |writer, leaf| writer.write_all(leaf),
+    )
+}
+
+fn read_smt<Rd: Read>(reader: &mut Rd, base: &Store) -> io::Result<ProverSMT> {
+    let root = read_h256(reader)?;
+
+    let branches = read_delta(
+        reader,
+        base.branches().clone(),
+        |reader| Ok(BranchKey::new(read_u8(reader)?, read_h256(reader)?)),
+        |reader| {
+            let left = read_merge_value(reader)?;
+            let right = read_merge_value(reader)?;
+            Ok(BranchNode { left, right })
+        },
+    )?;
+    let leaves = read_delta(reader, base.leaves().clone(), read_h256, read_bytes32)?;
+
+    Ok(ProverSMT::new(root, Store::new(branches, leaves)))
+}
+
+fn write_delta<K, V, W, WK, WV>(
+    writer: &mut W,
+    base: &HashMap<K, V>,
+    map: &HashMap<K, V>,
+    write_key: WK,
+    write_value: WV,
+) -> io::Result<()>
+where
+    K: Hash + Eq + Clone,
+    V: PartialEq + Clone,
+    W: Write,
+    WK: Fn(&mut W, &K) -> io::Result<()>,
+    WV: Fn(&mut W, &V) -> io::Result<()>,
+{
+    let removed: Vec<&K> = base.keys().filter(|key| !map.contains_key(key)).collect();
+    write_u64(writer, removed.len() as u64)?;
+    for key in removed {
+        write_key(writer, key)?;
+    }
+
+    let updated: Vec<(&K, &V)> = map
+        .iter()
+        .filter(|(key, value)| base.get(key) != Some(value))
+        .collect();
+    write_u64(writer, updated.len() as u64)?;
+    for (key, value) in updated {
+        write_key(writer, key)?;
+        write_value(writer, value)?;
+    }
+
+    Ok(())
+}
+
+fn read_delta<K, V, Rd, RK, RV>(
+    reader: &mut Rd,
+    mut map: HashMap<K, V>,
+    read_key: RK,
+    read_value: RV,
+) -> io::Result<HashMap<K, V>>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    Rd: Read,
+    RK: Fn(&mut Rd) -> io::Result<K>,
+    RV: Fn(&mut Rd) -> io::Result<V>,
+{
+    for _ in 0..read_u64(reader)? {
+        map.remove(&read_key(reader)?);
+    }
+    for _ in 0..read_u64(reader)? {
+        let key = read_key(reader)?;
+        map.insert(key, read_value(reader)?);
+    }
+
+    Ok(map)
+}
+
+fn write_merge_value<W: Write>(writer: &mut W, value: &MergeValue) -> io::Result<()> {
+    match value {
+        MergeValue::Value(v) => {
+            writer.write_all(&[MERGE_VALUE])?;
+            writer.write_all(v.as_slice())
+        }
+        MergeValue::MergeWithZero {
+            base_node,
+            zero_bits,
+            zero_count,
+        } => {
+            writer.write_all(&[MERGE_WITH_ZERO])?;
+            writer.write_all(base_node.as_slice())?;
+            writer.write_all(zero_bits.as_slice())?;
+            writer.write_all(&[*zero_count])
+        }
+        MergeValue::ShortCut { key, value, height } => {
+            writer.write_all(&[SHORT_CUT])?;
+            writer.write_all(key.as_slice())?;
+            writer.write_all(value.as_slice())?;
+            writer.write_all(&[*height])
+        }
+    }
+}
+
+fn read_merge_value<Rd: Read>(reader: &mut Rd) -> io::Result<MergeValue> {
+    let value = match read_u8(reader)? {
+        MERGE_VALUE => MergeValue::Value(read_h256(reader)?),
+        MERGE_WITH_ZERO => MergeValue::MergeWithZero {
+            base_node: read_h256(reader)?,
+            zero_bits: read_h256(reader)?,
+            zero_count: read_u8(reader)?,
+        },
+        SHORT_CUT => MergeValue::ShortCut {
+            key: read_h256(reader)?,
+            value: read_h256(reader)?,
+            height: read_u8(reader)?,
+        },
+        _ => return Err(invalid_data("unknown merge value")),
+    };
+
+    Ok(value)
+}
+
+fn write_u64<W: Write>(writer: &mut W, val: u64) -> io::Result<()> {
+    writer.write_all(&val.to_le_bytes())
+}
+
+fn read_u8<Rd: Read>(reader: &mut Rd) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_bool<Rd: Read>(reader: &mut Rd) -> io::Result<bool> {
+    match read_u8(reader)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(invalid_data("invalid bool")),
+    }
+}
+
+fn read_u32<Rd: Read>(reader: &mut Rd) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<Rd: Read>(reader: &mut Rd) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_bytes32<Rd: Read>(reader: &mut Rd) -> io::Result<Bytes32> {
+    let mut buf = [0u8; 32];
+    reader.read_exact(&mut buf)?;
+    Ok(buf.into())
+}
+
+fn read_h256<Rd: Read>(reader: &mut Rd) -> io::Result<H256> {
+    let mut buf = [0u8; 32];
+    reader.read_exact(&mut buf)?;
+    Ok(buf.into())
+}
@@ -0,0 +1,447 @@
+//! Compact binary encoding of `StepProof`.
+//!
+//! Integers are LEB128 varints. Zero registers are omitted behind a bitmap,
+//! memory keys from an index keyed tree are encoded as their index, and
+//! memory values drop trailing zero bytes, so untouched or mostly-zero chunks
+//! cost a byte or two instead of 32.
+//!
+//! ```text
+//! version u8 | step_num | register bits u8 | register bitmap u32 | register*
+//!     | pc | next_pc | cycles | max_cycles | running u8 | isa u8 | version
+//!     | memory_size | root | kv count | (key | value)* | merkle_proof
+//!     | hint count | (kind u8 | index)* | syscall count
+//!     | (number | commitment)* | syscall_witness
+//!
+//! key: 0 | 32 bytes, or 1 | index
+//! kind: 0 data chunk, 1 page flag
+//! value: len u8 | bytes without trailing zeros
+//! bytes: len | bytes
+//! ```
+//!
+//! Decoding rejects anything the encoder wouldn't write, such as overlong
+//! varints or duplicate keys, so a proof has exactly one encoding.
+
+use ckb_vm::{Register, RISCV_GENERAL_REGISTER_NUMBER};
+
+use crate::{
+    collections::BTreeMap,
+    machine::StepProof,
+    memory::{KeyHint, MemoryProof},
+    syscalls::SyscallCommitment,
+    types::{conversion::MoleculeSize, Bytes32},
+    vec::Vec,
+    Error,
+};
+
+pub const COMPACT_CODEC_VERSION: u8 = 2;
+
+const FULL_KEY: u8 = 0;
+const INDEX_KEY: u8 = 1;
+
+const DATA_CHUNK_HINT: u8 = 0;
+const FLAG_HINT: u8 = 1;
+
+/// Encoded sizes of a step proof, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofSizeReport {
+    pub molecule: usize,
+    pub compact: usize,
+    /// Parts of compact encoding
+    pub registers: usize,
+    pub memory_kvs: usize,
+    pub merkle_proof: usize,
+    pub syscall_witness: usize,
+}
+
+pub fn size_report<R: Register>(proof: &StepProof<R>) -> ProofSizeReport
+where
+    StepProof<R>: MoleculeSize,
+{
+    let part = |encode: &dyn Fn(&mut Vec<u8>)| {
+        let mut buf = Vec::new();
+        encode(&mut buf);
+        buf.len()
+    };
+
+    ProofSizeReport {
+        molecule: proof.molecule_size(),
+        compact: encode_step_proof(proof).len(),
+        registers: part(&|buf| write_registers(buf, &proof.registers)),
+        memory_kvs: part(&|buf| write_kvs(buf, &proof.memory.kvs)),
+        merkle_proof: part(&|buf| write_bytes(buf, &proof.memory.merkle_proof)),
+        syscall_witness: part(&|buf| write_bytes(buf, &proof.syscall_witness)),
+    }
+}
+
+pub fn encode_step_proof<R: Register>(proof: &StepProof<R>) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    buf.push(COMPACT_CODEC_VERSION);
+    write_varint(&mut buf, proof.step_num);
+    buf.push(R::BITS);
+    write_registers(&mut buf, &proof.registers);
+    write_varint(&mut buf, proof.pc.to_u64());
+    write_varint(&mut buf, proof.next_pc.to_u64());
+    write_varint(&mut buf, proof.cycles);
+    write_varint(&mut buf, proof.max_cycles);
+    buf.push(u8::from(proof.running));
+    buf.push(proof.isa);
+    write_varint(&mut buf, proof.version as u64);
+
+    write_varint(&mut buf, proof.memory.memory_size as u64);
+    buf.extend_from_slice(&proof.memory.root);
+    write_kvs(&mut buf, &proof.memory.kvs);
+    write_bytes(&mut buf, &proof.memory.merkle_proof);
+    write_hints(&mut buf, &proof.memory.hints);
+
+    write_varint(&mut buf, proof.syscalls.len() as u64);
+    for syscall in proof.syscalls.iter() {
+        write_varint(&mut buf, syscall.number);
+        buf.extend_from_slice(&syscall.commitment);
+    }
+    write_bytes(&mut buf, &proof.syscall_witness);
+
+    buf
+}
+
+pub fn decode_step_proof<R: Register>(bytes: &[u8]) -> Result<StepProof<R>, Error> {
+    let mut reader = CompactReader { bytes };
+
+    let version = reader.read_u8()?;
+    if version != COMPACT_CODEC_VERSION {
+        return Err(Error::Decode);
+    }
+
+    let step_num = reader.read_varint()?;
+    let bits = reader.read_u8()?;
+    if bits != R::BITS {
+        return Err(Error::Decode);
+    }
+    let registers = reader.read_registers()?;
+    let pc = R::from_u64(reader.read_varint()?);
+    let next_pc = R::from_u64(reader.read_varint()?);
+    let cycles = reader.read_varint()?;
+    let max_cycles = reader.read_varint()?;
+    let running = match reader.read_u8()? {
+        0 => false,
+        1 => true,
+        _ => return Err(Error::Decode),
+    };
+    let isa = reader.read_u8()?;
+    let version = u32::try_from(reader.read_varint()?).map_err(|_| Error::Decode)?;
+
+    let memory_size = usize::try_from(reader.read_varint()?).map_err(|_| Error::Decode)?;
+    let root = reader.read_bytes32()?;
+    let kvs = reader.read_kvs()?;
+    let merkle_proof = reader.read_bytes()?;
+    let hints = reader.read_hints()?;
+
+    let mut syscalls = Vec::new();
+    for _ in 0..reader.read_varint()? {
+        syscalls.push(SyscallCommitment {
+            number: reader.read_varint()?,
+            commitment: reader.read_bytes32()?,
+        });
+    }
+    let syscall_witness = reader.read_bytes()?;
+
+    if !reader.bytes.is_empty() {
+        return Err(Error::Decode);
+    }
+
+    let proof = StepProof {
+        step_num,
+        registers,
+        pc,
+        next_pc,
+        memory: MemoryProof {
+            memory_size,
+            root,
+            kvs,
+            merkle_proof,
+            hints,
+        },
+        cycles,
+        max_cycles,
+        running,
+        isa,
+        version,
+        syscalls,
+        syscall_witness,
+    };
+
+    Ok(proof)
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut val: u64) {
+    while val >= 0x80 {
+        buf.push((val as u8) | 0x80);
+        val >>= 7;
+    }
+    buf.push(val as u8);
+}
+
+fn write_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn write_registers<R: Register>(buf: &mut Vec<u8>, registers: &[R]) {
+    let mut bitmap = 0u32;
+    for (idx, reg) in registers.iter().enumerate() {
+        if reg.to_u64() != 0 {
+            bitmap |= 1 << idx;
+        }
+    }
+
+    buf.extend_from_slice(&bitmap.to_le_bytes());
+    for reg in registers.iter().filter(|r| r.to_u64() != 0) {
+        write_varint(buf, reg.to_u64());
+    }
+}
+
+fn write_kvs(buf: &mut Vec<u8>, kvs: &BTreeMap<Bytes32, Bytes32>) {
+    write_varint(buf, kvs.len() as u64);
+    for (key, value) in kvs.iter() {
+        if key[8..].iter().all(|b| *b == 0) {
+            buf.push(INDEX_KEY);
+            write_varint(buf, key.to_u64());
+        } else {
+            buf.push(FULL_KEY);
+            buf.extend_from_slice(key);
+        }
+
+        let len = value.iter().rposition(|b| *b != 0).map_or(0, |pos| pos + 1);
+        buf.push(len as u8);
+        buf.extend_from_slice(&value[..len]);
+    }
+}
+
+fn write_hints(buf: &mut Vec<u8>, hints: &[KeyHint]) {
+    write_varint(buf, hints.len() as u64);
+    for hint in hints.iter() {
+        let (kind, index) = match *hint {
+            KeyHint::DataChunk(chunk) => (DATA_CHUNK_HINT, chunk),
+            KeyHint::Flag(page) => (FLAG_HINT, page),
+        };
+        buf.push(kind);
+        write_varint(buf, index);
+    }
+}
+
+struct CompactReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> CompactReader<'a> {
+    fn read_slice(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() < len {
+            return Err(Error::Decode);
+        }
+        let (slice, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    fn read_varint(&mut self) -> Result<u64, Error> {
+        let mut val = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.read_u8()?;
+            // Only the top bit of u64 is left for the 10th byte
+            if shift == 63 && b > 1 {
+                return Err(Error::Decode);
+            }
+            val |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                // Trailing zero byte is overlong
+                if b == 0 && shift > 0 {
+                    return Err(Error::Decode);
+                }
+                return Ok(val);
+            }
+        }
+        Err(Error::Decode)
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let len = usize::try_from(self.read_varint()?).map_err(|_| Error::Decode)?;
+        Ok(self.read_slice(len)?.to_vec())
+    }
+
+    fn read_bytes32(&mut self) -> Result<Bytes32, Error> {
+        let mut buf = [0u8; 32];
+        buf.copy_from_slice(self.read_slice(32)?);
+        Ok(buf.into())
+    }
+
+    fn read_registers<R: Register>(&mut self) -> Result<[R; RISCV_GENERAL_REGISTER_NUMBER], Error> {
+        let mut bitmap = [0u8; 4];
+        bitmap.copy_from_slice(self.read_slice(4)?);
+        let bitmap = u32::from_le_bytes(bitmap);
+
+        let mut registers: [R; RISCV_GENERAL_REGISTER_NUMBER] = Default::default();
+        for (idx, reg) in registers.iter_mut().enumerate() {
+            if bitmap & (1 << idx) != 0 {
+                let val = self.read_varint()?;
+                if val == 0 {
+                    return Err(Error::Decode);
+                }
+                *reg = R::from_u64(val);
+            }
+        }
+        Ok(registers)
+    }
+
+    fn read_kvs(&mut self) -> Result<BTreeMap<Bytes32, Bytes32>, Error> {
+        let mut kvs = BTreeMap::new();
+        for _ in 0..self.read_varint()? {
+            let key = match self.read_u8()? {
+                FULL_KEY => {
+                    let key = self.read_bytes32()?;
+                    if key[8..].iter().all(|b| *b == 0) {
+                        return Err(Error::Decode);
+                    }
+                    key
+                }
+                INDEX_KEY => Bytes32::from_u64(self.read_varint()?),
+                _ => return Err(Error::Decode),
+            };
+
+            let len = self.read_u8()? as usize;
+            if len > 32 {
+                return Err(Error::Decode);
+            }
+            let mut value = [0u8; 32];
+            value[..len].copy_from_slice(self.read_slice(len)?);
+            if len > 0 && value[len - 1] == 0 {
+                return Err(Error::Decode);
+            }
+
+            if kvs.insert(key, value.into()).is_some() {
+                return Err(Error::Decode);
+            }
+        }
+        Ok(kvs)
+    }
+
+    fn read_hints(&mut self) -> Result<Vec<KeyHint>, Error> {
+        let mut hints = Vec::new();
+        for _ in 0..self.read_varint()? {
+            let hint = match self.read_u8()? {
+                DATA_CHUNK_HINT => KeyHint::DataChunk(self.read_varint()?),
+                FLAG_HINT => KeyHint::Flag(self.read_varint()?),
+                _ => return Err(Error::Decode),
+            };
+            hints.push(hint);
+        }
+        Ok(hints)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use ckb_vm::Bytes;
+
+    use super::{
+        decode_step_proof, encode_step_proof, size_report, CompactReader, FULL_KEY, INDEX_KEY,
+    };
+    use crate::{
+        error::Error,
+        machine::Machine,
+        memory::{prover::ProverSMT, shallow::ShallowProverSMT},
+        syscalls::LoadInput,
+        types::{
+            conversion::{Entity, Pack},
+            packed::{StepProof32, StepProof64},
+        },
+    };
+
+    const MEMORY_SIZE: usize = 4 << 20;
+
+    fn program(name: &str) -> Bytes {
+        fs::read(format!("../../../{}", name)).unwrap().into()
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        let mut prover = Machine::<u32, ProverSMT>::new(MEMORY_SIZE);
+        prover.load_program(&program("simple")).unwrap();
+        for _ in 0..50 {
+            let proof = prover.prove_next_step().unwrap();
+            let encoded = encode_step_proof(&proof);
+            let decoded = decode_step_proof::<u32>(&encoded).unwrap();
+            assert_eq!(
+                Pack::<StepProof32>::pack(&decoded).as_slice(),
+                Pack::<StepProof32>::pack(&proof).as_slice()
+            );
+
+            let report = size_report(&proof);
+            assert_eq!(report.compact, encoded.len());
+            assert!(report.compact < report.molecule);
+
+            // Truncated or mismatched register bits
+            assert!(decode_step_proof::<u32>(&encoded[..encoded.len() - 1]).is_err());
+            assert!(decode_step_proof::<u64>(&encoded).is_err());
+        }
+
+        // Index keys and syscall witness
+        let input = Bytes::from_static(b"*woss load input");
+        let mut prover = Machine::<u64, ShallowProverSMT>::builder(MEMORY_SIZE)
+            .syscall(Box::new(LoadInput::new(input)))
+            .build()
+            .unwrap();
+        prover.load_program(&program("load_input64")).unwrap();
+        let proof = prover.prove_steps(u64::MAX).unwrap();
+        let encoded = encode_step_proof(&proof);
+        let decoded = decode_step_proof::<u64>(&encoded).unwrap();
+        assert_eq!(
+            Pack::<StepProof64>::pack(&decoded).as_slice(),
+            Pack::<StepProof64>::pack(&proof).as_slice()
+        );
+
+        let report = size_report(&proof);
+        assert!(report.memory_kvs < proof.memory.kvs.len() * 64);
+    }
+
+    #[test]
+    fn test_compact_non_canonical() {
+        let read_varint = |bytes: &[u8]| CompactReader { bytes }.read_varint();
+        assert_eq!(read_varint(&[0]), Ok(0));
+        assert_eq!(read_varint(&[0x80, 0x01]), Ok(0x80));
+        let mut max = vec![0xff; 9];
+        max.push(0x01);
+        assert_eq!(read_varint(&max), Ok(u64::MAX));
+
+        // Overlong, or bits beyond u64
+        assert_eq!(read_varint(&[0x80, 0x00]), Err(Error::Decode));
+        assert_eq!(read_varint(&[0x81, 0x80, 0x00]), Err(Error::Decode));
+        *max.last_mut().unwrap() = 0x02;
+        assert_eq!(read_varint(&max), Err(Error::Decode));
+
+        // Same key twice
+        let kv = [INDEX_KEY, 5, 1, 42];
+        let bytes = [&[2][..], &kv, &kv].concat();
+        assert_eq!(
+            CompactReader { bytes: &bytes }.read_kvs(),
+            Err(Error::Decode)
+        );
+
+        // Value with trailing zero, and full key fitting an index
+        let bytes = [1, INDEX_KEY, 5, 2, 42, 0];
+        assert_eq!(
+            CompactReader { bytes: &bytes }.read_kvs(),
+            Err(Error::Decode)
+        );
+        let bytes = [&[1, FULL_KEY][..], &[7; 8], &[0; 24], &[0]].concat();
+        assert_eq!(
+            CompactReader { bytes: &bytes }.read_kvs(),
+            Err(Error::Decode)
+        );
+    }
+}
@@ -1,13 +1,160 @@
 use blake2b_ref::{Blake2b, Blake2bBuilder};
+#[cfg(feature = "sha256")]
+use sha2::{Digest, Sha256};
+use sparse_merkle_tree::{traits::Hasher, H256};
 
-pub fn blake2b_hasher() -> Blake2b {
-    Blake2bBuilder::new(32).personal(b"woss").build()
+cfg_if::cfg_if! {
+    if #[cfg(feature = "sha256")] {
+        /// Hasher of machine commitments, selected by cargo feature
+        pub type CommitHasher = Sha256Hasher;
+        /// Hasher of sparse merkle memory tree, selected by cargo feature
+        pub type MemoryHasher = SMTHasher<Sha256Hasher>;
+    } else {
+        /// Hasher of machine commitments, selected by cargo feature
+        pub type CommitHasher = Blake2bHasher;
+        /// Hasher of sparse merkle memory tree, selected by cargo feature
+        pub type MemoryHasher = SMTHasher<CkbBlake2bHasher>;
+    }
 }
 
-pub fn blake2b<'a>(data: impl IntoIterator<Item = &'a [u8]>) -> [u8; 32] {
-    let mut buf = [0u8; 32];
-    let mut blake2b = Blake2bBuilder::new(32).personal(b"woss").build();
-    data.into_iter().for_each(|d| blake2b.update(d));
-    blake2b.finalize(&mut buf);
-    buf
+/// Hash function producing 32 bytes digest for commitments
+pub trait CommitmentHasher: Sized {
+    fn new() -> Self;
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> [u8; 32];
+
+    fn hash<'a>(data: impl IntoIterator<Item = &'a [u8]>) -> [u8; 32] {
+        let mut hasher = Self::new();
+        data.into_iter().for_each(|d| hasher.update(d));
+        hasher.finalize()
+    }
+}
+
+/// Blake2b with personal `woss`
+pub struct Blake2bHasher(Blake2b);
+
+impl CommitmentHasher for Blake2bHasher {
+    fn new() -> Self {
+        Blake2bHasher(Blake2bBuilder::new(32).personal(b"woss").build())
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finalize(self) -> [u8; 32] {
+        let mut buf = [0u8; 32];
+        self.0.finalize(&mut buf);
+        buf
+    }
+}
+
+/// Plain sha256, for verifiers on chains which provide it natively
+#[cfg(feature = "sha256")]
+pub struct Sha256Hasher(Sha256);
+
+#[cfg(feature = "sha256")]
+impl CommitmentHasher for Sha256Hasher {
+    fn new() -> Self {
+        Sha256Hasher(Sha256::new())
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finalize(self) -> [u8; 32] {
+        self.0.finalize().into()
+    }
+}
+
+/// Blake2b with personal `ckb-default-hash`, hardcoded by `ckb_smt.c` which
+/// calculates memory root in `VerifierSMT` without `sha256` feature
+pub struct CkbBlake2bHasher(Blake2b);
+
+impl CommitmentHasher for CkbBlake2bHasher {
+    fn new() -> Self {
+        // Must match the personal compiled into `ckb_smt.c`, which verifies
+        // by default. Under `sha256` the rust_smt verifier hashes with plain
+        // sha256 and this hasher is unused.
+        let blake2b = Blake2bBuilder::new(32)
+            .personal(b"ckb-default-hash")
+            .build();
+        CkbBlake2bHasher(blake2b)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finalize(self) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        self.0.finalize(&mut hash);
+        hash
+    }
+}
+
+/// Adapts a `CommitmentHasher` to the sparse merkle tree
+pub struct SMTHasher<H>(H);
+
+impl<H: CommitmentHasher> Default for SMTHasher<H> {
+    fn default() -> Self {
+        SMTHasher(H::new())
+    }
+}
+
+impl<H: CommitmentHasher> Hasher for SMTHasher<H> {
+    fn write_byte(&mut self, b: u8) {
+        self.0.update(&[b][..]);
+    }
+    fn write_h256(&mut self, h: &H256) {
+        self.0.update(h.as_slice());
+    }
+    fn finish(self) -> H256 {
+        self.0.finalize().into()
+    }
+}
+
+/// Version of commitment encoding. Bump it on any change to what or how a
+/// commitment hashes, so commitments of different schemes never collide.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u8)]
+pub enum CommitmentScheme {
+    V1 = 1,
+    /// `LoadInput` commits to chunk tree root of input instead of input
+    V2 = 2,
+}
+
+impl CommitmentScheme {
+    pub const CURRENT: CommitmentScheme = CommitmentScheme::V2;
+
+    pub fn version(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Commitment hasher, fed with scheme version first
+pub fn hasher() -> CommitHasher {
+    let mut hasher = CommitHasher::new();
+    hasher.update(&[CommitmentScheme::CURRENT.version()]);
+    hasher
+}
+
+pub fn hash<'a>(data: impl IntoIterator<Item = &'a [u8]>) -> [u8; 32] {
+    let mut hasher = hasher();
+    data.into_iter().for_each(|d| hasher.update(d));
+    hasher.finalize()
+}
+
+#[cfg(all(test, feature = "sha256"))]
+mod tests {
+    use super::{CommitmentHasher, Sha256Hasher};
+
+    #[test]
+    fn test_sha256() {
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let digest = Sha256Hasher::hash([b"a".as_slice(), b"bc"]);
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, expected);
+    }
 }
@@ -1,53 +1,606 @@
+use crate::Error;
+
 use crate::machine::StepCommitment;
+use crate::store::StepCommitmentStore;
 use crate::vec::Vec;
 
-const MAX_STEP_CHUNKS: usize = 40;
+pub const MAX_STEP_CHUNKS: usize = 40;
 
-pub struct StepDiffFinder {
-    step_commitments: Vec<StepCommitment>,
+pub struct StepDiffFinder<S = Vec<StepCommitment>> {
+    store: S,
 }
 
-impl StepDiffFinder {
-    pub fn new(step_commitments: Vec<StepCommitment>) -> Self {
-        Self { step_commitments }
+impl<S: StepCommitmentStore> StepDiffFinder<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
     }
 
-    pub fn diff_step_range<'a, 'b: 'a>(
-        &'a self,
-        step_commitments: &'b [StepCommitment],
-    ) -> (&'a StepCommitment, &'a StepCommitment) {
-        let (idx, first_diff_step) = step_commitments
-            .iter()
-            .enumerate()
-            .find(|(_idx, sc)| self.step_commitments.binary_search(sc).is_err())
-            .unwrap();
-        if step_commitments.len() < MAX_STEP_CHUNKS {
-            return (first_diff_step, first_diff_step);
+    /// Returns last agreed step and first disagreed step in step commitments
+    /// published by the other party
+    pub fn diff_step_range<'a>(
+        &mut self,
+        step_commitments: &'a [StepCommitment],
+    ) -> Result<(&'a StepCommitment, &'a StepCommitment), DissectionError> {
+        let (first, last) = match (step_commitments.first(), step_commitments.last()) {
+            (Some(first), Some(last)) if step_commitments.len() >= 2 => (first, last),
+            _ => {
+                return Err(DissectionError::ChunkCountMismatch {
+                    expected: 2,
+                    actual: step_commitments.len(),
+                })
+            }
+        };
+        if !is_valid_steps(step_commitments) {
+            return Err(DissectionError::NonMonotonic);
+        }
+
+        let expected = expected_chunks(first.step_num, last.step_num);
+        if step_commitments.len() as u64 != expected {
+            return Err(DissectionError::ChunkCountMismatch {
+                expected: expected as usize,
+                actual: step_commitments.len(),
+            });
         }
 
-        (&step_commitments[idx - 1], first_diff_step)
+        let mut first_diff = None;
+        for (idx, sc) in step_commitments.iter().enumerate() {
+            if self.store.get(sc.step_num)? != Some(*sc) {
+                first_diff = Some((idx, sc));
+                break;
+            }
+        }
+
+        let (idx, first_diff_step) = first_diff.ok_or(DissectionError::NoDifference)?;
+        if idx == 0 {
+            return Err(DissectionError::FirstStepDiffers(first_diff_step.step_num));
+        }
+
+        Ok((&step_commitments[idx - 1], first_diff_step))
     }
 
-    pub fn step_range(&self, start: usize, end: usize) -> Vec<StepCommitment> {
-        assert!((start < end) && (end < self.step_commitments.len()));
+    pub fn step_range(
+        &mut self,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<StepCommitment>, DissectionError> {
+        let out_of_range = DissectionError::OutOfRange { start, end };
+        match self.store.last_step_num() {
+            Some(last) if start < end && (end as u64) <= last => (),
+            _ => return Err(out_of_range),
+        }
+
+        // Spread chunks evenly, keep both ends and step numbers strictly increasing
+        let chunks = expected_chunks(start as u64, end as u64) as usize;
+        let mut range = Vec::with_capacity(chunks);
+        for idx in 0..chunks {
+            let position = start + (end - start) * idx / (chunks - 1);
+            let step = self.store.get(position as u64)?;
+            range.push(step.ok_or_else(|| out_of_range.clone())?);
+        }
+
+        Ok(range)
+    }
 
-        let chunks = if end - start + 1 < MAX_STEP_CHUNKS {
-            end - start + 1
+    /// Move responding to step commitments published by the other party
+    pub fn next_move(
+        &mut self,
+        step_commitments: &[StepCommitment],
+    ) -> Result<Move, DissectionError> {
+        let (start, end) = self.diff_step_range(step_commitments)?;
+        let steps = if end.step_num - start.step_num == 1 {
+            Vec::new()
         } else {
-            MAX_STEP_CHUNKS
+            self.step_range(start.step_num as usize, end.step_num as usize)?
         };
 
-        let mut position = start;
-        let chunk_len = (end - start) / chunks + 1;
-        let mut range = Vec::with_capacity(chunks);
-        for idx in 0..chunks {
-            if idx == chunks - 1 {
-                position = end;
+        let mv = Move {
+            start: *start,
+            end: *end,
+            steps,
+        };
+        Ok(mv)
+    }
+}
+
+fn expected_chunks(start: u64, end: u64) -> u64 {
+    (end - start).saturating_add(1).min(MAX_STEP_CHUNKS as u64)
+}
+
+fn check_chunks(steps: &[StepCommitment], start: u64, end: u64) -> Result<(), DissectionError> {
+    let expected = expected_chunks(start, end) as usize;
+    if steps.len() != expected {
+        return Err(DissectionError::ChunkCountMismatch {
+            expected,
+            actual: steps.len(),
+        });
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Party {
+    Producer,
+    Challenger,
+}
+
+impl Party {
+    pub fn opponent(&self) -> Party {
+        match self {
+            Party::Producer => Party::Challenger,
+            Party::Challenger => Party::Producer,
+        }
+    }
+}
+
+/// Response to step commitments published in previous round
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Move {
+    /// Last agreed step in previous round
+    pub start: StepCommitment,
+    /// First disagreed step in previous round, must follow `start`
+    pub end: StepCommitment,
+    /// Mover's own step commitments from `start` to `end`, empty if `end` is
+    /// the step right after `start`
+    pub steps: Vec<StepCommitment>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DissectionState {
+    /// Waiting for `turn` to move before `deadline`
+    InProgress { turn: Party, deadline: u64 },
+    /// Dissection is narrowed to a single step. Run until `agreed.step_num`,
+    /// then `prove_next_step` to settle `disputed`
+    Resolved {
+        agreed: StepCommitment,
+        disputed: StepCommitment,
+    },
+    /// `loser` didn't move before deadline
+    Forfeited { loser: Party },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DissectionError {
+    /// Dissection is resolved or forfeited
+    Finished,
+    NotYourTurn(Party),
+    /// Deadline passed, mover forfeits
+    Timeout {
+        deadline: u64,
+        now: u64,
+    },
+    /// Opening step commitments don't start from agreed initial step
+    InvalidOpening,
+    /// `start` and `end` aren't adjacent step commitments in previous round
+    RangeMismatch,
+    /// Step commitments don't start from `start`, or don't end at `end` step
+    /// with a different commitment
+    EndpointsMismatch,
+    /// Step commitments are too few, not strictly increasing, or given for
+    /// adjacent steps
+    InvalidSteps,
+    /// Every step commitment matches
+    NoDifference,
+    /// First step commitment differs, it should have been agreed before
+    FirstStepDiffers(u64),
+    /// Step numbers aren't strictly increasing
+    NonMonotonic,
+    /// Requested step range is empty or beyond known steps
+    OutOfRange {
+        start: usize,
+        end: usize,
+    },
+    /// Number of step commitments doesn't match their step range
+    ChunkCountMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    /// Failed to query step commitment store
+    Store(Error),
+}
+
+impl From<Error> for DissectionError {
+    fn from(err: Error) -> Self {
+        DissectionError::Store(err)
+    }
+}
+
+/// Interactive bisection between producer and challenger.
+///
+/// Producer opens with step commitments from the agreed initial step to the
+/// final step. Then parties take turns: pick the last agreed step and the first
+/// disagreed step from the other party's commitments, and publish own
+/// commitments in between. Dissection is resolved once the picked steps are
+/// adjacent. A party who doesn't move in time forfeits.
+pub struct Dissection {
+    round: u64,
+    timeout: u64,
+    steps: Vec<StepCommitment>,
+    state: DissectionState,
+}
+
+impl Dissection {
+    pub fn new(
+        initial: StepCommitment,
+        opening: Vec<StepCommitment>,
+        now: u64,
+        timeout: u64,
+    ) -> Result<Self, DissectionError> {
+        if opening.first() != Some(&initial) {
+            return Err(DissectionError::InvalidOpening);
+        }
+        if opening.len() < 2 || !is_valid_steps(&opening) {
+            return Err(DissectionError::InvalidSteps);
+        }
+        let last = opening[opening.len() - 1].step_num;
+        check_chunks(&opening, initial.step_num, last)?;
+
+        let dissection = Dissection {
+            round: 0,
+            timeout,
+            steps: opening,
+            state: DissectionState::InProgress {
+                turn: Party::Challenger,
+                deadline: now.saturating_add(timeout),
+            },
+        };
+
+        Ok(dissection)
+    }
+
+    pub fn round(&self) -> u64 {
+        self.round
+    }
+
+    /// Step commitments published in last round
+    pub fn steps(&self) -> &[StepCommitment] {
+        &self.steps
+    }
+
+    pub fn state(&self) -> &DissectionState {
+        &self.state
+    }
+
+    pub fn turn(&self) -> Option<Party> {
+        match self.state {
+            DissectionState::InProgress { turn, .. } => Some(turn),
+            _ => None,
+        }
+    }
+
+    /// Step to prove with `prove_next_step` once resolved
+    pub fn step_to_prove(&self) -> Option<u64> {
+        match self.state {
+            DissectionState::Resolved { agreed, .. } => Some(agreed.step_num),
+            _ => None,
+        }
+    }
+
+    pub fn check_timeout(&mut self, now: u64) -> &DissectionState {
+        if let DissectionState::InProgress { turn, deadline } = self.state {
+            if now > deadline {
+                self.state = DissectionState::Forfeited { loser: turn };
             }
-            range.push(self.step_commitments[position]);
-            position += chunk_len;
         }
 
-        range
+        &self.state
+    }
+
+    pub fn submit(
+        &mut self,
+        party: Party,
+        mv: Move,
+        now: u64,
+    ) -> Result<&DissectionState, DissectionError> {
+        let (turn, deadline) = match self.state {
+            DissectionState::InProgress { turn, deadline } => (turn, deadline),
+            _ => return Err(DissectionError::Finished),
+        };
+        if party != turn {
+            return Err(DissectionError::NotYourTurn(party));
+        }
+        if now > deadline {
+            self.state = DissectionState::Forfeited { loser: turn };
+            return Err(DissectionError::Timeout { deadline, now });
+        }
+
+        let is_adjacent = self
+            .steps
+            .windows(2)
+            .any(|w| w[0] == mv.start && w[1] == mv.end);
+        if !is_adjacent {
+            return Err(DissectionError::RangeMismatch);
+        }
+
+        if mv.end.step_num - mv.start.step_num == 1 {
+            if !mv.steps.is_empty() {
+                return Err(DissectionError::InvalidSteps);
+            }
+
+            self.round += 1;
+            self.state = DissectionState::Resolved {
+                agreed: mv.start,
+                disputed: mv.end,
+            };
+            return Ok(&self.state);
+        }
+
+        // Same chunk count as `StepDiffFinder` expects, so opponent can
+        // always respond
+        check_chunks(&mv.steps, mv.start.step_num, mv.end.step_num)?;
+        let (first, last) = (&mv.steps[0], &mv.steps[mv.steps.len() - 1]);
+        if *first != mv.start
+            || last.step_num != mv.end.step_num
+            || last.commitment == mv.end.commitment
+        {
+            return Err(DissectionError::EndpointsMismatch);
+        }
+        if !is_valid_steps(&mv.steps) {
+            return Err(DissectionError::InvalidSteps);
+        }
+
+        self.round += 1;
+        self.steps = mv.steps;
+        self.state = DissectionState::InProgress {
+            turn: turn.opponent(),
+            deadline: now.saturating_add(self.timeout),
+        };
+
+        Ok(&self.state)
+    }
+}
+
+fn is_valid_steps(steps: &[StepCommitment]) -> bool {
+    steps.windows(2).all(|w| w[0].step_num < w[1].step_num)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Dissection, DissectionError, DissectionState, Move, Party, StepDiffFinder, MAX_STEP_CHUNKS,
+    };
+    use crate::{machine::StepCommitment, types::Bytes32};
+
+    const TIMEOUT: u64 = 10;
+
+    fn steps(count: u64, diff_from: u64) -> Vec<StepCommitment> {
+        (0..=count)
+            .map(|step_num| {
+                let forged = u64::from(step_num >= diff_from);
+                StepCommitment {
+                    step_num,
+                    commitment: Bytes32::from_u64((step_num << 1) | forged),
+                }
+            })
+            .collect()
+    }
+
+    fn play(step_count: u64, diff_step: u64) -> Dissection {
+        let mut producer = StepDiffFinder::new(steps(step_count, diff_step));
+        let mut challenger = StepDiffFinder::new(steps(step_count, u64::MAX));
+
+        let opening = producer.step_range(0, step_count as usize).unwrap();
+        let mut dissection = Dissection::new(opening[0], opening, 0, TIMEOUT).unwrap();
+
+        let mut now = 0;
+        while let Some(turn) = dissection.turn() {
+            let finder = match turn {
+                Party::Producer => &mut producer,
+                Party::Challenger => &mut challenger,
+            };
+
+            now += 1;
+            let mv = finder.next_move(dissection.steps()).unwrap();
+            assert!(mv.steps.len() <= MAX_STEP_CHUNKS);
+            dissection.submit(turn, mv, now).unwrap();
+        }
+
+        dissection
+    }
+
+    #[test]
+    fn test_dissection_resolved() {
+        for (step_count, diff_step) in [(1, 1), (2, 1), (2, 2), (40, 20), (100, 99), (12345, 678)] {
+            let dissection = play(step_count, diff_step);
+            assert_eq!(dissection.step_to_prove(), Some(diff_step - 1));
+            match dissection.state() {
+                DissectionState::Resolved { agreed, disputed } => {
+                    assert_eq!(agreed.step_num + 1, disputed.step_num);
+                    assert_eq!(disputed.step_num, diff_step);
+                }
+                state => panic!("unexpected state {:?}", state),
+            }
+        }
+    }
+
+    #[test]
+    fn test_step_range_strictly_increasing() {
+        let mut finder = StepDiffFinder::new(steps(2000, u64::MAX));
+        for (start, end) in [(0, 1), (0, 39), (0, 40), (3, 44), (0, 760), (5, 2000)] {
+            let range = finder.step_range(start, end).unwrap();
+            assert_eq!(range.first().unwrap().step_num, start as u64);
+            assert_eq!(range.last().unwrap().step_num, end as u64);
+            assert!(range.windows(2).all(|w| w[0].step_num < w[1].step_num));
+        }
+    }
+
+    #[test]
+    fn test_dissection_invalid_moves() {
+        let mut producer = StepDiffFinder::new(steps(100, 50));
+        let mut challenger = StepDiffFinder::new(steps(100, u64::MAX));
+        let opening = producer.step_range(0, 100).unwrap();
+
+        let forged_initial = StepCommitment {
+            step_num: 0,
+            commitment: Bytes32::from_u64(u64::MAX),
+        };
+        assert_eq!(
+            Dissection::new(forged_initial, opening.clone(), 0, TIMEOUT).err(),
+            Some(DissectionError::InvalidOpening)
+        );
+
+        let mut dissection = Dissection::new(opening[0], opening.clone(), 0, TIMEOUT).unwrap();
+        let mv = challenger.next_move(dissection.steps()).unwrap();
+
+        assert_eq!(
+            dissection.submit(Party::Producer, mv.clone(), 1),
+            Err(DissectionError::NotYourTurn(Party::Producer))
+        );
+
+        let mut not_adjacent = mv.clone();
+        not_adjacent.start = opening[0];
+        assert_eq!(
+            dissection.submit(Party::Challenger, not_adjacent, 1),
+            Err(DissectionError::RangeMismatch)
+        );
+
+        // Challenger must disagree with producer's end step
+        let mut agreed_end = mv.clone();
+        *agreed_end.steps.last_mut().unwrap() = mv.end;
+        assert_eq!(
+            dissection.submit(Party::Challenger, agreed_end, 1),
+            Err(DissectionError::EndpointsMismatch)
+        );
+
+        let mut unordered = mv.clone();
+        unordered.steps.swap(1, 2);
+        assert_eq!(
+            dissection.submit(Party::Challenger, unordered, 1),
+            Err(DissectionError::InvalidSteps)
+        );
+
+        // Short move keeps both endpoints, but opponent's finder expects
+        // exactly `MAX_STEP_CHUNKS` commitments
+        let short = Move {
+            steps: vec![mv.steps[0], mv.steps[1], mv.steps[mv.steps.len() - 1]],
+            ..mv.clone()
+        };
+        assert_eq!(
+            producer.diff_step_range(&short.steps).err(),
+            dissection.submit(Party::Challenger, short, 1).err()
+        );
+        assert_eq!(dissection.turn(), Some(Party::Challenger));
+
+        let round = dissection.round();
+        dissection.submit(Party::Challenger, mv, 1).unwrap();
+        assert_eq!(dissection.round(), round + 1);
+        assert_eq!(dissection.turn(), Some(Party::Producer));
+    }
+
+    #[test]
+    fn test_dissection_short_opening() {
+        let mut producer = StepDiffFinder::new(steps(100, 50));
+        let mut challenger = StepDiffFinder::new(steps(100, u64::MAX));
+        let opening = producer.step_range(0, 100).unwrap();
+
+        // Any count `submit` accepts is one the opponent can respond to
+        let short = vec![opening[0], opening[opening.len() - 1]];
+        let mismatch = DissectionError::ChunkCountMismatch {
+            expected: MAX_STEP_CHUNKS,
+            actual: 2,
+        };
+        assert_eq!(
+            challenger.diff_step_range(&short).err(),
+            Some(mismatch.clone())
+        );
+        assert_eq!(
+            Dissection::new(opening[0], short, 0, TIMEOUT).err(),
+            Some(mismatch)
+        );
+
+        let dissection = Dissection::new(opening[0], opening, 0, TIMEOUT).unwrap();
+        assert!(challenger.next_move(dissection.steps()).is_ok());
+    }
+
+    #[test]
+    fn test_dissection_timeout() {
+        let mut producer = StepDiffFinder::new(steps(100, 50));
+        let mut challenger = StepDiffFinder::new(steps(100, u64::MAX));
+        let opening = producer.step_range(0, 100).unwrap();
+
+        let mut dissection = Dissection::new(opening[0], opening, 0, TIMEOUT).unwrap();
+        let mv = challenger.next_move(dissection.steps()).unwrap();
+        dissection.submit(Party::Challenger, mv, TIMEOUT).unwrap();
+
+        assert!(matches!(
+            dissection.check_timeout(TIMEOUT * 2),
+            DissectionState::InProgress { .. }
+        ));
+
+        let mv = producer.next_move(dissection.steps()).unwrap();
+        assert_eq!(
+            dissection.submit(Party::Producer, mv.clone(), TIMEOUT * 2 + 1),
+            Err(DissectionError::Timeout {
+                deadline: TIMEOUT * 2,
+                now: TIMEOUT * 2 + 1
+            })
+        );
+        assert_eq!(
+            dissection.state(),
+            &DissectionState::Forfeited {
+                loser: Party::Producer
+            }
+        );
+        assert_eq!(
+            dissection.submit(Party::Producer, mv, TIMEOUT * 2),
+            Err(DissectionError::Finished)
+        );
+    }
+
+    #[test]
+    fn test_step_diff_finder_errors() {
+        let mut finder = StepDiffFinder::new(steps(100, u64::MAX));
+        let mut forged = StepDiffFinder::new(steps(100, 0));
+        let same = finder.step_range(0, 100).unwrap();
+
+        assert_eq!(
+            finder.diff_step_range(&same),
+            Err(DissectionError::NoDifference)
+        );
+        assert_eq!(
+            finder.diff_step_range(&forged.step_range(0, 100).unwrap()),
+            Err(DissectionError::FirstStepDiffers(0))
+        );
+
+        let mut unordered = same.clone();
+        unordered.swap(1, 2);
+        assert_eq!(
+            finder.diff_step_range(&unordered),
+            Err(DissectionError::NonMonotonic)
+        );
+
+        assert_eq!(
+            finder.diff_step_range(&same[..1]),
+            Err(DissectionError::ChunkCountMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+        let mut missing = same.clone();
+        missing.remove(1);
+        assert_eq!(
+            finder.diff_step_range(&missing),
+            Err(DissectionError::ChunkCountMismatch {
+                expected: MAX_STEP_CHUNKS,
+                actual: MAX_STEP_CHUNKS - 1
+            })
+        );
+
+        for (start, end) in [(1, 1), (2, 1), (0, 101), (0, usize::MAX)] {
+            assert_eq!(
+                finder.step_range(start, end),
+                Err(DissectionError::OutOfRange { start, end })
+            );
+        }
+
+        // Step beyond known range
+        let mut longer = StepDiffFinder::new(steps(200, 150));
+        let mv_steps = longer.step_range(100, 200).unwrap();
+        assert_eq!(
+            finder.next_move(&mv_steps),
+            Err(DissectionError::OutOfRange {
+                start: 100,
+                end: 102
+            })
+        );
     }
 }
@@ -0,0 +1,188 @@
+//! Typed errors of proof verification and proving.
+//!
+//! `Memory` and syscalls must return `ckb_vm::Error` to ckb-vm. Errors
+//! without a ckb-vm counterpart are stashed by the memory or syscall which
+//! raised them and ckb-vm only sees a placeholder, `Machine` takes the typed
+//! error back once the failed call returns.
+//!
+//! Codes are stable, the step verifier lock returns them on chain. Never
+//! reuse or renumber a code.
+
+use core::fmt;
+
+use crate::{budget::ProofEstimate, string::String};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Merkle proof doesn't prove memory kvs under root
+    InvalidMerkleProof,
+    /// Key hint doesn't match its key, or is out of memory
+    InvalidKeyHint,
+    /// Step accesses a key not in proof
+    UnprovenKey,
+    /// Proof has a key which verified steps don't access
+    UnusedProofKey,
+    MemOutOfBound,
+    /// Step commitment differs from the expected one
+    CommitmentMismatch,
+    StepOverflow,
+    /// Malformed molecule or compact step proof
+    Decode,
+    /// Syscall witness doesn't match syscall commitment
+    SyscallWitnessMismatch,
+    InvalidEcall(u64),
+    /// Any other VM error
+    Vm(ckb_vm::Error),
+    /// Instruction doesn't decode under machine ISA and version, unsupported
+    /// extension for example
+    InvalidInstruction {
+        pc: u64,
+        instruction: u32,
+    },
+    /// Decoded op isn't supported by machine version
+    InvalidOp(u16),
+    /// Step proof exceeds prover budget
+    BudgetExceeded(ProofEstimate),
+    /// Sparse merkle tree backend failure
+    Smt,
+    /// Zero interval of checkpoints or commitments
+    InvalidInterval,
+    /// Step is behind current one and no checkpoint precedes it
+    StepBehind(u64),
+    /// Program exits at given step, before the requested one
+    ProgramExited(u64),
+    #[cfg(feature = "std")]
+    Io(String),
+    /// ISA has bits other than `ISA_IMC | ISA_B | ISA_MOP`
+    InvalidIsa(u8),
+    /// VM version is above `VERSION2`
+    UnsupportedVersion(u32),
+}
+
+impl Error {
+    pub fn code(&self) -> u8 {
+        match self {
+            Error::InvalidMerkleProof => 1,
+            Error::InvalidKeyHint => 2,
+            Error::UnprovenKey => 3,
+            Error::UnusedProofKey => 4,
+            Error::MemOutOfBound => 5,
+            Error::CommitmentMismatch => 6,
+            Error::StepOverflow => 7,
+            Error::Decode => 8,
+            Error::SyscallWitnessMismatch => 9,
+            Error::InvalidEcall(_) => 10,
+            Error::Vm(_) => 11,
+            Error::InvalidInstruction { .. } => 12,
+            Error::InvalidOp(_) => 13,
+            Error::BudgetExceeded(_) => 14,
+            Error::Smt => 15,
+            Error::InvalidInterval => 16,
+            Error::StepBehind(_) => 17,
+            Error::ProgramExited(_) => 18,
+            #[cfg(feature = "std")]
+            Error::Io(_) => 19,
+            Error::InvalidIsa(_) => 20,
+            Error::UnsupportedVersion(_) => 21,
+        }
+    }
+
+    /// Error reported to ckb-vm, a placeholder unless ckb-vm has the same
+    /// error. Its raiser must stash `self` for `Machine` to take back.
+    pub(crate) fn to_vm(&self) -> ckb_vm::Error {
+        match self {
+            Error::MemOutOfBound => ckb_vm::Error::MemOutOfBound,
+            Error::InvalidEcall(number) => ckb_vm::Error::InvalidEcall(*number),
+            Error::Vm(err) => err.clone(),
+            Error::InvalidInstruction { pc, instruction } => ckb_vm::Error::InvalidInstruction {
+                pc: *pc,
+                instruction: *instruction,
+            },
+            Error::InvalidOp(op) => ckb_vm::Error::InvalidOp(*op),
+            _ => ckb_vm::Error::Unexpected(String::new()),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidMerkleProof => write!(f, "invalid merkle proof"),
+            Error::InvalidKeyHint => write!(f, "invalid key hint"),
+            Error::UnprovenKey => write!(f, "key not proven"),
+            Error::UnusedProofKey => write!(f, "unused proof key"),
+            Error::MemOutOfBound => write!(f, "memory out of bound"),
+            Error::CommitmentMismatch => write!(f, "step commitment mismatch"),
+            Error::StepOverflow => write!(f, "step overflow"),
+            Error::Decode => write!(f, "invalid step proof encoding"),
+            Error::SyscallWitnessMismatch => write!(f, "syscall witness mismatch"),
+            Error::InvalidEcall(number) => write!(f, "invalid ecall {}", number),
+            Error::Vm(err) => write!(f, "vm error: {}", err),
+            Error::InvalidInstruction { pc, instruction } => {
+                write!(f, "invalid instruction {:#x} at pc {:#x}", instruction, pc)
+            }
+            Error::InvalidOp(op) => write!(f, "invalid op {}", op),
+            Error::BudgetExceeded(estimate) => write!(
+                f,
+                "proof budget exceeded, witness size {} verifier cycles {}",
+                estimate.witness_size, estimate.verifier_cycles
+            ),
+            Error::Smt => write!(f, "sparse merkle tree failure"),
+            Error::InvalidInterval => write!(f, "interval must be positive"),
+            Error::StepBehind(step) => write!(f, "step {} is behind, reset required", step),
+            Error::ProgramExited(step) => write!(f, "program exits at step {}", step),
+            #[cfg(feature = "std")]
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::InvalidIsa(isa) => write!(f, "invalid isa {:#b}", isa),
+            Error::UnsupportedVersion(version) => write!(f, "unsupported vm version {}", version),
+        }
+    }
+}
+
+impl From<ckb_vm::Error> for Error {
+    fn from(err: ckb_vm::Error) -> Self {
+        match err {
+            ckb_vm::Error::MemOutOfBound => Error::MemOutOfBound,
+            ckb_vm::Error::InvalidEcall(number) => Error::InvalidEcall(number),
+            ckb_vm::Error::InvalidInstruction { pc, instruction } => {
+                Error::InvalidInstruction { pc, instruction }
+            }
+            ckb_vm::Error::InvalidOp(op) => Error::InvalidOp(op),
+            err => Error::Vm(err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+
+    #[test]
+    fn test_vm_error_conversion() {
+        let errors = [
+            Error::MemOutOfBound,
+            Error::InvalidEcall(93),
+            Error::Vm(ckb_vm::Error::CyclesExceeded),
+            Error::InvalidInstruction {
+                pc: 0x1000,
+                instruction: 0x0800_00b3,
+            },
+            Error::InvalidOp(42),
+        ];
+        for err in errors {
+            assert_eq!(Error::from(err.to_vm()), err);
+        }
+
+        // Woss only errors never come back from their placeholder
+        for err in [Error::UnprovenKey, Error::StepOverflow, Error::Smt] {
+            assert!(matches!(Error::from(err.to_vm()), Error::Vm(_)));
+        }
+    }
+}
@@ -1,14 +1,26 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub mod budget;
+#[cfg(feature = "std")]
+pub mod checkpoint;
+pub mod codec;
 pub mod common;
 pub mod dissection;
+pub mod error;
 pub mod machine;
 pub mod memory;
 #[cfg(feature = "std")]
 pub mod prover;
+pub mod store;
+pub mod syscalls;
+pub mod trace;
 pub mod types;
+pub mod vectors;
 pub mod verifier;
 
+pub use ckb_vm;
+pub use error::Error;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "std")] {
         use std::vec;
@@ -19,6 +31,8 @@ cfg_if::cfg_if! {
         use std::cmp;
         use std::string;
         use std::borrow;
+        use std::sync;
+        use std::boxed;
     } else {
         use core::marker;
         use core::cell;
@@ -30,5 +44,7 @@ cfg_if::cfg_if! {
         use alloc::collections;
         use alloc::string;
         use alloc::borrow;
+        use alloc::sync;
+        use alloc::boxed;
     }
 }
@@ -1,27 +1,81 @@
 use ckb_vm::{
-    decoder::build_decoder, machine::VERSION1, Bytes, CoreMachine, DefaultCoreMachine,
-    DefaultMachine, DefaultMachineBuilder, Error, Register, SupportMachine, ISA_IMC,
-    RISCV_GENERAL_REGISTER_NUMBER,
+    decoder::{build_decoder, Decoder},
+    instructions::{extract_opcode, instruction_opcode_name, Instruction},
+    machine::VERSION1,
+    Bytes, CoreMachine as _, DefaultCoreMachine, DefaultMachine, DefaultMachineBuilder, Register,
+    SupportMachine, ISA_B, ISA_IMC, ISA_MOP, RISCV_GENERAL_REGISTER_NUMBER,
 };
 use serde::{Deserialize, Serialize};
+use spin::Mutex;
 
 #[cfg(feature = "std")]
 use crate::memory::prover::ProverSMT;
+use crate::{boxed::Box, marker::PhantomData, string::ToString, sync::Arc, vec::Vec};
 use crate::{
-    common::{blake2b, blake2b_hasher},
-    memory::{verifier::VerifierSMT, MemoryProof, SMTMemory, SMTOps},
+    common::{hash, hasher, CommitmentHasher},
+    memory::{MemoryCommitment, MemoryProof, SMTMemory, SMTOps, SMTProve, SMTRestore},
+    store::StepCommitmentStore,
+    syscalls::{ProvableSyscall, SyscallCommitment, SyscallSet, SyscallsAdapter},
+    trace::{StepTrace, StepTracer},
     types::Bytes32,
+    Error,
 };
-use crate::{string::ToString, vec::Vec};
 
 pub type Reg = u32;
 
+/// Latest supported CKB-VM version, `VERSION2` isn't exported by the pinned
+/// ckb-vm yet
+pub const MAX_VERSION: u32 = 2;
+
+/// ISA extensions machine can be built with
+pub const SUPPORTED_ISA: u8 = ISA_IMC | ISA_B | ISA_MOP;
+
+pub type CoreMachine<R, M> = DefaultCoreMachine<R, SMTMemory<R, M>>;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
 pub struct StepCommitment {
     pub step_num: u64,
     pub commitment: Bytes32,
 }
 
+/// Commitment to the initial state, agreed by both parties before dissection starts.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InitialCommitment {
+    pub program_hash: Bytes32,
+    pub args_hash: Bytes32,
+    pub step: StepCommitment,
+}
+
+impl InitialCommitment {
+    pub fn commitment(&self) -> Bytes32 {
+        hash([
+            b"Initial",
+            self.program_hash.as_ref(),
+            self.args_hash.as_ref(),
+            self.step.step_num.to_le_bytes().as_slice(),
+            self.step.commitment.as_ref(),
+        ])
+        .into()
+    }
+}
+
+pub fn hash_program(program: &Bytes) -> Bytes32 {
+    hash([b"Program", program.as_ref()]).into()
+}
+
+pub fn hash_args(args: &[Bytes]) -> Bytes32 {
+    let mut hasher = hasher();
+
+    hasher.update(b"Args");
+    hasher.update(&(args.len() as u64).to_le_bytes());
+    for arg in args {
+        hasher.update(&(arg.len() as u64).to_le_bytes());
+        hasher.update(arg);
+    }
+
+    hasher.finalize().into()
+}
+
 pub struct RunResult {
     pub step_count: u64,
     pub step_commitments: Vec<StepCommitment>,
@@ -39,27 +93,176 @@ pub struct StepProof<Reg> {
     pub running: bool,
     pub isa: u8,
     pub version: u32,
+    pub syscalls: Vec<SyscallCommitment>,
+    /// Witness of syscall invoked by this step, empty if none
+    pub syscall_witness: Vec<u8>,
+}
+
+pub struct MachineBuilder<R, M> {
+    memory_size: usize,
+    isa: u8,
+    version: u32,
+    max_cycles: u64,
+    syscalls: Vec<Box<dyn ProvableSyscall<CoreMachine<R, M>>>>,
+    _marker: PhantomData<(R, M)>,
+}
+
+impl<R: Register + 'static, M: SMTOps + 'static> MachineBuilder<R, M> {
+    pub fn new(memory_size: usize) -> Self {
+        Self {
+            memory_size,
+            isa: ISA_IMC,
+            version: VERSION1,
+            max_cycles: u64::MAX,
+            syscalls: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// ISA flags, for example `ISA_IMC | ISA_B | ISA_MOP`
+    pub fn isa(mut self, isa: u8) -> Self {
+        self.isa = isa;
+        self
+    }
+
+    /// CKB-VM version, for example `VERSION0`, `VERSION1` or `VERSION2`
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn max_cycles(mut self, max_cycles: u64) -> Self {
+        self.max_cycles = max_cycles;
+        self
+    }
+
+    /// Register a syscall, its commitment is part of the machine commitment
+    pub fn syscall(mut self, syscall: Box<dyn ProvableSyscall<CoreMachine<R, M>>>) -> Self {
+        self.syscalls.push(syscall);
+        self
+    }
+
+    /// Fails with `InvalidIsa` or `UnsupportedVersion` on unknown ISA bits or
+    /// version
+    pub fn build(self) -> Result<Machine<R, M>, Error> {
+        check_config(self.isa, self.version)?;
+        let core = DefaultCoreMachine::<R, _>::new(
+            self.isa,
+            self.version,
+            self.max_cycles,
+            self.memory_size,
+        );
+        Ok(Machine::with_syscalls(core, SyscallSet::new(self.syscalls)))
+    }
+}
+
+fn check_config(isa: u8, version: u32) -> Result<(), Error> {
+    if isa & !SUPPORTED_ISA != 0 {
+        return Err(Error::InvalidIsa(isa));
+    }
+    if version > MAX_VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    Ok(())
 }
 
 pub struct Machine<R, M> {
-    inner: DefaultMachine<DefaultCoreMachine<R, SMTMemory<R, M>>>,
+    inner: DefaultMachine<CoreMachine<R, M>>,
     step: u64,
+    initial: Option<InitialCommitment>,
+    /// Commitment of initial step while state is untouched since loading,
+    /// cleared by anything that may change it, see `commit_step`
+    initial_step: Option<StepCommitment>,
+    syscalls: Arc<Mutex<SyscallSet<CoreMachine<R, M>>>>,
+    step_tracer: Option<Box<dyn StepTracer>>,
 }
 
-impl<R: Register, M: SMTOps> Machine<R, M> {
+impl<R: Register + 'static, M: SMTOps + 'static> Machine<R, M> {
     pub fn new(memory_size: usize) -> Self {
-        let core = DefaultCoreMachine::<R, _>::new(ISA_IMC, VERSION1, u64::MAX, memory_size);
-        let inner = DefaultMachineBuilder::new(core).build();
-        Self { inner, step: 0 }
+        MachineBuilder::new(memory_size)
+            .build()
+            .expect("default isa and version")
+    }
+
+    pub fn builder(memory_size: usize) -> MachineBuilder<R, M> {
+        MachineBuilder::new(memory_size)
+    }
+
+    fn with_syscalls(core: CoreMachine<R, M>, syscalls: SyscallSet<CoreMachine<R, M>>) -> Self {
+        let syscalls = Arc::new(Mutex::new(syscalls));
+        let inner = DefaultMachineBuilder::new(core)
+            .syscall(Box::new(SyscallsAdapter::new(Arc::clone(&syscalls))))
+            .build();
+
+        Machine {
+            inner,
+            step: 0,
+            initial: None,
+            initial_step: None,
+            syscalls,
+            step_tracer: None,
+        }
+    }
+}
+
+impl<R: Register, M: SMTOps> Machine<R, M> {
+    pub fn isa(&self) -> u8 {
+        self.inner.isa()
+    }
+
+    pub fn version(&self) -> u32 {
+        self.inner.version()
+    }
+
+    pub fn max_cycles(&self) -> u64 {
+        self.inner.max_cycles()
+    }
+
+    pub fn step_num(&self) -> u64 {
+        self.step
+    }
+
+    /// Emit a record of every step executed from now on, `None` to stop
+    pub fn set_step_tracer(&mut self, tracer: Option<Box<dyn StepTracer>>) {
+        self.step_tracer = tracer;
     }
 
     pub fn load_program(&mut self, program: &Bytes) -> Result<u64, Error> {
-        self.inner.load_program(program, &[])
+        self.load_program_with_args(program, &[])
+    }
+
+    /// Load program with argv, should be called on a fresh or reset machine.
+    pub fn load_program_with_args(
+        &mut self,
+        program: &Bytes,
+        args: &[Bytes],
+    ) -> Result<u64, Error> {
+        let size = self
+            .inner
+            .load_program(program, args)
+            .map_err(|err| self.vm_error(err))?;
+        self.initial_step = None;
+        let initial_step = self.commit_step()?;
+        self.initial = Some(InitialCommitment {
+            program_hash: hash_program(program),
+            args_hash: hash_args(args),
+            step: initial_step,
+        });
+        self.initial_step = Some(initial_step);
+
+        Ok(size)
+    }
+
+    pub fn initial_commitment(&self) -> Option<InitialCommitment> {
+        self.initial
     }
 
     pub fn reset(&mut self) {
-        self.inner.reset(u64::MAX);
+        let max_cycles = self.inner.max_cycles();
+        self.inner.reset(max_cycles);
         self.step = 0;
+        self.initial = None;
+        self.initial_step = None;
     }
 
     pub fn run(&mut self) -> Result<RunResult, Error> {
@@ -67,39 +270,137 @@ impl<R: Register, M: SMTOps> Machine<R, M> {
     }
 
     pub fn run_until_step(&mut self, step_num: u64) -> Result<RunResult, Error> {
-        let mut decoder = build_decoder::<R>(self.inner.isa(), self.inner.version());
-        let mut step_commitments: Vec<StepCommitment> = vec![self.commit_step()?];
-
-        self.inner.set_running(true);
-        while self.inner.running() && self.step < step_num {
-            self.inner.step(&mut decoder)?;
-            self.step = self.next_step()?;
-            step_commitments.push(self.commit_step()?);
-        }
+        let mut step_commitments: Vec<StepCommitment> = Vec::new();
+        let step_count = self.run_until_step_with_store(step_num, &mut step_commitments)?;
 
         let ret = RunResult {
-            step_count: self.step,
+            step_count,
             step_commitments,
         };
         Ok(ret)
     }
 
+    /// Run until given step, append commitments from current step into store.
+    /// Returns step count.
+    pub fn run_until_step_with_store<S: StepCommitmentStore>(
+        &mut self,
+        step_num: u64,
+        store: &mut S,
+    ) -> Result<u64, Error> {
+        self.run_until_step_with(step_num, |machine| store.append(machine.commit_step()?))
+    }
+
+    /// Run until given step, `on_step` is called on current step and after
+    /// every executed step. Returns step count.
+    pub fn run_until_step_with<F>(&mut self, step_num: u64, mut on_step: F) -> Result<u64, Error>
+    where
+        F: FnMut(&mut Self) -> Result<(), Error>,
+    {
+        let mut decoder = build_decoder::<R>(self.inner.isa(), self.inner.version());
+        on_step(self)?;
+
+        self.start_running();
+        while self.inner.running() && self.step < step_num {
+            self.execute_step(&mut decoder)?;
+            on_step(self)?;
+        }
+
+        Ok(self.step)
+    }
+
     pub fn next_step(&mut self) -> Result<u64, Error> {
-        let step_num = self
-            .step
-            .checked_add(1)
-            .ok_or(Error::Unexpected("step overflow".to_string()))?;
+        let step_num = self.step.checked_add(1).ok_or(Error::StepOverflow)?;
         Ok(step_num)
     }
 
     pub fn execute_next_step(&mut self) -> Result<(), Error> {
         let mut decoder = build_decoder::<R>(self.inner.isa(), self.inner.version());
 
+        self.start_running();
+        self.execute_step(&mut decoder)?;
+
+        Ok(())
+    }
+
+    /// Execute at most `count` steps, stops early if program exits. Returns
+    /// executed step count.
+    pub fn execute_steps(&mut self, count: u64) -> Result<u64, Error> {
+        let mut decoder = build_decoder::<R>(self.inner.isa(), self.inner.version());
+        let step_num = self.step;
+
+        self.start_running();
+        while self.inner.running() && self.step - step_num < count {
+            self.execute_step(&mut decoder)?;
+        }
+
+        Ok(self.step - step_num)
+    }
+
+    fn start_running(&mut self) {
+        self.initial_step = None;
         self.inner.set_running(true);
-        self.inner.step(&mut decoder)?;
+    }
+
+    fn execute_step(&mut self, decoder: &mut Decoder) -> Result<(), Error> {
+        if self.step_tracer.is_none() {
+            self.inner.step(decoder).map_err(|err| self.vm_error(err))?;
+            self.step = self.next_step()?;
+            return Ok(());
+        }
+
+        let pc = self.inner.pc().to_u64();
+        let instruction = decoder
+            .decode(self.inner.memory_mut(), pc)
+            .map_err(|err| self.vm_error(err))?;
+        let registers: Vec<u64> = self.inner.registers().iter().map(|r| r.to_u64()).collect();
+
+        self.inner.memory_mut().enable_access_log();
+        let ret = self.inner.step(decoder);
+        let access_log = self.inner.memory_mut().take_access_log();
+        ret.map_err(|err| self.vm_error(err))?;
+
+        let register_writes = self
+            .inner
+            .registers()
+            .iter()
+            .enumerate()
+            .filter(|(idx, r)| r.to_u64() != registers[*idx])
+            .map(|(idx, r)| (idx, r.to_u64()))
+            .collect();
+        let access_log = access_log.unwrap_or_default();
+        let trace = StepTrace {
+            step_num: self.step,
+            pc,
+            instruction,
+            opcode: instruction_opcode_name(extract_opcode(instruction)).to_string(),
+            register_writes,
+            memory_reads: access_log.reads,
+            memory_writes: access_log.writes,
+            cycles: self.inner.cycles(),
+        };
         self.step = self.next_step()?;
 
-        Ok(())
+        match self.step_tracer.as_mut() {
+            Some(tracer) => tracer.trace(&trace),
+            None => Ok(()),
+        }
+    }
+
+    /// Decode instruction at current pc
+    pub fn current_instruction(&mut self) -> Result<Instruction, Error> {
+        let mut decoder = build_decoder::<R>(self.inner.isa(), self.inner.version());
+        let pc = self.inner.pc().to_u64();
+        decoder
+            .decode(self.inner.memory_mut(), pc)
+            .map_err(|err| self.vm_error(err))
+    }
+
+    /// Typed error of a failed ckb-vm call, preferring the one stashed by
+    /// memory, then by syscalls, see `crate::error`
+    fn vm_error(&mut self, err: ckb_vm::Error) -> Error {
+        let memory = self.inner.memory_mut().take_error();
+        let syscall = self.syscalls.lock().take_error();
+        memory.or(syscall).unwrap_or_else(|| err.into())
     }
 
     pub fn get_next_pc(&mut self) -> R {
@@ -111,6 +412,11 @@ impl<R: Register, M: SMTOps> Machine<R, M> {
     }
 
     pub fn commit_step(&mut self) -> Result<StepCommitment, Error> {
+        // Initial step is committed once by `load_program_with_args`
+        if let Some(initial_step) = self.initial_step.filter(|s| s.step_num == self.step) {
+            return Ok(initial_step);
+        }
+
         let com = StepCommitment {
             step_num: self.step,
             commitment: self.commit()?,
@@ -120,91 +426,184 @@ impl<R: Register, M: SMTOps> Machine<R, M> {
     }
 
     pub fn commit(&mut self) -> Result<Bytes32, Error> {
-        let mut hasher = blake2b_hasher();
+        let next_pc = self.get_next_pc();
+        let syscalls = self.syscall_commitments();
+        let memory = self.inner.memory_mut().commit_memory()?;
+        let state = MachineState {
+            registers: self.inner.registers(),
+            pc: self.inner.pc(),
+            next_pc: &next_pc,
+            memory,
+            cycles: self.inner.cycles(),
+            max_cycles: self.inner.max_cycles(),
+            running: self.inner.running(),
+            isa: self.inner.isa(),
+            version: self.inner.version(),
+            syscalls: &syscalls,
+        };
+
+        Ok(state.commitment())
+    }
+
+    pub fn syscall_commitments(&self) -> Vec<SyscallCommitment> {
+        self.syscalls.lock().commitments()
+    }
+}
+
+/// Everything committed of a machine, see docs/vm-steps-commit-and-verify-design.md
+/// for the encoding
+pub struct MachineState<'a, R> {
+    pub registers: &'a [R],
+    pub pc: &'a R,
+    pub next_pc: &'a R,
+    pub memory: MemoryCommitment,
+    pub cycles: u64,
+    pub max_cycles: u64,
+    pub running: bool,
+    pub isa: u8,
+    pub version: u32,
+    pub syscalls: &'a [SyscallCommitment],
+}
+
+impl<'a, R: Register> MachineState<'a, R> {
+    pub fn commitment(&self) -> Bytes32 {
+        let mut hasher = hasher();
 
         hasher.update(b"Machine");
         hasher.update(&self.commit_registers());
-        hasher.update(&blake2b([
+        hasher.update(&hash([
             b"PC",
             R::BITS.to_le_bytes().as_slice(),
-            self.inner.pc().to_u64().to_le_bytes().as_slice(),
+            self.pc.to_u64().to_le_bytes().as_slice(),
         ]));
-        hasher.update(&self.commit_next_pc());
-        hasher.update(&self.commit_memory()?);
-        hasher.update(&blake2b([
-            b"Cycles",
-            self.inner.cycles().to_le_bytes().as_slice(),
+        hasher.update(&hash([
+            b"Next_PC",
+            R::BITS.to_le_bytes().as_slice(),
+            self.next_pc.to_u64().to_le_bytes().as_slice(),
         ]));
-        hasher.update(&blake2b([
+        hasher.update(&hash([
+            b"Memory",
+            self.memory.size.to_le_bytes().as_slice(),
+            self.memory.root.as_ref(),
+        ]));
+        hasher.update(&hash([b"Cycles", self.cycles.to_le_bytes().as_slice()]));
+        hasher.update(&hash([
             b"Max_Cycles",
-            self.inner.max_cycles().to_le_bytes().as_slice(),
+            self.max_cycles.to_le_bytes().as_slice(),
         ]));
-        hasher.update(&blake2b([
+        hasher.update(&hash([
             b"Running",
-            u8::from(self.inner.running()).to_le_bytes().as_slice(),
-        ]));
-        hasher.update(&blake2b([
-            b"ISA",
-            self.inner.isa().to_le_bytes().as_slice(),
-        ]));
-        hasher.update(&blake2b([
-            b"Version",
-            self.inner.version().to_le_bytes().as_slice(),
+            u8::from(self.running).to_le_bytes().as_slice(),
         ]));
+        hasher.update(&hash([b"ISA", self.isa.to_le_bytes().as_slice()]));
+        hasher.update(&hash([b"Version", self.version.to_le_bytes().as_slice()]));
+        hasher.update(&self.commit_syscalls());
 
-        let mut buf = [0u8; 32];
-        hasher.finalize(&mut buf);
-        Ok(buf.into())
+        hasher.finalize().into()
     }
 
-    fn commit_next_pc(&mut self) -> Bytes32 {
-        let next_pc = self.get_next_pc();
-        blake2b([
-            b"Next_PC",
-            R::BITS.to_le_bytes().as_slice(),
-            next_pc.to_u64().to_le_bytes().as_slice(),
-        ])
-        .into()
+    fn commit_registers(&self) -> Bytes32 {
+        let mut hasher = hasher();
+
+        hasher.update(b"Registers");
+        for (idx, r) in self.registers.iter().enumerate() {
+            hasher.update(&(idx as u64).to_le_bytes());
+            hasher.update(&R::BITS.to_le_bytes());
+            hasher.update(&r.to_u64().to_le_bytes());
+        }
+
+        hasher.finalize().into()
     }
 
-    fn commit_memory(&self) -> Result<Bytes32, Error> {
-        let mut hasher = blake2b_hasher();
-        let mem_com = self.inner.memory().commit_memory()?;
+    fn commit_syscalls(&self) -> Bytes32 {
+        let mut hasher = hasher();
 
-        hasher.update(b"Memory");
-        hasher.update(&mem_com.size.to_le_bytes());
-        hasher.update(&mem_com.root);
+        hasher.update(b"Syscalls");
+        hasher.update(&(self.syscalls.len() as u64).to_le_bytes());
+        for syscall in self.syscalls {
+            hasher.update(&syscall.number.to_le_bytes());
+            hasher.update(&syscall.commitment);
+        }
 
-        let mut buf = [0u8; 32];
-        hasher.finalize(&mut buf);
-        Ok(buf.into())
+        hasher.finalize().into()
     }
+}
 
-    fn commit_registers(&self) -> Bytes32 {
-        let mut hasher = blake2b_hasher();
+/// Machine state at a step. Memory is a snapshot of the persistent SMT
+/// store, so taking one is cheap.
+#[cfg(feature = "std")]
+pub struct MachineSnapshot<R, M = ProverSMT> {
+    pub step: u64,
+    pub registers: [R; RISCV_GENERAL_REGISTER_NUMBER],
+    pub pc: R,
+    pub next_pc: R,
+    pub cycles: u64,
+    pub max_cycles: u64,
+    pub running: bool,
+    pub initial: Option<InitialCommitment>,
+    pub memory: M,
+}
 
-        hasher.update(b"Registers");
+#[cfg(feature = "std")]
+impl<R: Register, M: SMTProve + SMTOps> Machine<R, M> {
+    /// Syscalls are not captured, they should keep their state in memory
+    pub fn snapshot(&mut self) -> Result<MachineSnapshot<R, M>, Error> {
+        let mut registers: [R; RISCV_GENERAL_REGISTER_NUMBER] = Default::default();
         self.inner
             .registers()
             .iter()
             .enumerate()
-            .for_each(|(idx, r)| {
-                hasher.update(&idx.to_le_bytes());
-                hasher.update(&R::BITS.to_le_bytes());
-                hasher.update(&r.to_u64().to_le_bytes());
-            });
+            .for_each(|(idx, r)| registers[idx] = r.clone());
+
+        Ok(MachineSnapshot {
+            step: self.step,
+            registers,
+            pc: self.inner.pc().clone(),
+            next_pc: self.get_next_pc(),
+            cycles: self.inner.cycles(),
+            max_cycles: self.inner.max_cycles(),
+            running: self.inner.running(),
+            initial: self.initial,
+            memory: self.inner.memory_mut().snap()?,
+        })
+    }
+
+    pub fn restore_snapshot(&mut self, snapshot: &MachineSnapshot<R, M>) {
+        self.inner.reset(snapshot.max_cycles);
+
+        for (idx, reg) in snapshot.registers.iter().enumerate() {
+            self.inner.set_register(idx, reg.clone());
+        }
+
+        self.inner.update_pc(snapshot.pc.clone());
+        self.inner.commit_pc();
+        self.inner.update_pc(snapshot.next_pc.clone());
+
+        self.inner.memory_mut().restore_snap(snapshot.memory.snap());
+
+        self.inner.set_cycles(snapshot.cycles);
+        self.inner.set_running(snapshot.running);
 
-        let mut buf = [0u8; 32];
-        hasher.finalize(&mut buf);
-        buf.into()
+        self.step = snapshot.step;
+        self.initial = snapshot.initial;
+        self.initial_step = None;
+        self.syscalls.lock().take_witness();
     }
 }
 
-#[cfg(feature = "std")]
-impl<R: Register> Machine<R, ProverSMT> {
+impl<R: Register, M: SMTProve + SMTOps> Machine<R, M> {
     pub fn prove_next_step(&mut self) -> Result<StepProof<R>, Error> {
+        self.prove_steps(1)
+    }
+
+    /// Prove at most `count` steps from current one, stops early if program
+    /// exits. Memory proof covers every key touched across these steps.
+    ///
+    /// Proof carries a single syscall witness, range invoking syscalls with
+    /// different witnesses can't be proved at once.
+    pub fn prove_steps(&mut self, count: u64) -> Result<StepProof<R>, Error> {
         let mut decoder = build_decoder::<R>(self.inner.isa(), self.inner.version());
-        self.inner.memory_mut().enable_tracer();
+        self.inner.memory_mut().enable_tracer()?;
 
         let step_num = self.step;
         let mut registers: [R; RISCV_GENERAL_REGISTER_NUMBER] = Default::default();
@@ -220,10 +619,23 @@ impl<R: Register> Machine<R, ProverSMT> {
         let running = self.inner.running();
         let isa = self.inner.isa();
         let version = self.inner.version();
+        let syscalls = self.syscall_commitments();
 
-        self.inner.set_running(true);
-        self.inner.step(&mut decoder)?;
-        self.step = self.next_step()?;
+        let mut syscall_witness: Option<Bytes> = None;
+        self.syscalls.lock().take_witness();
+        self.start_running();
+        while self.inner.running() && self.step - step_num < count {
+            self.execute_step(&mut decoder)?;
+
+            let witness = self.syscalls.lock().take_witness();
+            match (&syscall_witness, witness) {
+                (Some(recorded), Some(witness)) if *recorded != witness => {
+                    return Err(Error::SyscallWitnessMismatch);
+                }
+                (None, Some(witness)) => syscall_witness = Some(witness),
+                _ => (),
+            }
+        }
 
         let memory = self.inner.memory().prove_traces()?.expect("tracer enabled");
 
@@ -238,21 +650,28 @@ impl<R: Register> Machine<R, ProverSMT> {
             running,
             isa,
             version,
+            syscalls,
+            syscall_witness: syscall_witness.map(|w| w.to_vec()).unwrap_or_default(),
         };
 
         Ok(step_proof)
     }
 }
 
-impl<R: Register> Machine<R, VerifierSMT> {
+impl<R: Register + 'static, M: SMTRestore + SMTOps + 'static> Machine<R, M> {
     pub fn restore_from_proof(proof: StepProof<R>) -> Result<Self, Error> {
-        let core = DefaultCoreMachine::<R, SMTMemory<R, VerifierSMT>>::new(
+        check_config(proof.isa, proof.version)?;
+        let core = DefaultCoreMachine::<R, SMTMemory<R, M>>::new(
             proof.isa,
             proof.version,
             proof.max_cycles,
             proof.memory.memory_size,
         );
-        let mut inner = DefaultMachineBuilder::new(core).build();
+        let syscalls = SyscallSet::restore(proof.syscalls, proof.syscall_witness.into());
+        let mut machine = Self::with_syscalls(core, syscalls);
+        machine.step = proof.step_num;
+
+        let inner = &mut machine.inner;
 
         for (idx, reg) in proof.registers.into_iter().enumerate() {
             inner.set_register(idx, reg);
@@ -267,9 +686,537 @@ impl<R: Register> Machine<R, VerifierSMT> {
         inner.set_cycles(proof.cycles);
         inner.set_running(proof.running);
 
-        Ok(Self {
-            inner,
-            step: proof.step_num,
-        })
+        Ok(machine)
+    }
+
+    /// See `SMTMemory::check_proof_keys`
+    pub fn check_proof_keys(&self) -> Result<(), Error> {
+        self.inner.memory().check_proof_keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use ckb_vm::{
+        machine::{VERSION0, VERSION1},
+        Bytes, CoreMachine, Memory, ISA_B, ISA_IMC, ISA_MOP,
+    };
+
+    use super::{hash_args, hash_program, Machine, StepProof, MAX_VERSION};
+    use crate::{
+        error::Error,
+        memory::{
+            flat::LazySMT,
+            prover::ProverSMT,
+            shallow::{ShallowProverSMT, ShallowVerifierSMT},
+            verifier::VerifierSMT,
+            KeyHint, SMTOps, SMTProve, SMTRestore,
+        },
+        syscalls::{LoadInput, INPUT_CHUNK_SIZE, LOAD_INPUT_SYSCALL_NUMBER},
+        types::{
+            conversion::{Builder, Entity, Pack, TryUnpack},
+            packed, Bytes32,
+        },
+        verifier::Verifier,
+    };
+
+    const MEMORY_SIZE: usize = 4 << 20;
+
+    // https://github.com/nervosnetwork/ckb-vm/blob/develop/tests/programs/simple
+    fn simple_program() -> Bytes {
+        fs::read("../../../simple").unwrap().into()
+    }
+
+    // Assembled from load_input64.S
+    fn load_input_program() -> Bytes {
+        fs::read("../../../load_input64").unwrap().into()
+    }
+
+    fn prove_and_verify_every_step(isa: u8, version: u32, max_cycles: u64) {
+        let program = simple_program();
+        // VERSION0 can't access the last memory byte, where an empty argv
+        // stack ends
+        let args = [Bytes::from_static(b"simple")];
+        let mut prover = Machine::<u32, ProverSMT>::builder(MEMORY_SIZE)
+            .isa(isa)
+            .version(version)
+            .max_cycles(max_cycles)
+            .build()
+            .unwrap();
+        prover.load_program_with_args(&program, &args).unwrap();
+        let result = prover.run().unwrap();
+
+        prover.reset();
+        prover.load_program_with_args(&program, &args).unwrap();
+
+        for window in result.step_commitments.windows(2) {
+            let proof = prover.prove_next_step().unwrap();
+            assert_eq!(
+                (proof.isa, proof.version, proof.max_cycles),
+                (isa, version, max_cycles)
+            );
+
+            let mut verifier = Machine::<u32, VerifierSMT>::restore_from_proof(proof).unwrap();
+            assert_eq!(window[0], verifier.commit_step().unwrap());
+            verifier.execute_next_step().unwrap();
+            assert_eq!(window[1], verifier.commit_step().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_machine_version0() {
+        prove_and_verify_every_step(ISA_IMC, VERSION0, u64::MAX);
+    }
+
+    #[test]
+    fn test_machine_isa_b_mop() {
+        prove_and_verify_every_step(ISA_IMC | ISA_B | ISA_MOP, VERSION1, 1 << 32);
+
+        // `andn ra, sp, gp`, only decodes with B extension
+        const ANDN: u32 = 0x4031_70b3;
+        let andn_machine = |isa: u8| {
+            let mut machine = Machine::<u32, ProverSMT>::builder(MEMORY_SIZE)
+                .isa(isa)
+                .build()
+                .unwrap();
+            machine.load_program(&simple_program()).unwrap();
+            let pc = *machine.inner.pc();
+            machine.inner.memory_mut().store32(&pc, &ANDN).unwrap();
+            machine
+        };
+
+        let mut prover = andn_machine(ISA_IMC | ISA_B);
+        let proof = prover.prove_next_step().unwrap();
+        let next = prover.commit_step().unwrap();
+        let mut verifier = Machine::<u32, VerifierSMT>::restore_from_proof(proof.clone()).unwrap();
+        verifier.execute_next_step().unwrap();
+        assert_eq!(verifier.commit_step().unwrap(), next);
+
+        let mut imc = andn_machine(ISA_IMC);
+        let pc = proof.pc as u64;
+        assert_eq!(
+            imc.execute_next_step().unwrap_err(),
+            Error::InvalidInstruction {
+                pc,
+                instruction: ANDN
+            }
+        );
+    }
+
+    #[test]
+    fn test_machine_config_rejected() {
+        let build = |isa: u8, version: u32| {
+            Machine::<u32, ProverSMT>::builder(MEMORY_SIZE)
+                .isa(isa)
+                .version(version)
+                .build()
+                .err()
+        };
+        assert_eq!(build(ISA_IMC | ISA_B | ISA_MOP, MAX_VERSION), None);
+        assert_eq!(build(0b100, VERSION1), Some(Error::InvalidIsa(0b100)));
+        assert_eq!(
+            build(ISA_IMC, MAX_VERSION + 1),
+            Some(Error::UnsupportedVersion(MAX_VERSION + 1))
+        );
+
+        let mut prover = Machine::<u32, ProverSMT>::new(MEMORY_SIZE);
+        prover.load_program(&simple_program()).unwrap();
+        let proof = prover.prove_next_step().unwrap();
+
+        let mut invalid_isa = proof.clone();
+        invalid_isa.isa = 0xff;
+        let err = Machine::<u32, VerifierSMT>::restore_from_proof(invalid_isa).err();
+        assert_eq!(err, Some(Error::InvalidIsa(0xff)));
+
+        let mut unsupported_version = proof;
+        unsupported_version.version = u32::MAX;
+        let err = Machine::<u32, VerifierSMT>::restore_from_proof(unsupported_version).err();
+        assert_eq!(err, Some(Error::UnsupportedVersion(u32::MAX)));
+    }
+
+    #[test]
+    fn test_machine_config_committed() {
+        let program = simple_program();
+        let commit = |isa: u8, version: u32, max_cycles: u64| {
+            let mut machine = Machine::<u32, ProverSMT>::builder(MEMORY_SIZE)
+                .isa(isa)
+                .version(version)
+                .max_cycles(max_cycles)
+                .build()
+                .unwrap();
+            machine.load_program(&program).unwrap();
+            machine.commit().unwrap()
+        };
+
+        let default = commit(ISA_IMC, VERSION1, u64::MAX);
+        assert_ne!(default, commit(ISA_IMC | ISA_B, VERSION1, u64::MAX));
+        assert_ne!(default, commit(ISA_IMC, VERSION0, u64::MAX));
+        assert_ne!(default, commit(ISA_IMC, VERSION1, 1 << 32));
+    }
+
+    #[test]
+    fn test_program_args_committed() {
+        let program = simple_program();
+        let args: Vec<Bytes> = vec!["simple".into(), "--input".into(), "42".into()];
+
+        let mut machine = Machine::<u32, ProverSMT>::new(MEMORY_SIZE);
+        machine.load_program(&program).unwrap();
+        let no_args = machine.initial_commitment().unwrap();
+
+        machine.reset();
+        assert!(machine.initial_commitment().is_none());
+        machine.load_program_with_args(&program, &args).unwrap();
+        let with_args = machine.initial_commitment().unwrap();
+
+        assert_eq!(no_args.program_hash, with_args.program_hash);
+        assert_eq!(with_args.program_hash, hash_program(&program));
+        assert_eq!(with_args.args_hash, hash_args(&args));
+        assert_ne!(no_args.args_hash, with_args.args_hash);
+        // Args are pushed onto the initial stack
+        assert_ne!(no_args.step, with_args.step);
+        assert_eq!(with_args.step.step_num, 0);
+        assert_ne!(no_args.commitment(), with_args.commitment());
+
+        // Args boundaries are committed
+        let joined: Vec<Bytes> = vec!["simple--input".into(), "42".into()];
+        assert_ne!(hash_args(&args), hash_args(&joined));
+
+        // Run result starts from the agreed step 0
+        let result = machine.run().unwrap();
+        assert_eq!(result.step_commitments[0], with_args.step);
+    }
+
+    #[test]
+    fn test_initial_step_mutated() {
+        let program = simple_program();
+        let mut machine = Machine::<u32, ProverSMT>::new(MEMORY_SIZE);
+        machine.load_program(&program).unwrap();
+        let initial = machine.initial_commitment().unwrap();
+        assert_eq!(machine.commit_step().unwrap(), initial.step);
+
+        // Step 0 state changed after loading isn't hidden by initial commitment
+        let mut snapshot = machine.snapshot().unwrap();
+        snapshot.registers[1] = 1;
+        machine.restore_snapshot(&snapshot);
+        let mutated = machine.commit_step().unwrap();
+        assert_eq!(mutated.step_num, 0);
+        assert_ne!(mutated, initial.step);
+        assert_eq!(mutated.commitment, machine.commit().unwrap());
+
+        machine.reset();
+        machine.load_program(&program).unwrap();
+        assert_eq!(machine.commit_step().unwrap(), initial.step);
+    }
+
+    #[test]
+    fn test_prove_multiple_steps() {
+        let program = simple_program();
+        let mut prover = Machine::<u32, ProverSMT>::new(MEMORY_SIZE);
+        prover.load_program(&program).unwrap();
+        let result = prover.run().unwrap();
+        let steps = &result.step_commitments;
+
+        for count in [1, 7, result.step_count, u64::MAX] {
+            prover.reset();
+            prover.load_program(&program).unwrap();
+
+            while prover.step_num() < result.step_count {
+                let start = prover.step_num();
+                let proof = prover.prove_steps(count).unwrap();
+                let end = prover.step_num();
+                assert_eq!(end, start.saturating_add(count).min(result.step_count));
+
+                let mut verifier = Verifier::<u32>::from_proof(proof).unwrap();
+                assert_eq!(verifier.commit_step().unwrap(), steps[start as usize]);
+                assert_eq!(verifier.execute_steps(count).unwrap(), steps[end as usize]);
+            }
+        }
+
+        // Syscall witness is carried through aggregated proof
+        let input = Bytes::from_static(b"*woss load input");
+        let mut prover = Machine::<u64, ProverSMT>::builder(MEMORY_SIZE)
+            .syscall(Box::new(LoadInput::new(input.clone())))
+            .build()
+            .unwrap();
+        prover.load_program(&load_input_program()).unwrap();
+        let initial = prover.commit_step().unwrap();
+        let proof = prover.prove_steps(u64::MAX).unwrap();
+        let last = prover.commit_step().unwrap();
+        assert!(!proof.syscall_witness.is_empty());
+
+        let mut verifier = Verifier::<u64>::from_proof(proof).unwrap();
+        assert_eq!(verifier.commit_step().unwrap(), initial);
+        assert_eq!(verifier.execute_steps(u64::MAX).unwrap(), last);
+    }
+
+    #[test]
+    fn test_shallow_memory() {
+        let program = simple_program();
+        let mut prover = Machine::<u32, ShallowProverSMT>::new(MEMORY_SIZE);
+        prover.load_program(&program).unwrap();
+        let result = prover.run().unwrap();
+
+        let mut smt_prover = Machine::<u32, ProverSMT>::new(MEMORY_SIZE);
+        smt_prover.load_program(&program).unwrap();
+
+        prover.reset();
+        prover.load_program(&program).unwrap();
+
+        for window in result.step_commitments.windows(2) {
+            let proof = prover.prove_next_step().unwrap();
+            let smt_proof = smt_prover.prove_next_step().unwrap();
+            assert_eq!(proof.memory.kvs.len(), smt_proof.memory.kvs.len());
+
+            let mut verifier = Verifier::<u32, ShallowVerifierSMT>::from_proof(proof).unwrap();
+            assert_eq!(window[0], verifier.commit_step().unwrap());
+            assert_eq!(window[1], verifier.execute_next_step().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_key_hints() {
+        let program = simple_program();
+        let mut prover = Machine::<u32, ProverSMT>::new(MEMORY_SIZE);
+        prover.load_program(&program).unwrap();
+        let proof = prover.prove_steps(20).unwrap();
+        assert!(!proof.memory.hints.is_empty());
+        assert_eq!(proof.memory.hints.len(), proof.memory.kvs.len());
+        assert!(Verifier::<u32>::from_proof(proof.clone()).is_ok());
+
+        // Hints are optional
+        let mut no_hints = proof.clone();
+        no_hints.memory.hints.clear();
+        assert!(Verifier::<u32>::from_proof(no_hints).is_ok());
+
+        // Every key needs a matching hint
+        let mut missing = proof.clone();
+        missing.memory.hints.pop();
+        assert_eq!(
+            Verifier::<u32>::from_proof(missing).err(),
+            Some(Error::InvalidKeyHint)
+        );
+
+        let mut wrong = proof.clone();
+        wrong.memory.hints[0] = match wrong.memory.hints[0] {
+            KeyHint::DataChunk(chunk) => KeyHint::DataChunk(chunk + 1),
+            KeyHint::Flag(page) => KeyHint::Flag(page + 1),
+        };
+        assert_eq!(
+            Verifier::<u32>::from_proof(wrong).err(),
+            Some(Error::InvalidKeyHint)
+        );
+
+        // Unknown hint kind fails to decode rather than passing as another one
+        let packed: packed::StepProof32 = proof.pack();
+        let unknown = packed::KeyHint::new_builder()
+            .kind(2u8.into())
+            .index(0u64.pack())
+            .build();
+        let hints = packed::KeyHintVec::new_builder().push(unknown).build();
+        let memory = packed.memory().as_builder().hints(hints).build();
+        let packed = packed.as_builder().memory(memory).build();
+        assert_eq!(
+            TryUnpack::<StepProof<u32>>::try_unpack(&packed).err(),
+            Some(Error::Decode)
+        );
+
+        // Index keys
+        let mut prover = Machine::<u32, ShallowProverSMT>::new(MEMORY_SIZE);
+        prover.load_program(&program).unwrap();
+        let shallow_proof = prover.prove_steps(20).unwrap();
+        assert_eq!(shallow_proof.memory.hints.len(), proof.memory.hints.len());
+        assert!(Verifier::<u32, ShallowVerifierSMT>::from_proof(shallow_proof).is_ok());
+    }
+
+    fn execute_with_unused_keys<P, V>()
+    where
+        P: SMTProve + SMTOps + 'static,
+        V: SMTRestore + SMTOps + 'static,
+    {
+        let program = simple_program();
+        let mut prover = Machine::<u32, P>::new(MEMORY_SIZE);
+        prover.load_program(&program).unwrap();
+
+        // Find a step whose next step touches more keys
+        let (proof, broad) = loop {
+            let snapshot = prover.snapshot().unwrap();
+            let broad = prover.prove_steps(2).unwrap();
+            prover.restore_snapshot(&snapshot);
+            let proof = prover.prove_next_step().unwrap();
+            if broad.memory.kvs.len() > proof.memory.kvs.len() {
+                break (proof, broad);
+            }
+        };
+
+        let mut verifier = Verifier::<u32, V>::from_proof(proof).unwrap();
+        let step = verifier.execute_next_step().unwrap();
+
+        // Keys of both steps verify as a single step proof, but aren't all
+        // accessed by it
+        let mut verifier = Verifier::<u32, V>::from_proof(broad.clone()).unwrap();
+        assert_eq!(verifier.execute_next_step(), Err(Error::UnusedProofKey));
+
+        let mut verifier = Verifier::<u32, V>::from_proof(broad).unwrap();
+        assert_eq!(
+            verifier.execute_steps(2).unwrap().step_num,
+            step.step_num + 1
+        );
+    }
+
+    #[test]
+    fn test_unused_proof_keys() {
+        execute_with_unused_keys::<ProverSMT, VerifierSMT>();
+        execute_with_unused_keys::<ShallowProverSMT, ShallowVerifierSMT>();
+    }
+
+    // Drop each key of a step proof, remaining keys still verify against the
+    // root but executing the step must fail
+    fn execute_with_omitted_keys<P, V>()
+    where
+        P: SMTProve + SMTOps + 'static,
+        V: SMTRestore + SMTOps + 'static,
+    {
+        let mut prover = Machine::<u32, P>::new(MEMORY_SIZE);
+        prover.load_program(&simple_program()).unwrap();
+        prover.run_until_step(10).unwrap();
+        let snapshot = prover.snapshot().unwrap();
+        let proof = prover.prove_next_step().unwrap();
+
+        for key in proof.memory.kvs.keys() {
+            let mut omitted = proof.clone();
+            omitted.memory.kvs.remove(key);
+            omitted.memory.hints.clear();
+            let keys: Vec<Bytes32> = omitted.memory.kvs.keys().cloned().collect();
+            if keys.is_empty() {
+                // Nothing left to prove against the root
+                continue;
+            }
+            omitted.memory.merkle_proof = snapshot.memory.prove(&keys).unwrap();
+
+            let mut verifier = Machine::<u32, V>::restore_from_proof(omitted).unwrap();
+            let err = verifier.execute_next_step().unwrap_err();
+            assert_eq!(err, Error::UnprovenKey);
+        }
+    }
+
+    #[test]
+    fn test_omitted_proof_key() {
+        execute_with_omitted_keys::<ProverSMT, VerifierSMT>();
+        execute_with_omitted_keys::<ShallowProverSMT, ShallowVerifierSMT>();
+    }
+
+    #[test]
+    fn test_lazy_memory() {
+        let program = simple_program();
+        let mut smt_prover = Machine::<u32, ProverSMT>::new(MEMORY_SIZE);
+        smt_prover.load_program(&program).unwrap();
+        let result = smt_prover.run().unwrap();
+
+        let mut prover = Machine::<u32, LazySMT<ProverSMT>>::new(MEMORY_SIZE);
+        prover.load_program(&program).unwrap();
+        assert_eq!(
+            prover.run().unwrap().step_commitments,
+            result.step_commitments
+        );
+
+        // Proofs are the same, also after restoring a snapshot
+        smt_prover.reset();
+        smt_prover.load_program(&program).unwrap();
+        prover.reset();
+        prover.load_program(&program).unwrap();
+        let middle = result.step_count / 2;
+        prover.run_until_step(middle).unwrap();
+        let snapshot = prover.snapshot().unwrap();
+        prover.run().unwrap();
+        prover.restore_snapshot(&snapshot);
+        smt_prover.run_until_step(middle).unwrap();
+
+        for window in result.step_commitments[middle as usize..].windows(2) {
+            let proof = prover.prove_next_step().unwrap();
+            let smt_proof = smt_prover.prove_next_step().unwrap();
+            assert_eq!(proof.memory.kvs, smt_proof.memory.kvs);
+            assert_eq!(proof.memory.merkle_proof, smt_proof.memory.merkle_proof);
+
+            let mut verifier = Verifier::<u32>::from_proof(proof).unwrap();
+            assert_eq!(window[0], verifier.commit_step().unwrap());
+            assert_eq!(window[1], verifier.execute_next_step().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_load_input_syscall() {
+        let program = load_input_program();
+        // Program loads only the first 16 bytes
+        let input: Bytes = [b"*woss load input".as_slice(), &[7u8; 4096]]
+            .concat()
+            .into();
+        let new_prover = || {
+            Machine::<u64, ProverSMT>::builder(MEMORY_SIZE)
+                .syscall(Box::new(LoadInput::new(input.clone())))
+                .build()
+                .unwrap()
+        };
+
+        let mut prover = new_prover();
+        prover.load_program(&program).unwrap();
+        let result = prover.run().unwrap();
+        assert_eq!(prover.inner.exit_code(), i8::try_from(b'*').unwrap());
+
+        let mut prover = new_prover();
+        prover.load_program(&program).unwrap();
+
+        let mut ecall_steps = 0;
+        for window in result.step_commitments.windows(2) {
+            let proof = prover.prove_next_step().unwrap();
+            let syscalls = proof.syscalls.clone();
+            assert_eq!(syscalls.len(), 1);
+            assert_eq!(syscalls[0].number, LOAD_INPUT_SYSCALL_NUMBER);
+            assert_eq!(syscalls[0].commitment, LoadInput::hash_input(&input));
+
+            if !proof.syscall_witness.is_empty() {
+                ecall_steps += 1;
+                // Witness carries the first chunk and its proof
+                assert!(proof.syscall_witness.len() < 2 * INPUT_CHUNK_SIZE);
+                assert!(proof.syscall_witness.len() < input.len());
+
+                let mut forged_chunk = proof.clone();
+                let data_offset = 24;
+                forged_chunk.syscall_witness[data_offset] ^= 1;
+                let mut tampered = proof.clone();
+                tampered.syscall_witness = b"*forged input".to_vec();
+                for proof in [forged_chunk, tampered] {
+                    let mut verifier =
+                        Machine::<u64, VerifierSMT>::restore_from_proof(proof).unwrap();
+                    assert_eq!(window[0], verifier.commit_step().unwrap());
+                    assert_eq!(
+                        verifier.execute_next_step().unwrap_err(),
+                        Error::SyscallWitnessMismatch
+                    );
+                }
+            }
+
+            let mut verifier = Machine::<u64, VerifierSMT>::restore_from_proof(proof).unwrap();
+            assert_eq!(window[0], verifier.commit_step().unwrap());
+            verifier.execute_next_step().unwrap();
+            assert_eq!(window[1], verifier.commit_step().unwrap());
+        }
+        assert_eq!(ecall_steps, 1);
+
+        // Input is committed
+        let mut other = Machine::<u64, ProverSMT>::builder(MEMORY_SIZE)
+            .syscall(Box::new(LoadInput::new(Bytes::from_static(
+                b"*other input",
+            ))))
+            .build()
+            .unwrap();
+        other.load_program(&program).unwrap();
+        assert_ne!(other.commit_step().unwrap(), result.step_commitments[0]);
+
+        // Unregistered syscall
+        let mut machine = Machine::<u64, ProverSMT>::new(MEMORY_SIZE);
+        machine.load_program(&program).unwrap();
+        assert!(machine.run().is_err());
     }
 }
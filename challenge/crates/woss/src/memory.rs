@@ -1,22 +1,63 @@
-use crate::{collections::BTreeMap, marker::PhantomData, string::ToString, vec::Vec};
+use crate::{
+    collections::{btree_map, BTreeMap},
+    marker::PhantomData,
+    vec::Vec,
+};
 
 use ckb_vm::{
-    memory::{fill_page_data, get_page_indices, set_dirty},
-    Error, Memory, Register, RISCV_PAGESIZE,
+    memory::{get_page_indices, set_dirty},
+    Memory, Register, RISCV_PAGESIZE,
 };
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
-use crate::{common::blake2b, types::Bytes32};
+use crate::{
+    common::hash,
+    trace::{AccessLog, MemoryAccess},
+    types::Bytes32,
+    Error,
+};
+
+use self::flat::FlatCache;
 
+pub mod flat;
 #[cfg(feature = "std")]
 pub mod prover;
+pub mod shallow;
 pub mod verifier;
 
 pub trait SMTOps: Default {
+    /// Execute `SMTMemory` against a flat copy of memory, see `flat::LazySMT`
+    const FLAT_MEMORY: bool = false;
+
     fn update(&mut self, key: Bytes32, value: Bytes32) -> Result<(), Error>;
     fn get(&self, key: Bytes32) -> Result<Bytes32, Error>;
     fn root(&self) -> Result<Bytes32, Error>;
+
+    /// Update keys in one batch, keys must be distinct
+    fn update_all(&mut self, kvs: Vec<(Bytes32, Bytes32)>) -> Result<(), Error> {
+        kvs.into_iter().try_for_each(|(k, v)| self.update(k, v))
+    }
+
+    /// Called once on a new tree, before any other operation
+    fn set_memory_size(&mut self, _memory_size: usize) {}
+
+    fn flag_key(&self, page: u64) -> Bytes32 {
+        hash_flag_key(page)
+    }
+
+    /// Key of `chunk`th data chunk, chunk is `SMTMemory::DATA_CHUNK_SIZE` bytes
+    fn data_chunk_key(&self, chunk: u64) -> Bytes32 {
+        hash_data_chunk_key(chunk)
+    }
+}
+
+pub fn hash_flag_key(page: u64) -> Bytes32 {
+    hash([b"Flag", page.to_le_bytes().as_slice()]).into()
+}
+
+pub fn hash_data_chunk_key(chunk: u64) -> Bytes32 {
+    hash([b"Data", chunk.to_le_bytes().as_slice()]).into()
 }
 
 pub trait SMTProve: Sized {
@@ -25,7 +66,11 @@ pub trait SMTProve: Sized {
 }
 
 pub trait SMTRestore: Sized {
+    /// Only keys of proof can be accessed, others fail with `UnprovenKey`
     fn restore_from_proof(proof: MemoryProof) -> Result<Self, Error>;
+    /// Every key of proof must be accessed since restoring, otherwise
+    /// `UnusedProofKey`
+    fn check_proof_keys(&self) -> Result<(), Error>;
 }
 
 pub struct MemoryCommitment {
@@ -33,6 +78,15 @@ pub struct MemoryCommitment {
     pub root: Bytes32,
 }
 
+/// Preimage of a memory key
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyHint {
+    /// Chunk index, chunk is `SMTMemory::DATA_CHUNK_SIZE` bytes
+    DataChunk(u64),
+    /// Page of flag
+    Flag(u64),
+}
+
 #[serde_as]
 #[derive(Serialize, Deserialize, Clone)]
 pub struct MemoryProof {
@@ -41,11 +95,16 @@ pub struct MemoryProof {
     #[serde_as(as = "Vec<(_, _)>")]
     pub kvs: BTreeMap<Bytes32, Bytes32>,
     pub merkle_proof: Vec<u8>,
+    /// Hint of each key in `kvs`, in key order. Empty if proof carries no
+    /// hints, otherwise verifier recomputes every key from its hint.
+    #[serde(default)]
+    pub hints: Vec<KeyHint>,
 }
 
 pub struct MemoryTracer<S> {
     smt_snap: S,
     kvs: BTreeMap<Bytes32, Bytes32>,
+    hints: BTreeMap<Bytes32, KeyHint>,
 }
 
 impl<S> MemoryTracer<S> {
@@ -53,6 +112,7 @@ impl<S> MemoryTracer<S> {
         Self {
             smt_snap,
             kvs: Default::default(),
+            hints: Default::default(),
         }
     }
 }
@@ -60,6 +120,10 @@ impl<S> MemoryTracer<S> {
 pub struct SMTMemory<R, S> {
     smt: S,
     tracer: Option<MemoryTracer<S>>,
+    flat: Option<FlatCache>,
+    access_log: Option<AccessLog>,
+    /// Error of the last failed `Memory` call, see `take_error`
+    error: Option<Error>,
     memory_size: usize,
     riscv_pages: usize,
     _reg: PhantomData<R>,
@@ -68,16 +132,24 @@ pub struct SMTMemory<R, S> {
 impl<R: Register, S: SMTOps> SMTMemory<R, S> {
     pub const DATA_CHUNK_SIZE: u64 = 32;
 
-    pub fn flag_key(page: u64) -> Bytes32 {
-        blake2b([b"Flag", page.to_le_bytes().as_slice()]).into()
+    pub fn flag_key(&self, page: u64) -> Bytes32 {
+        self.smt.flag_key(page)
     }
 
-    pub fn data_chunk_key(addr: u64) -> Bytes32 {
-        let idx = addr / Self::DATA_CHUNK_SIZE;
-        blake2b([b"Data", idx.to_le_bytes().as_slice()]).into()
+    pub fn data_chunk_key(&self, addr: u64) -> Bytes32 {
+        self.smt.data_chunk_key(addr / Self::DATA_CHUNK_SIZE)
+    }
+
+    pub fn hint_key(&self, hint: KeyHint) -> Bytes32 {
+        match hint {
+            KeyHint::DataChunk(chunk) => self.smt.data_chunk_key(chunk),
+            KeyHint::Flag(page) => self.smt.flag_key(page),
+        }
     }
 
-    pub fn commit_memory(&self) -> Result<MemoryCommitment, Error> {
+    pub fn commit_memory(&mut self) -> Result<MemoryCommitment, Error> {
+        self.flush()?;
+
         let com = MemoryCommitment {
             size: self.memory_size as u64,
             root: self.smt.root()?,
@@ -86,12 +158,60 @@ impl<R: Register, S: SMTOps> SMTMemory<R, S> {
         Ok(com)
     }
 
+    /// Record data accesses until `take_access_log`
+    pub fn enable_access_log(&mut self) {
+        self.access_log = Some(AccessLog::default());
+    }
+
+    pub fn take_access_log(&mut self) -> Option<AccessLog> {
+        self.access_log.take()
+    }
+
+    /// Typed error behind the `ckb_vm::Error` returned by last failed
+    /// `Memory` call
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    // Run `f` for a `Memory` call, its error is stashed for `take_error`
+    fn raise<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, ckb_vm::Error> {
+        f(self).map_err(|err| {
+            let vm_err = err.to_vm();
+            self.error = Some(err);
+            vm_err
+        })
+    }
+
+    fn check_page(&self, page: u64) -> Result<(), Error> {
+        if page >= self.riscv_pages as u64 {
+            return Err(Error::MemOutOfBound);
+        }
+        Ok(())
+    }
+
     pub fn get_data(
         &mut self,
         addr: u64,
         buf: &mut [u8], // buf to fill
         data_size: u64,
     ) -> Result<(), Error> {
+        self.read_data(addr, buf, data_size)?;
+
+        if let Some(log) = self.access_log.as_mut() {
+            log.reads.push(MemoryAccess {
+                addr,
+                data: buf[..data_size as usize].to_vec(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Same as `get_data`, not logged
+    fn read_data(&mut self, addr: u64, buf: &mut [u8], data_size: u64) -> Result<(), Error> {
         check_addr(self, addr, data_size)?;
 
         let mut remain = data_size as usize;
@@ -99,7 +219,6 @@ impl<R: Register, S: SMTOps> SMTMemory<R, S> {
         let mut addr = addr;
 
         while remain > 0 {
-            let chunk_key = Self::data_chunk_key(addr).into();
             let chunk_offset = addr.rem_euclid(Self::DATA_CHUNK_SIZE) as usize;
             let chunk_available_size = Self::DATA_CHUNK_SIZE as usize - chunk_offset;
 
@@ -107,7 +226,7 @@ impl<R: Register, S: SMTOps> SMTMemory<R, S> {
             let start = buf_filled;
             let end = buf_filled + read_size;
 
-            let chunk = self.smt_get(chunk_key)?;
+            let chunk = self.read_chunk(addr / Self::DATA_CHUNK_SIZE)?;
             chunk.read_value(&mut buf[start..end], chunk_offset, read_size);
 
             remain = remain.saturating_sub(read_size);
@@ -119,55 +238,179 @@ impl<R: Register, S: SMTOps> SMTMemory<R, S> {
     }
 
     pub fn update_data(&mut self, addr: u64, value: &[u8]) -> Result<(), Error> {
-        check_addr(self, addr, value.len() as u64)?;
+        self.write_chunks(addr, value.len() as u64, |offset, buf| {
+            buf.copy_from_slice(&value[offset..offset + buf.len()])
+        })
+    }
 
-        let mut remain = value.len();
+    /// Set `size` bytes from `addr` to `value`
+    pub fn fill_data(&mut self, addr: u64, size: u64, value: u8) -> Result<(), Error> {
+        self.write_chunks(addr, size, |_, buf| buf.fill(value))
+    }
+
+    /// Write `size` bytes from `addr` chunk by chunk, `fill` writes bytes at
+    /// given offset from `addr` into buf. Fully overwritten chunks aren't
+    /// read, and all chunks are updated in one batch.
+    fn write_chunks(
+        &mut self,
+        addr: u64,
+        size: u64,
+        fill: impl Fn(usize, &mut [u8]),
+    ) -> Result<(), Error> {
+        check_addr(self, addr, size)?;
+
+        let start_addr = addr;
+        let mut remain = size as usize;
         let mut value_wrote = 0usize;
         let mut addr = addr;
+        let mut updates = Vec::new();
+        let mut logged = Vec::new();
 
         while remain > 0 {
-            let chunk_key = Self::data_chunk_key(addr).into();
+            let chunk_idx = addr / Self::DATA_CHUNK_SIZE;
             let chunk_offset = addr.rem_euclid(Self::DATA_CHUNK_SIZE) as usize;
             let chunk_available_size = Self::DATA_CHUNK_SIZE as usize - chunk_offset;
 
             let write_size = crate::cmp::min(remain, chunk_available_size);
-            let start = value_wrote;
-            let end = value_wrote + write_size;
-
-            let mut chunk = self.smt_get(chunk_key)?;
-            chunk.write_value(&value[start..end], chunk_offset);
-            self.smt_update(chunk_key, chunk)?;
+            let mut chunk = if write_size == Self::DATA_CHUNK_SIZE as usize {
+                if self.recording() {
+                    self.record(KeyHint::DataChunk(chunk_idx))?;
+                }
+                Bytes32::default()
+            } else {
+                self.read_chunk(chunk_idx)?
+            };
+
+            let mut buf = [0u8; 32];
+            fill(value_wrote, &mut buf[..write_size]);
+            chunk.write_value(&buf[..write_size], chunk_offset);
+            updates.push((chunk_idx, chunk));
+            if self.access_log.is_some() {
+                logged.extend_from_slice(&buf[..write_size]);
+            }
 
             remain = remain.saturating_sub(write_size);
             value_wrote = value_wrote.saturating_add(write_size);
             addr = addr.saturating_add(write_size as u64);
         }
 
-        Ok(())
+        if let Some(log) = self.access_log.as_mut() {
+            log.writes.push(MemoryAccess {
+                addr: start_addr,
+                data: logged,
+            });
+        }
+
+        match self.flat.as_mut() {
+            Some(flat) => {
+                for (chunk_idx, chunk) in updates {
+                    flat.store_chunk(chunk_idx, chunk);
+                }
+                Ok(())
+            }
+            None => {
+                let updates = updates
+                    .into_iter()
+                    .map(|(chunk_idx, chunk)| (self.smt.data_chunk_key(chunk_idx), chunk))
+                    .collect();
+                self.smt.update_all(updates)
+            }
+        }
     }
 
-    fn smt_get(&mut self, key: Bytes32) -> Result<Bytes32, Error> {
-        self.record(key)?;
+    fn read_chunk(&mut self, chunk_idx: u64) -> Result<Bytes32, Error> {
+        let cached = self.flat.as_ref().and_then(|flat| flat.chunk(chunk_idx));
+        match cached {
+            Some(chunk) if !self.recording() => Ok(chunk),
+            Some(chunk) => {
+                self.record(KeyHint::DataChunk(chunk_idx))?;
+                Ok(chunk)
+            }
+            None => {
+                let chunk = self.smt_get(KeyHint::DataChunk(chunk_idx))?;
+                if let Some(flat) = self.flat.as_mut() {
+                    flat.load_chunk(chunk_idx, chunk);
+                }
+                Ok(chunk)
+            }
+        }
+    }
 
-        self.smt
-            .get(key)
-            .map_err(|err| Error::Unexpected(err.to_string()))
+    fn read_flag(&mut self, page: u64) -> Result<u8, Error> {
+        let cached = self.flat.as_ref().and_then(|flat| flat.flag(page));
+        match cached {
+            Some(flag) if !self.recording() => Ok(flag),
+            Some(flag) => {
+                self.record(KeyHint::Flag(page))?;
+                Ok(flag)
+            }
+            None => {
+                let flag = self.smt_get(KeyHint::Flag(page))?.to_u8();
+                if let Some(flat) = self.flat.as_mut() {
+                    flat.load_flag(page, flag);
+                }
+                Ok(flag)
+            }
+        }
+    }
+
+    fn write_flag(&mut self, page: u64, flag: u8) -> Result<(), Error> {
+        match self.flat.as_mut() {
+            Some(flat) => {
+                flat.store_flag(page, flag);
+                Ok(())
+            }
+            None => self.smt_update(KeyHint::Flag(page), Bytes32::from_u8(flag)),
+        }
     }
 
-    fn smt_update(&mut self, key: Bytes32, value: Bytes32) -> Result<(), Error> {
-        self.record(key)?;
+    /// Write dirty chunks and flags of flat memory to the SMT
+    fn flush(&mut self) -> Result<(), Error> {
+        let flat = match self.flat.as_mut() {
+            Some(flat) => flat,
+            None => return Ok(()),
+        };
 
-        self.smt
-            .update(key.into(), value)
-            .map_err(|err| Error::Unexpected(err.to_string()))?;
-        Ok(())
+        let (chunks, pages) = flat.take_dirty();
+        let mut updates = Vec::with_capacity(chunks.len() + pages.len());
+        for (chunk_idx, chunk) in chunks {
+            updates.push((self.smt.data_chunk_key(chunk_idx), chunk));
+        }
+        for (page, flag) in pages {
+            updates.push((self.smt.flag_key(page), Bytes32::from_u8(flag)));
+        }
+
+        self.smt.update_all(updates)
+    }
+
+    fn smt_get(&mut self, hint: KeyHint) -> Result<Bytes32, Error> {
+        self.record(hint)?;
+
+        let key = self.hint_key(hint);
+        self.smt.get(key)
     }
 
-    fn record(&mut self, key: Bytes32) -> Result<(), Error> {
+    fn smt_update(&mut self, hint: KeyHint, value: Bytes32) -> Result<(), Error> {
+        self.record(hint)?;
+
+        let key = self.hint_key(hint);
+        self.smt.update(key, value)
+    }
+
+    fn recording(&self) -> bool {
+        self.tracer.is_some()
+    }
+
+    fn record(&mut self, hint: KeyHint) -> Result<(), Error> {
+        if !self.recording() {
+            return Ok(());
+        }
+
+        let key = self.hint_key(hint);
         if let Some(tracer) = self.tracer.as_mut() {
-            if !tracer.kvs.contains_key(&key) {
-                let value = self.smt.get(key)?;
-                tracer.kvs.insert(key, value);
+            if let btree_map::Entry::Vacant(entry) = tracer.kvs.entry(key) {
+                entry.insert(self.smt.get(key)?);
+                tracer.hints.insert(key, hint);
             }
         }
 
@@ -177,20 +420,66 @@ impl<R: Register, S: SMTOps> SMTMemory<R, S> {
 
 impl<R: Register, S: SMTRestore + SMTOps> SMTMemory<R, S> {
     pub fn restore_from_proof(&mut self, proof: MemoryProof) -> Result<(), Error> {
+        if !proof.hints.is_empty() {
+            self.check_hints(&proof)?;
+        }
+
         self.smt = S::restore_from_proof(proof)?;
         Ok(())
     }
+
+    /// See `SMTRestore::check_proof_keys`
+    pub fn check_proof_keys(&self) -> Result<(), Error> {
+        self.smt.check_proof_keys()
+    }
+
+    /// Every key must be recomputed from its hint, within memory
+    fn check_hints(&self, proof: &MemoryProof) -> Result<(), Error> {
+        if proof.hints.len() != proof.kvs.len() {
+            return Err(Error::InvalidKeyHint);
+        }
+
+        let chunks = self.memory_size as u64 / Self::DATA_CHUNK_SIZE;
+        for (key, hint) in proof.kvs.keys().zip(proof.hints.iter()) {
+            let in_memory = match *hint {
+                KeyHint::DataChunk(chunk) => chunk < chunks,
+                KeyHint::Flag(page) => page < self.riscv_pages as u64,
+            };
+            if !in_memory || self.hint_key(*hint) != *key {
+                return Err(Error::InvalidKeyHint);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<R: Register, S: SMTProve + SMTOps> SMTMemory<R, S> {
-    pub fn enable_tracer(&mut self) {
-        self.tracer = Some(MemoryTracer::new(self.smt.snap()))
+    pub fn enable_tracer(&mut self) -> Result<(), Error> {
+        // Traced values are read from the SMT
+        self.flush()?;
+        self.tracer = Some(MemoryTracer::new(self.smt.snap()));
+        Ok(())
     }
 
     pub fn disable_tracer(&mut self) {
         self.tracer = None
     }
 
+    pub fn snap(&mut self) -> Result<S, Error> {
+        self.flush()?;
+        Ok(self.smt.snap())
+    }
+
+    /// Replace memory with a snapshot, tracer is disabled
+    pub fn restore_snap(&mut self, smt: S) {
+        self.smt = smt;
+        self.tracer = None;
+        if let Some(flat) = self.flat.as_mut() {
+            flat.invalidate();
+        }
+    }
+
     pub fn prove_traces(&self) -> Result<Option<MemoryProof>, Error> {
         let tracer = match self.tracer.as_ref() {
             Some(tracer) => tracer,
@@ -203,6 +492,7 @@ impl<R: Register, S: SMTProve + SMTOps> SMTMemory<R, S> {
             memory_size: self.memory_size,
             kvs: tracer.kvs.clone(),
             merkle_proof: tracer.smt_snap.prove(&keys)?,
+            hints: tracer.hints.values().cloned().collect(),
         };
 
         Ok(Some(proof))
@@ -215,11 +505,17 @@ impl<R: Register, S: SMTOps> Memory for SMTMemory<R, S> {
     fn new(memory_size: usize) -> Self {
         assert!(memory_size % RISCV_PAGESIZE == 0);
 
+        let mut smt = S::default();
+        smt.set_memory_size(memory_size);
+
         Self {
-            smt: S::default(),
+            smt,
             memory_size,
             riscv_pages: memory_size / RISCV_PAGESIZE,
             tracer: None,
+            flat: S::FLAT_MEMORY.then(|| FlatCache::new(memory_size)),
+            access_log: None,
+            error: None,
             _reg: PhantomData,
         }
     }
@@ -231,159 +527,140 @@ impl<R: Register, S: SMTOps> Memory for SMTMemory<R, S> {
         _flags: u8,
         source: Option<ckb_vm::Bytes>,
         offset_from_addr: u64,
-    ) -> Result<(), Error> {
-        fill_page_data(self, addr, size, source, offset_from_addr)
+    ) -> Result<(), ckb_vm::Error> {
+        if size == 0 {
+            return Ok(());
+        }
+
+        // Same bytes and dirty pages as `fill_page_data`, which zeroes
+        // `offset_from_addr` bytes then copies source and zeroes the rest,
+        // but in a single batched update
+        let page_indices = get_page_indices(addr, size)?;
+        set_dirty(self, &page_indices)?;
+
+        let source = source.unwrap_or_default();
+        let source_start = offset_from_addr.min(size) as usize;
+        let source_end = (source_start + source.len()).min(size as usize);
+        self.raise(|mem| {
+            mem.write_chunks(addr, size, |offset, buf| {
+                for (idx, b) in buf.iter_mut().enumerate() {
+                    let pos = offset + idx;
+                    *b = if (source_start..source_end).contains(&pos) {
+                        source[pos - source_start]
+                    } else {
+                        0
+                    };
+                }
+            })
+        })
     }
 
     fn memory_size(&self) -> usize {
-        self.memory_size as usize
+        self.memory_size
     }
 
-    fn fetch_flag(&mut self, page: u64) -> Result<u8, Error> {
-        if page < self.riscv_pages as u64 {
-            Ok(self.smt_get(Self::flag_key(page))?.to_u8())
-        } else {
-            Err(Error::MemOutOfBound)
-        }
+    fn fetch_flag(&mut self, page: u64) -> Result<u8, ckb_vm::Error> {
+        self.raise(|mem| {
+            mem.check_page(page)?;
+            mem.read_flag(page)
+        })
     }
 
-    fn set_flag(&mut self, page: u64, flag: u8) -> Result<(), Error> {
-        if page < self.riscv_pages as u64 {
-            let key = Self::flag_key(page);
-            let flag = self.smt_get(key)?.to_u8() | flag;
-            self.smt_update(key, Bytes32::from_u8(flag))
-        } else {
-            Err(Error::MemOutOfBound)
-        }
+    fn set_flag(&mut self, page: u64, flag: u8) -> Result<(), ckb_vm::Error> {
+        self.raise(|mem| {
+            mem.check_page(page)?;
+            let flag = mem.read_flag(page)? | flag;
+            mem.write_flag(page, flag)
+        })
     }
 
-    fn clear_flag(&mut self, page: u64, flag: u8) -> Result<(), Error> {
-        if page < self.riscv_pages as u64 {
-            let key = Self::flag_key(page);
-            let flag = self.smt_get(key)?.to_u8() & !flag;
-            self.smt_update(key, Bytes32::from_u8(flag))
-        } else {
-            Err(Error::MemOutOfBound)
-        }
+    fn clear_flag(&mut self, page: u64, flag: u8) -> Result<(), ckb_vm::Error> {
+        self.raise(|mem| {
+            mem.check_page(page)?;
+            let flag = mem.read_flag(page)? & !flag;
+            mem.write_flag(page, flag)
+        })
     }
 
-    fn execute_load16(&mut self, addr: u64) -> Result<u16, Error> {
-        self.load16(&Self::REG::from_u64(addr)).map(|v| v.to_u16())
+    // Instruction fetch isn't logged as data access
+    fn execute_load16(&mut self, addr: u64) -> Result<u16, ckb_vm::Error> {
+        let mut buf = [0u8; 2];
+        self.raise(|mem| mem.read_data(addr, &mut buf, 2))?;
+        Ok(u16::from_le_bytes(buf))
     }
 
-    fn execute_load32(&mut self, addr: u64) -> Result<u32, Error> {
-        self.load32(&R::from_u64(addr)).map(|v| v.to_u32())
+    fn execute_load32(&mut self, addr: u64) -> Result<u32, ckb_vm::Error> {
+        let mut buf = [0u8; 4];
+        self.raise(|mem| mem.read_data(addr, &mut buf, 4))?;
+        Ok(u32::from_le_bytes(buf))
     }
 
-    fn load8(&mut self, addr: &Self::REG) -> Result<Self::REG, Error> {
-        let addr = addr.to_u64();
-        check_addr(self, addr, 1)?;
-
+    fn load8(&mut self, addr: &Self::REG) -> Result<Self::REG, ckb_vm::Error> {
         let mut buf = [0u8];
-        self.get_data(addr, &mut buf, 1)?;
-
+        self.raise(|mem| mem.get_data(addr.to_u64(), &mut buf, 1))?;
         Ok(Self::REG::from_u8(buf[0]))
     }
 
-    fn load16(&mut self, addr: &Self::REG) -> Result<Self::REG, Error> {
-        let addr = addr.to_u64();
-        check_addr(self, addr, 2)?;
-
+    fn load16(&mut self, addr: &Self::REG) -> Result<Self::REG, ckb_vm::Error> {
         let mut buf = [0u8; 2];
-        self.get_data(addr, &mut buf, 2)?;
-        let v = u16::from_le_bytes(buf);
-
-        Ok(Self::REG::from_u16(v))
+        self.raise(|mem| mem.get_data(addr.to_u64(), &mut buf, 2))?;
+        Ok(Self::REG::from_u16(u16::from_le_bytes(buf)))
     }
 
-    fn load32(&mut self, addr: &Self::REG) -> Result<Self::REG, Error> {
-        let addr = addr.to_u64();
-        check_addr(self, addr, 4)?;
-
+    fn load32(&mut self, addr: &Self::REG) -> Result<Self::REG, ckb_vm::Error> {
         let mut buf = [0u8; 4];
-        self.get_data(addr, &mut buf, 4)?;
-        let v = u32::from_le_bytes(buf);
-
-        Ok(Self::REG::from_u32(v))
+        self.raise(|mem| mem.get_data(addr.to_u64(), &mut buf, 4))?;
+        Ok(Self::REG::from_u32(u32::from_le_bytes(buf)))
     }
 
-    fn load64(&mut self, addr: &Self::REG) -> Result<Self::REG, Error> {
-        let addr = addr.to_u64();
-        check_addr(self, addr, 8)?;
-
+    fn load64(&mut self, addr: &Self::REG) -> Result<Self::REG, ckb_vm::Error> {
         let mut buf = [0u8; 8];
-        self.get_data(addr, &mut buf, 8)?;
-        let v = u64::from_le_bytes(buf);
-
-        Ok(Self::REG::from_u64(v))
+        self.raise(|mem| mem.get_data(addr.to_u64(), &mut buf, 8))?;
+        Ok(Self::REG::from_u64(u64::from_le_bytes(buf)))
     }
 
-    fn store8(&mut self, addr: &Self::REG, value: &Self::REG) -> Result<(), Error> {
-        let addr = addr.to_u64();
-        let page_indices = get_page_indices(addr, 1)?;
-        set_dirty(self, &page_indices)?;
-        self.update_data(addr, &[value.to_u8()])
+    fn store8(&mut self, addr: &Self::REG, value: &Self::REG) -> Result<(), ckb_vm::Error> {
+        self.store_bytes(addr.to_u64(), &[value.to_u8()])
     }
 
-    fn store16(&mut self, addr: &Self::REG, value: &Self::REG) -> Result<(), Error> {
-        let addr = addr.to_u64();
-        let page_indices = get_page_indices(addr, 2)?;
-        set_dirty(self, &page_indices)?;
-        self.update_data(addr, value.to_u16().to_le_bytes().as_slice())
+    fn store16(&mut self, addr: &Self::REG, value: &Self::REG) -> Result<(), ckb_vm::Error> {
+        self.store_bytes(addr.to_u64(), &value.to_u16().to_le_bytes())
     }
 
-    fn store32(&mut self, addr: &Self::REG, value: &Self::REG) -> Result<(), Error> {
-        let addr = addr.to_u64();
-        let page_indices = get_page_indices(addr, 4)?;
-        set_dirty(self, &page_indices)?;
-        self.update_data(addr, value.to_u32().to_le_bytes().as_slice())
+    fn store32(&mut self, addr: &Self::REG, value: &Self::REG) -> Result<(), ckb_vm::Error> {
+        self.store_bytes(addr.to_u64(), &value.to_u32().to_le_bytes())
     }
 
-    fn store64(&mut self, addr: &Self::REG, value: &Self::REG) -> Result<(), Error> {
-        let addr = addr.to_u64();
-        let page_indices = get_page_indices(addr, 8)?;
-        set_dirty(self, &page_indices)?;
-        self.update_data(addr, value.to_u64().to_le_bytes().as_slice())
+    fn store64(&mut self, addr: &Self::REG, value: &Self::REG) -> Result<(), ckb_vm::Error> {
+        self.store_bytes(addr.to_u64(), &value.to_u64().to_le_bytes())
     }
 
-    fn store_bytes(&mut self, addr: u64, value: &[u8]) -> Result<(), Error> {
+    fn store_bytes(&mut self, addr: u64, value: &[u8]) -> Result<(), ckb_vm::Error> {
         let size = value.len() as u64;
         if size == 0 {
             return Ok(());
         }
-        let page_indices = get_page_indices(addr.to_u64(), size)?;
+        let page_indices = get_page_indices(addr, size)?;
         set_dirty(self, &page_indices)?;
-        self.update_data(addr, value)
+        self.raise(|mem| mem.update_data(addr, value))
     }
 
-    fn store_byte(&mut self, addr: u64, size: u64, value: u8) -> Result<(), Error> {
+    fn store_byte(&mut self, addr: u64, size: u64, value: u8) -> Result<(), ckb_vm::Error> {
         if size == 0 {
             return Ok(());
         }
-        let page_indices = get_page_indices(addr.to_u64(), size)?;
+        let page_indices = get_page_indices(addr, size)?;
         set_dirty(self, &page_indices)?;
-
-        // TODO: optimize
-        let mut remain = size;
-        let mut addr = addr;
-
-        while remain > 0 {
-            self.update_data(addr, &[value])?;
-
-            remain -= 1;
-            addr += 1;
-        }
-
-        Ok(())
+        self.raise(|mem| mem.fill_data(addr, size, value))
     }
 
-    fn load_bytes(&mut self, addr: u64, size: u64) -> Result<ckb_vm::Bytes, Error> {
-        check_addr(self, addr, size)?;
-
+    fn load_bytes(&mut self, addr: u64, size: u64) -> Result<ckb_vm::Bytes, ckb_vm::Error> {
         let mut buf = ckb_vm::bytes::BytesMut::new();
-        buf.resize(size as usize, 0);
-        self.get_data(addr, buf.as_mut(), size)?;
-
+        self.raise(|mem| {
+            check_addr(mem, addr, size)?;
+            buf.resize(size as usize, 0);
+            mem.get_data(addr, buf.as_mut(), size)
+        })?;
         Ok(buf.freeze())
     }
 }
@@ -399,7 +676,10 @@ pub fn check_addr<M: Memory>(mem: &mut M, addr: u64, offset: u64) -> Result<(),
 
 #[cfg(test)]
 mod tests {
-    use ckb_vm::{memory::get_page_indices, FlatMemory, Memory, RISCV_MAX_MEMORY, RISCV_PAGESIZE};
+    use ckb_vm::{
+        memory::{fill_page_data, get_page_indices},
+        Bytes, FlatMemory, Memory, RISCV_MAX_MEMORY, RISCV_PAGESIZE,
+    };
     use proptest::prelude::*;
 
     use super::{prover::ProverSMT, SMTMemory};
@@ -509,7 +789,6 @@ mod tests {
             let mut smt_mem = SMTMemory::<u64, ProverSMT>::new(RISCV_MAX_MEMORY);
             let mut flat_mem = FlatMemory::<u64>::new(RISCV_MAX_MEMORY);
 
-            let value = value as u64;
             smt_mem.store64(&addr, &value).unwrap();
             flat_mem.store64(&addr, &value).unwrap();
 
@@ -530,7 +809,7 @@ mod tests {
         #[test]
         fn test_store_byte(
             addr in (0..=(RISCV_MAX_MEMORY - 100) as u64),
-            size in (0..=100 as u64),
+            size in (0..=100_u64),
             value in any::<u8>()
         ) {
             let mut smt_mem = SMTMemory::<u64, ProverSMT>::new(RISCV_MAX_MEMORY);
@@ -577,5 +856,44 @@ mod tests {
                 flat_mem.load_bytes(addr, bytes.len() as u64).unwrap()
             );
         }
+
+        #[test]
+        fn test_init_pages(
+            page in (0..(RISCV_MAX_MEMORY / RISCV_PAGESIZE - 2) as u64),
+            size in (0..=(RISCV_PAGESIZE * 2) as u64),
+            offset_from_addr in (0..=100_u64),
+            source in proptest::collection::vec(any::<u8>(), 0..5000),
+        ) {
+            let addr = page * RISCV_PAGESIZE as u64;
+            let source = Bytes::from(source);
+            let mut smt_mem = SMTMemory::<u64, ProverSMT>::new(RISCV_MAX_MEMORY);
+            let mut flat_mem = FlatMemory::<u64>::new(RISCV_MAX_MEMORY);
+            let mut filled_mem = SMTMemory::<u64, ProverSMT>::new(RISCV_MAX_MEMORY);
+
+            // Make sure zero fill overwrites existing data
+            let garbage = [0xffu8; RISCV_PAGESIZE * 2];
+            smt_mem.store_bytes(addr, &garbage).unwrap();
+            flat_mem.store_bytes(addr, &garbage).unwrap();
+            filled_mem.store_bytes(addr, &garbage).unwrap();
+
+            smt_mem.init_pages(addr, size, 0, Some(source.clone()), offset_from_addr).unwrap();
+            flat_mem.init_pages(addr, size, 0, Some(source.clone()), offset_from_addr).unwrap();
+            fill_page_data(&mut filled_mem, addr, size, Some(source), offset_from_addr).unwrap();
+
+            assert_eq!(
+                smt_mem.commit_memory().unwrap().root,
+                filled_mem.commit_memory().unwrap().root
+            );
+            assert_eq!(
+                smt_mem.load_bytes(addr, garbage.len() as u64).unwrap(),
+                flat_mem.load_bytes(addr, garbage.len() as u64).unwrap()
+            );
+            for page in page..page + 2 {
+                assert_eq!(
+                    smt_mem.fetch_flag(page).unwrap(),
+                    flat_mem.fetch_flag(page).unwrap()
+                );
+            }
+        }
     }
 }
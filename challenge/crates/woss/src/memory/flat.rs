@@ -0,0 +1,245 @@
+//! Flat copy of memory for provers.
+//!
+//! `SMTMemory` over `LazySMT<S>` executes loads and stores against a flat
+//! copy of memory and records dirty chunks and page flags. They are written
+//! to `S` in one batch when memory is committed, snapshotted or traced, so
+//! roots are identical to `SMTMemory` over `S`.
+//!
+//! After restoring a snapshot, chunks are read from the SMT on first access.
+
+use ckb_vm::RISCV_PAGESIZE;
+
+use crate::{
+    collections::{BTreeMap, BTreeSet},
+    types::Bytes32,
+    vec::Vec,
+    Error,
+};
+
+use super::{SMTOps, SMTProve};
+
+const CHUNK_SIZE: usize = 32;
+
+/// Memory executed against a flat copy, see module docs
+#[derive(Default)]
+pub struct LazySMT<S>(S);
+
+impl<S> LazySMT<S> {
+    pub fn inner(&self) -> &S {
+        &self.0
+    }
+}
+
+impl<S: SMTOps> SMTOps for LazySMT<S> {
+    const FLAT_MEMORY: bool = true;
+
+    fn update(&mut self, key: Bytes32, value: Bytes32) -> Result<(), Error> {
+        self.0.update(key, value)
+    }
+
+    fn get(&self, key: Bytes32) -> Result<Bytes32, Error> {
+        self.0.get(key)
+    }
+
+    fn root(&self) -> Result<Bytes32, Error> {
+        self.0.root()
+    }
+
+    fn update_all(&mut self, kvs: Vec<(Bytes32, Bytes32)>) -> Result<(), Error> {
+        self.0.update_all(kvs)
+    }
+
+    fn set_memory_size(&mut self, memory_size: usize) {
+        self.0.set_memory_size(memory_size)
+    }
+
+    fn flag_key(&self, page: u64) -> Bytes32 {
+        self.0.flag_key(page)
+    }
+
+    fn data_chunk_key(&self, chunk: u64) -> Bytes32 {
+        self.0.data_chunk_key(chunk)
+    }
+}
+
+impl<S: SMTProve> SMTProve for LazySMT<S> {
+    fn snap(&self) -> Self {
+        LazySMT(self.0.snap())
+    }
+
+    fn prove(&self, keys: &[Bytes32]) -> Result<Vec<u8>, Error> {
+        self.0.prove(keys)
+    }
+}
+
+pub struct FlatCache {
+    data: Vec<u8>,
+    flags: Vec<u8>,
+    loaded_chunks: Vec<bool>,
+    loaded_pages: Vec<bool>,
+    dirty_chunks: BTreeSet<u64>,
+    dirty_pages: BTreeSet<u64>,
+}
+
+impl FlatCache {
+    /// Cache of empty memory, every chunk is loaded as zero
+    pub fn new(memory_size: usize) -> Self {
+        let pages = memory_size / RISCV_PAGESIZE;
+        let chunks = memory_size / CHUNK_SIZE;
+
+        FlatCache {
+            data: vec![0; memory_size],
+            flags: vec![0; pages],
+            loaded_chunks: vec![true; chunks],
+            loaded_pages: vec![true; pages],
+            dirty_chunks: BTreeSet::new(),
+            dirty_pages: BTreeSet::new(),
+        }
+    }
+
+    /// Drop cached memory, chunks are loaded again from the SMT
+    pub fn invalidate(&mut self) {
+        self.loaded_chunks.iter_mut().for_each(|l| *l = false);
+        self.loaded_pages.iter_mut().for_each(|l| *l = false);
+        self.dirty_chunks.clear();
+        self.dirty_pages.clear();
+    }
+
+    pub fn chunk(&self, chunk: u64) -> Option<Bytes32> {
+        let idx = chunk as usize;
+        if !self.loaded_chunks[idx] {
+            return None;
+        }
+
+        let mut value = [0u8; CHUNK_SIZE];
+        value.copy_from_slice(&self.data[idx * CHUNK_SIZE..(idx + 1) * CHUNK_SIZE]);
+        Some(value.into())
+    }
+
+    pub fn load_chunk(&mut self, chunk: u64, value: Bytes32) {
+        let idx = chunk as usize;
+        self.data[idx * CHUNK_SIZE..(idx + 1) * CHUNK_SIZE].copy_from_slice(&value);
+        self.loaded_chunks[idx] = true;
+    }
+
+    pub fn store_chunk(&mut self, chunk: u64, value: Bytes32) {
+        self.load_chunk(chunk, value);
+        self.dirty_chunks.insert(chunk);
+    }
+
+    pub fn flag(&self, page: u64) -> Option<u8> {
+        let idx = page as usize;
+        self.loaded_pages[idx].then(|| self.flags[idx])
+    }
+
+    pub fn load_flag(&mut self, page: u64, flag: u8) {
+        let idx = page as usize;
+        self.flags[idx] = flag;
+        self.loaded_pages[idx] = true;
+    }
+
+    pub fn store_flag(&mut self, page: u64, flag: u8) {
+        self.load_flag(page, flag);
+        self.dirty_pages.insert(page);
+    }
+
+    /// Take dirty chunks and page flags, in chunk and page order
+    pub fn take_dirty(&mut self) -> (BTreeMap<u64, Bytes32>, BTreeMap<u64, u8>) {
+        let chunks = core::mem::take(&mut self.dirty_chunks)
+            .into_iter()
+            .map(|chunk| (chunk, self.chunk(chunk).expect("dirty chunk loaded")))
+            .collect();
+        let pages = core::mem::take(&mut self.dirty_pages)
+            .into_iter()
+            .map(|page| (page, self.flags[page as usize]))
+            .collect();
+
+        (chunks, pages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_vm::{Memory, RISCV_PAGESIZE};
+    use proptest::prelude::*;
+
+    use super::LazySMT;
+    use crate::memory::{prover::ProverSMT, SMTMemory};
+
+    const MEMORY_SIZE: usize = 4 << 20;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Store64(u64, u64),
+        StoreByte(u64, u64, u8),
+        SetFlag(u64, u8),
+        ClearFlag(u64, u8),
+        Commit,
+        Restore,
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        let addr = 0..(MEMORY_SIZE - 200) as u64;
+        let page = 0..(MEMORY_SIZE / RISCV_PAGESIZE) as u64;
+        prop_oneof![
+            (addr.clone(), any::<u64>()).prop_map(|(a, v)| Op::Store64(a, v)),
+            (addr, 0..200u64, any::<u8>()).prop_map(|(a, s, v)| Op::StoreByte(a, s, v)),
+            (page.clone(), any::<u8>()).prop_map(|(p, f)| Op::SetFlag(p, f)),
+            (page, any::<u8>()).prop_map(|(p, f)| Op::ClearFlag(p, f)),
+            Just(Op::Commit),
+            Just(Op::Restore),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_same_root(ops in proptest::collection::vec(op(), 1..50)) {
+            let mut smt_mem = SMTMemory::<u64, ProverSMT>::new(MEMORY_SIZE);
+            let mut lazy_mem = SMTMemory::<u64, LazySMT<ProverSMT>>::new(MEMORY_SIZE);
+
+            for op in ops {
+                match op {
+                    Op::Store64(addr, value) => {
+                        smt_mem.store64(&addr, &value).unwrap();
+                        lazy_mem.store64(&addr, &value).unwrap();
+                        prop_assert_eq!(smt_mem.load64(&addr).unwrap(), lazy_mem.load64(&addr).unwrap());
+                    }
+                    Op::StoreByte(addr, size, value) => {
+                        smt_mem.store_byte(addr, size, value).unwrap();
+                        lazy_mem.store_byte(addr, size, value).unwrap();
+                        prop_assert_eq!(
+                            smt_mem.load_bytes(addr, 200).unwrap(),
+                            lazy_mem.load_bytes(addr, 200).unwrap()
+                        );
+                    }
+                    Op::SetFlag(page, flag) => {
+                        smt_mem.set_flag(page, flag).unwrap();
+                        lazy_mem.set_flag(page, flag).unwrap();
+                        prop_assert_eq!(smt_mem.fetch_flag(page).unwrap(), lazy_mem.fetch_flag(page).unwrap());
+                    }
+                    Op::ClearFlag(page, flag) => {
+                        smt_mem.clear_flag(page, flag).unwrap();
+                        lazy_mem.clear_flag(page, flag).unwrap();
+                        prop_assert_eq!(smt_mem.fetch_flag(page).unwrap(), lazy_mem.fetch_flag(page).unwrap());
+                    }
+                    Op::Commit => {
+                        prop_assert_eq!(
+                            smt_mem.commit_memory().unwrap().root,
+                            lazy_mem.commit_memory().unwrap().root
+                        );
+                    }
+                    Op::Restore => {
+                        // Reload cache from SMT
+                        let snap = lazy_mem.snap().unwrap();
+                        lazy_mem.restore_snap(snap);
+                    }
+                }
+            }
+
+            prop_assert_eq!(
+                smt_mem.commit_memory().unwrap().root,
+                lazy_mem.commit_memory().unwrap().root
+            );
+        }
+    }
+}
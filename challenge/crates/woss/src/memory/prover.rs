@@ -1,32 +1,34 @@
-use blake2b_ref::{Blake2b, Blake2bBuilder};
-use ckb_vm::Error;
 use im::HashMap;
 use sparse_merkle_tree::{
-    traits::{Hasher, StoreReadOps, StoreWriteOps},
+    traits::{StoreReadOps, StoreWriteOps},
     BranchKey, BranchNode, SparseMerkleTree, H256,
 };
 
-use crate::types::Bytes32;
+use crate::{common::MemoryHasher, types::Bytes32, Error};
 
 use super::{SMTOps, SMTProve};
 
-pub type ProverSMT = SparseMerkleTree<CkbBlake2bHasher, Bytes32, Store>;
+pub type ProverSMT = SparseMerkleTree<MemoryHasher, Bytes32, Store>;
 
 impl SMTOps for ProverSMT {
     fn update(&mut self, key: Bytes32, value: Bytes32) -> Result<(), Error> {
-        self.update(key.into(), value)
-            .map_err(|err| Error::Unexpected(err.to_string()))?;
+        self.update(key.into(), value).map_err(|_| Error::Smt)?;
         Ok(())
     }
 
     fn get(&self, key: Bytes32) -> Result<Bytes32, Error> {
-        self.get(&key.into())
-            .map_err(|err| Error::Unexpected(err.to_string()))
+        self.get(&key.into()).map_err(|_| Error::Smt)
     }
 
     fn root(&self) -> Result<Bytes32, Error> {
         Ok((*self.root()).into())
     }
+
+    fn update_all(&mut self, kvs: Vec<(Bytes32, Bytes32)>) -> Result<(), Error> {
+        let leaves = kvs.into_iter().map(|(k, v)| (k.into(), v)).collect();
+        self.update_all(leaves).map_err(|_| Error::Smt)?;
+        Ok(())
+    }
 }
 
 impl SMTProve for ProverSMT {
@@ -37,48 +39,31 @@ impl SMTProve for ProverSMT {
 
     fn prove(&self, keys: &[Bytes32]) -> Result<Vec<u8>, Error> {
         let keys: Vec<H256> = keys.iter().map(|key| (*key).into()).collect();
-        let proof = self
-            .merkle_proof(keys.clone())
-            .map_err(|err| Error::Unexpected(err.to_string()))?;
-
-        let compiled = proof
-            .compile(keys.clone())
-            .map_err(|err| Error::Unexpected(err.to_string()))?;
+        let proof = self.merkle_proof(keys.clone()).map_err(|_| Error::Smt)?;
+        let compiled = proof.compile(keys).map_err(|_| Error::Smt)?;
 
         Ok(compiled.0)
     }
 }
 
-pub struct CkbBlake2bHasher(Blake2b);
-
-impl Default for CkbBlake2bHasher {
-    fn default() -> Self {
-        // NOTE: here we not set the `personal` since ckb_smt.c linked blake2b implementation from blake2b-rs
-        let blake2b = Blake2bBuilder::new(32)
-            .personal(b"ckb-default-hash")
-            .build();
-        CkbBlake2bHasher(blake2b)
-    }
+#[derive(Default, Clone)]
+pub struct Store {
+    branches: HashMap<BranchKey, BranchNode>,
+    leaves: HashMap<H256, Bytes32>,
 }
 
-impl Hasher for CkbBlake2bHasher {
-    fn write_byte(&mut self, b: u8) {
-        self.0.update(&[b][..]);
-    }
-    fn write_h256(&mut self, h: &H256) {
-        self.0.update(h.as_slice());
+impl Store {
+    pub fn new(branches: HashMap<BranchKey, BranchNode>, leaves: HashMap<H256, Bytes32>) -> Self {
+        Self { branches, leaves }
     }
-    fn finish(self) -> H256 {
-        let mut hash = [0u8; 32];
-        self.0.finalize(&mut hash);
-        hash.into()
+
+    pub fn branches(&self) -> &HashMap<BranchKey, BranchNode> {
+        &self.branches
     }
-}
 
-#[derive(Default, Clone)]
-pub struct Store {
-    branches: HashMap<BranchKey, BranchNode>,
-    leaves: HashMap<H256, Bytes32>,
+    pub fn leaves(&self) -> &HashMap<H256, Bytes32> {
+        &self.leaves
+    }
 }
 
 impl StoreReadOps<Bytes32> for Store {
@@ -0,0 +1,423 @@
+//! Memory tree keyed directly by chunk index.
+//!
+//! Leaves are laid out as data chunks followed by page flags, tree depth is
+//! the smallest one holding all of them, for example 18 for 4MB memory
+//! instead of 256 levels of hashed keys.
+//!
+//! Empty subtree hashes to zero, other nodes hash to `hash(left | right)`.
+//!
+//! Proof lists siblings which can't be computed from proven leaves, bottom up
+//! and left to right in each level. Each sibling is `0` for empty subtree, or
+//! `1 | hash`.
+
+use ckb_vm::RISCV_PAGESIZE;
+
+use crate::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+use crate::{common::hash, types::Bytes32, Error};
+
+use super::{MemoryProof, SMTMemory, SMTOps, SMTRestore};
+
+const EMPTY_SIBLING: u8 = 0;
+const SIBLING: u8 = 1;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Layout {
+    depth: u8,
+    data_chunks: u64,
+}
+
+impl Layout {
+    fn new(memory_size: usize) -> Self {
+        let data_chunks =
+            memory_size as u64 / SMTMemory::<u64, ShallowVerifierSMT>::DATA_CHUNK_SIZE;
+        let pages = (memory_size / RISCV_PAGESIZE) as u64;
+        let leaves = data_chunks + pages;
+
+        let mut depth = 1;
+        while depth < 63 && (1u64 << depth) < leaves {
+            depth += 1;
+        }
+
+        Self { depth, data_chunks }
+    }
+
+    fn flag_key(&self, page: u64) -> Bytes32 {
+        Bytes32::from_u64(self.data_chunks + page)
+    }
+
+    fn data_chunk_key(&self, chunk: u64) -> Bytes32 {
+        Bytes32::from_u64(chunk)
+    }
+
+    fn index(&self, key: &Bytes32) -> Result<u64, Error> {
+        let index = key.to_u64();
+        if key[8..].iter().any(|b| *b != 0) || index >> self.depth != 0 {
+            return Err(Error::InvalidMerkleProof);
+        }
+        Ok(index)
+    }
+}
+
+pub(crate) fn merge(left: &Bytes32, right: &Bytes32) -> Bytes32 {
+    let zero = Bytes32::default();
+    if *left == zero && *right == zero {
+        return zero;
+    }
+    hash([left.as_ref(), right.as_ref()]).into()
+}
+
+fn merge_sibling(index: u64, node: &Bytes32, sibling: &Bytes32) -> Bytes32 {
+    if index & 1 == 0 {
+        merge(node, sibling)
+    } else {
+        merge(sibling, node)
+    }
+}
+
+/// Root of a tree of `depth` from proven leaves and their proof
+pub(crate) fn compute_root(
+    depth: u8,
+    leaves: &BTreeMap<u64, Bytes32>,
+    proof: &[u8],
+) -> Result<Bytes32, Error> {
+    let invalid_proof = || Error::InvalidMerkleProof;
+
+    let mut proof = proof.iter();
+    let mut next_sibling = || -> Result<Bytes32, Error> {
+        match proof.next() {
+            Some(&EMPTY_SIBLING) => Ok(Bytes32::default()),
+            Some(&SIBLING) => {
+                let mut buf = [0u8; 32];
+                for b in buf.iter_mut() {
+                    *b = *proof.next().ok_or_else(invalid_proof)?;
+                }
+                Ok(buf.into())
+            }
+            _ => Err(invalid_proof()),
+        }
+    };
+
+    let root = if leaves.is_empty() {
+        next_sibling()?
+    } else {
+        let mut nodes = leaves.clone();
+        for _ in 0..depth {
+            let mut parents = BTreeMap::new();
+            for (&index, node) in nodes.iter() {
+                let sibling_index = index ^ 1;
+                if index & 1 == 1 && nodes.contains_key(&sibling_index) {
+                    continue;
+                }
+
+                let sibling = match nodes.get(&sibling_index) {
+                    Some(sibling) => *sibling,
+                    None => next_sibling()?,
+                };
+                parents.insert(index >> 1, merge_sibling(index, node, &sibling));
+            }
+            nodes = parents;
+        }
+        *nodes.get(&0).ok_or_else(invalid_proof)?
+    };
+
+    if proof.next().is_some() {
+        return Err(invalid_proof());
+    }
+    Ok(root)
+}
+
+/// Proof of leaves at `indices` for `compute_root`, `node(height, index)`
+/// returns a node of the full tree, leaves are at height 0
+pub(crate) fn prove_indices(
+    depth: u8,
+    mut indices: BTreeSet<u64>,
+    node: impl Fn(u8, u64) -> Bytes32,
+) -> Vec<u8> {
+    let mut proof = Vec::new();
+    let mut push_sibling = |sibling: Bytes32| {
+        if sibling == Bytes32::default() {
+            proof.push(EMPTY_SIBLING);
+        } else {
+            proof.push(SIBLING);
+            proof.extend_from_slice(&sibling);
+        }
+    };
+
+    if indices.is_empty() {
+        push_sibling(node(depth, 0));
+        return proof;
+    }
+
+    // Same traversal as `compute_root`
+    for height in 0..depth {
+        for &index in indices.iter() {
+            if !indices.contains(&(index ^ 1)) {
+                push_sibling(node(height, index ^ 1));
+            }
+        }
+        indices = indices.iter().map(|index| index >> 1).collect();
+    }
+
+    proof
+}
+
+/// Verifier side tree, only proven leaves can be read or updated
+#[derive(Default)]
+pub struct ShallowVerifierSMT {
+    layout: Layout,
+    leaves: BTreeMap<u64, Bytes32>,
+    proof: Vec<u8>,
+    accessed: RefCell<BTreeSet<u64>>,
+}
+
+impl SMTOps for ShallowVerifierSMT {
+    fn update(&mut self, key: Bytes32, value: Bytes32) -> Result<(), Error> {
+        let index = self.layout.index(&key)?;
+        match self.leaves.get_mut(&index) {
+            Some(leaf) => *leaf = value,
+            None => return Err(Error::UnprovenKey),
+        }
+        self.accessed.borrow_mut().insert(index);
+        Ok(())
+    }
+
+    fn get(&self, key: Bytes32) -> Result<Bytes32, Error> {
+        let index = self.layout.index(&key)?;
+        let leaf = self.leaves.get(&index).cloned().ok_or(Error::UnprovenKey)?;
+        self.accessed.borrow_mut().insert(index);
+        Ok(leaf)
+    }
+
+    fn root(&self) -> Result<Bytes32, Error> {
+        compute_root(self.layout.depth, &self.leaves, &self.proof)
+    }
+
+    fn set_memory_size(&mut self, memory_size: usize) {
+        self.layout = Layout::new(memory_size);
+    }
+
+    fn flag_key(&self, page: u64) -> Bytes32 {
+        self.layout.flag_key(page)
+    }
+
+    fn data_chunk_key(&self, chunk: u64) -> Bytes32 {
+        self.layout.data_chunk_key(chunk)
+    }
+}
+
+impl SMTRestore for ShallowVerifierSMT {
+    fn restore_from_proof(proof: MemoryProof) -> Result<Self, Error> {
+        let layout = Layout::new(proof.memory_size);
+        let mut leaves = BTreeMap::new();
+        for (k, v) in proof.kvs {
+            leaves.insert(layout.index(&k)?, v);
+        }
+
+        let root = compute_root(layout.depth, &leaves, &proof.merkle_proof)?;
+        if root != proof.root {
+            return Err(Error::InvalidMerkleProof);
+        }
+
+        let smt = Self {
+            layout,
+            leaves,
+            proof: proof.merkle_proof,
+            accessed: Default::default(),
+        };
+
+        Ok(smt)
+    }
+
+    fn check_proof_keys(&self) -> Result<(), Error> {
+        // Only proven leaves can be accessed
+        if self.accessed.borrow().len() != self.leaves.len() {
+            return Err(Error::UnusedProofKey);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+pub use self::prover::ShallowProverSMT;
+
+#[cfg(feature = "std")]
+mod prover {
+    use im::HashMap;
+
+    use super::{merge_sibling, prove_indices, Layout};
+    use crate::{
+        collections::BTreeSet,
+        memory::{SMTOps, SMTProve},
+        types::Bytes32,
+        Error,
+    };
+
+    /// Prover side tree, keeps non-empty nodes in a persistent map so
+    /// snapshots are cheap
+    #[derive(Default, Clone)]
+    pub struct ShallowProverSMT {
+        layout: Layout,
+        // (height, index), leaves are at height 0
+        nodes: HashMap<(u8, u64), Bytes32>,
+    }
+
+    impl ShallowProverSMT {
+        fn node(&self, height: u8, index: u64) -> Bytes32 {
+            self.nodes
+                .get(&(height, index))
+                .cloned()
+                .unwrap_or_default()
+        }
+
+        fn set_node(&mut self, height: u8, index: u64, node: Bytes32) {
+            if node == Bytes32::default() {
+                self.nodes.remove(&(height, index));
+            } else {
+                self.nodes.insert((height, index), node);
+            }
+        }
+    }
+
+    impl SMTOps for ShallowProverSMT {
+        fn update(&mut self, key: Bytes32, value: Bytes32) -> Result<(), Error> {
+            let mut index = self.layout.index(&key)?;
+            let mut node = value;
+            self.set_node(0, index, node);
+
+            for height in 0..self.layout.depth {
+                let sibling = self.node(height, index ^ 1);
+                node = merge_sibling(index, &node, &sibling);
+                index >>= 1;
+                self.set_node(height + 1, index, node);
+            }
+
+            Ok(())
+        }
+
+        fn get(&self, key: Bytes32) -> Result<Bytes32, Error> {
+            let index = self.layout.index(&key)?;
+            Ok(self.node(0, index))
+        }
+
+        fn root(&self) -> Result<Bytes32, Error> {
+            Ok(self.node(self.layout.depth, 0))
+        }
+
+        fn set_memory_size(&mut self, memory_size: usize) {
+            self.layout = Layout::new(memory_size);
+        }
+
+        fn flag_key(&self, page: u64) -> Bytes32 {
+            self.layout.flag_key(page)
+        }
+
+        fn data_chunk_key(&self, chunk: u64) -> Bytes32 {
+            self.layout.data_chunk_key(chunk)
+        }
+    }
+
+    impl SMTProve for ShallowProverSMT {
+        fn snap(&self) -> Self {
+            self.clone()
+        }
+
+        fn prove(&self, keys: &[Bytes32]) -> Result<Vec<u8>, Error> {
+            let mut indices = BTreeSet::new();
+            for key in keys {
+                indices.insert(self.layout.index(key)?);
+            }
+
+            let proof = prove_indices(self.layout.depth, indices, |height, index| {
+                self.node(height, index)
+            });
+            Ok(proof)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use ckb_vm::{Memory, RISCV_PAGESIZE};
+    use proptest::prelude::*;
+
+    use super::{Layout, ShallowProverSMT, ShallowVerifierSMT};
+    use crate::{
+        memory::{MemoryProof, SMTMemory, SMTOps, SMTProve, SMTRestore},
+        types::Bytes32,
+    };
+
+    const MEMORY_SIZE: usize = 4 << 20;
+
+    #[test]
+    fn test_layout() {
+        let layout = Layout::new(MEMORY_SIZE);
+        assert_eq!(layout.depth, 18);
+
+        let mem = SMTMemory::<u64, ShallowProverSMT>::new(MEMORY_SIZE);
+        let pages = (MEMORY_SIZE / RISCV_PAGESIZE) as u64;
+        assert_eq!(
+            mem.data_chunk_key(MEMORY_SIZE as u64 - 1),
+            Bytes32::from_u64((MEMORY_SIZE / 32 - 1) as u64)
+        );
+        assert_eq!(
+            mem.flag_key(pages - 1),
+            Bytes32::from_u64((MEMORY_SIZE / 32) as u64 + pages - 1)
+        );
+        assert!(layout.index(&Bytes32::from_u64(1 << 18)).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_prove_and_restore(
+            kvs in prop::collection::btree_map(0..(1u64 << 18), any::<[u8; 32]>(), 1..20),
+            proven in prop::collection::btree_set(0..(1u64 << 18), 0..20),
+        ) {
+            let mut prover = ShallowProverSMT::default();
+            prover.set_memory_size(MEMORY_SIZE);
+            for (index, value) in kvs.iter() {
+                prover.update(Bytes32::from_u64(*index), (*value).into()).unwrap();
+            }
+
+            let keys: Vec<Bytes32> = proven.iter().map(|index| Bytes32::from_u64(*index)).collect();
+            let proof = MemoryProof {
+                memory_size: MEMORY_SIZE,
+                root: prover.root().unwrap(),
+                kvs: keys.iter().map(|key| (*key, prover.get(*key).unwrap())).collect::<BTreeMap<_, _>>(),
+                merkle_proof: prover.prove(&keys).unwrap(),
+                hints: Vec::new(),
+            };
+
+            // Tampered leaf is rejected
+            if let Some(key) = keys.first() {
+                let mut tampered = proof.clone();
+                tampered.kvs.insert(*key, Bytes32::from_u8(1));
+                if prover.get(*key).unwrap() != Bytes32::from_u8(1) {
+                    prop_assert!(ShallowVerifierSMT::restore_from_proof(tampered).is_err());
+                }
+            }
+
+            let mut verifier = ShallowVerifierSMT::restore_from_proof(proof).unwrap();
+            prop_assert_eq!(verifier.root().unwrap(), prover.root().unwrap());
+
+            // Updating proven keys tracks prover root
+            for key in keys.iter() {
+                let value = Bytes32::from_u64(key.to_u64() + 1);
+                prover.update(*key, value).unwrap();
+                verifier.update(*key, value).unwrap();
+                prop_assert_eq!(verifier.get(*key).unwrap(), value);
+            }
+            prop_assert_eq!(verifier.root().unwrap(), prover.root().unwrap());
+
+            // Unproven key can't be accessed
+            let unproven = (0..).find(|index| !proven.contains(index)).unwrap();
+            prop_assert!(verifier.get(Bytes32::from_u64(unproven)).is_err());
+            prop_assert!(verifier.update(Bytes32::from_u64(unproven), Bytes32::default()).is_err());
+        }
+    }
+}
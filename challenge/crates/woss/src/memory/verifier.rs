@@ -1,31 +1,49 @@
-use ckb_vm::Error;
-use sparse_merkle_tree::SMTBuilder;
+#[cfg(not(feature = "sha256"))]
+use sparse_merkle_tree::{SMTBuilder, SMT};
 
 use crate::types::Bytes32;
-use crate::{cell::RefCell, string::ToString, vec::Vec};
+use crate::{cell::RefCell, collections::BTreeSet, vec::Vec, Error};
 
+#[cfg(feature = "sha256")]
+use self::rust_smt::{SMTBuilder, SMT};
 use super::{SMTOps, SMTRestore};
 
+/// Tree of proven keys. Unproven keys are absent rather than zero, accessing
+/// them is an error.
 pub struct VerifierSMT {
-    inner: RefCell<sparse_merkle_tree::SMT>,
+    inner: RefCell<SMT>,
     proof: Vec<u8>,
+    proven: BTreeSet<Bytes32>,
+    accessed: RefCell<BTreeSet<Bytes32>>,
+}
+
+impl VerifierSMT {
+    fn access(&self, key: &Bytes32) -> Result<(), Error> {
+        if !self.proven.contains(key) {
+            return Err(Error::UnprovenKey);
+        }
+        self.accessed.borrow_mut().insert(*key);
+        Ok(())
+    }
 }
 
 impl SMTOps for VerifierSMT {
     fn update(&mut self, key: Bytes32, value: Bytes32) -> Result<(), Error> {
+        self.access(&key)?;
         self.inner
             .borrow_mut()
             .update(&(key.into()), &(value).into())
-            .map_err(|err| Error::Unexpected(err.to_string()))?;
+            .map_err(|_| Error::Smt)?;
         Ok(())
     }
 
     fn get(&self, key: Bytes32) -> Result<Bytes32, Error> {
+        self.access(&key)?;
         self.inner
             .borrow()
             .get(&(key.into()))
             .map(Into::into)
-            .map_err(|err| Error::Unexpected(err.to_string()))
+            .map_err(|_| Error::Smt)
     }
 
     fn root(&self) -> Result<Bytes32, Error> {
@@ -37,7 +55,7 @@ impl SMTOps for VerifierSMT {
             .borrow()
             .calculate_root(&self.proof)
             .map(Into::into)
-            .map_err(|err| Error::Unexpected(err.to_string()))
+            .map_err(|_| Error::Smt)
     }
 }
 
@@ -47,29 +65,101 @@ impl Default for VerifierSMT {
         VerifierSMT {
             inner: RefCell::new(inner),
             proof: Default::default(),
+            proven: Default::default(),
+            accessed: Default::default(),
         }
     }
 }
 
 impl SMTRestore for VerifierSMT {
     fn restore_from_proof(proof: super::MemoryProof) -> Result<Self, Error> {
+        let proven = proof.kvs.keys().cloned().collect();
         let mut builder = SMTBuilder::default();
         for (k, v) in proof.kvs {
             builder = builder
                 .insert(&k.into(), &v.into())
-                .map_err(|err| Error::Unexpected(err.to_string()))?;
+                .map_err(|_| Error::InvalidMerkleProof)?;
         }
 
-        let inner = { builder.build() }.map_err(|err| Error::Unexpected(err.to_string()))?;
+        let inner = { builder.build() }.map_err(|_| Error::InvalidMerkleProof)?;
         inner
             .verify(&proof.root.into(), &proof.merkle_proof)
-            .map_err(|err| Error::Unexpected(err.to_string()))?;
+            .map_err(|_| Error::InvalidMerkleProof)?;
 
         let smt = Self {
             inner: RefCell::new(inner),
             proof: proof.merkle_proof,
+            proven,
+            accessed: Default::default(),
         };
 
         Ok(smt)
     }
+
+    fn check_proof_keys(&self) -> Result<(), Error> {
+        // Only proven keys can be accessed
+        if self.accessed.borrow().len() != self.proven.len() {
+            return Err(Error::UnusedProofKey);
+        }
+        Ok(())
+    }
+}
+
+/// Same API as `ckb_smt.c` bindings, which hardcode blake2b. Calculates root
+/// with `MemoryHasher` in rust instead.
+#[cfg(feature = "sha256")]
+mod rust_smt {
+    use sparse_merkle_tree::{CompiledMerkleProof, H256};
+
+    use crate::{collections::BTreeMap, common::MemoryHasher};
+
+    #[derive(Default)]
+    pub struct SMTBuilder {
+        leaves: BTreeMap<H256, H256>,
+    }
+
+    impl SMTBuilder {
+        pub fn insert(mut self, key: &H256, value: &H256) -> Result<Self, ()> {
+            self.leaves.insert(*key, *value);
+            Ok(self)
+        }
+
+        pub fn build(self) -> Result<SMT, ()> {
+            Ok(SMT {
+                leaves: self.leaves,
+            })
+        }
+    }
+
+    #[allow(clippy::upper_case_acronyms)]
+    pub struct SMT {
+        leaves: BTreeMap<H256, H256>,
+    }
+
+    impl SMT {
+        pub fn verify(&self, root: &H256, proof: &[u8]) -> Result<(), ()> {
+            if self.calculate_root(proof)? != *root {
+                return Err(());
+            }
+            Ok(())
+        }
+
+        pub fn update(&mut self, key: &H256, value: &H256) -> Result<(), ()> {
+            self.leaves.insert(*key, *value);
+            Ok(())
+        }
+
+        pub fn get(&self, key: &H256) -> Result<H256, ()> {
+            Ok(self.leaves.get(key).cloned().unwrap_or_default())
+        }
+
+        pub fn normalize(&mut self) {}
+
+        pub fn calculate_root(&self, proof: &[u8]) -> Result<H256, ()> {
+            let leaves = self.leaves.iter().map(|(k, v)| (*k, *v)).collect();
+            CompiledMerkleProof(proof.into())
+                .compute_root::<MemoryHasher>(leaves)
+                .map_err(|_| ())
+        }
+    }
 }
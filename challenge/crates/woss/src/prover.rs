@@ -1,37 +1,604 @@
-use ckb_vm::{Bytes, Error, Register};
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    iter,
+    path::Path,
+};
+
+use crate::collections::{btree_map, BTreeMap, BTreeSet};
+
+use ckb_vm::{Bytes, Register};
 
 use crate::{
-    machine::{Machine, RunResult, StepProof},
-    memory::prover::ProverSMT,
+    budget::{ProofBudget, ProofEstimate},
+    checkpoint::{read_checkpoint, write_checkpoint},
+    machine::{InitialCommitment, Machine, MachineBuilder, MachineSnapshot, RunResult, StepProof},
+    memory::{prover::ProverSMT, SMTOps, SMTProve},
+    store::StepCommitmentStore,
+    trace::StepTracer,
+    types::conversion::MoleculeSize,
+    Error,
 };
 
-pub struct Prover<R> {
-    machine: Machine<R, ProverSMT>,
+/// Steps to commit during a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitPolicy {
+    /// Execute only, no commitment
+    None,
+    /// Steps which are multiple of given interval, including step 0
+    Every(u64),
+    /// Given steps, those beyond the last step are skipped
+    Steps(BTreeSet<u64>),
+    /// The last step only
+    Final,
+}
+
+impl CommitPolicy {
+    fn commits(&self, step_num: u64) -> bool {
+        match self {
+            CommitPolicy::None | CommitPolicy::Final => false,
+            CommitPolicy::Every(interval) => step_num % interval == 0,
+            CommitPolicy::Steps(steps) => steps.contains(&step_num),
+        }
+    }
+}
+
+pub struct Prover<R, M = ProverSMT> {
+    machine: Machine<R, M>,
+    checkpoint_interval: Option<u64>,
+    checkpoints: BTreeMap<u64, MachineSnapshot<R, M>>,
+    budget: Option<ProofBudget>,
 }
 
-impl<R: Register> Prover<R> {
+impl<R: Register + 'static, M: SMTProve + SMTOps + 'static> Prover<R, M> {
     pub fn new(memory_size: usize) -> Self {
         let machine = Machine::new(memory_size);
-        Self { machine }
+        Self::from_machine(machine)
+    }
+
+    pub fn from_builder(builder: MachineBuilder<R, M>) -> Result<Self, Error> {
+        let machine = builder.build()?;
+        Ok(Self::from_machine(machine))
+    }
+
+    fn from_machine(machine: Machine<R, M>) -> Self {
+        Self {
+            machine,
+            checkpoint_interval: None,
+            checkpoints: BTreeMap::new(),
+            budget: None,
+        }
+    }
+
+    /// Take a snapshot every `interval` steps while running, so later
+    /// `run_to_step` restores from the nearest one instead of replaying from
+    /// step 0. Interval 0 disables automatic checkpoints.
+    pub fn checkpoint_interval(mut self, interval: u64) -> Self {
+        self.checkpoint_interval = Some(interval).filter(|interval| *interval > 0);
+        self
+    }
+
+    /// Reject step proofs exceeding given budget. Rejected steps are rolled
+    /// back, prover stays at the step before them.
+    pub fn proof_budget(mut self, budget: ProofBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// See `Machine::set_step_tracer`
+    pub fn set_step_tracer(&mut self, tracer: Option<Box<dyn StepTracer>>) {
+        self.machine.set_step_tracer(tracer)
     }
 
     pub fn load_program(&mut self, program: &Bytes) -> Result<u64, Error> {
-        self.machine.load_program(program)
+        self.load_program_with_args(program, &[])
+    }
+
+    pub fn load_program_with_args(
+        &mut self,
+        program: &Bytes,
+        args: &[Bytes],
+    ) -> Result<u64, Error> {
+        let size = self.machine.load_program_with_args(program, args)?;
+
+        // Drop checkpoints taken from other program or args
+        let initial = self.machine.initial_commitment();
+        self.checkpoints.retain(|_, s| s.initial == initial);
+
+        Ok(size)
+    }
+
+    pub fn initial_commitment(&self) -> Option<InitialCommitment> {
+        self.machine.initial_commitment()
+    }
+
+    pub fn step_num(&self) -> u64 {
+        self.machine.step_num()
     }
 
     pub fn run(&mut self) -> Result<RunResult, Error> {
-        self.machine.run()
+        self.run_until_step(u64::MAX)
     }
 
     pub fn run_until_step(&mut self, step_num: u64) -> Result<RunResult, Error> {
-        self.machine.run_until_step(step_num)
+        let mut step_commitments = Vec::new();
+        let step_count = self.run_until_step_with_store(step_num, &mut step_commitments)?;
+
+        let ret = RunResult {
+            step_count,
+            step_commitments,
+        };
+        Ok(ret)
     }
 
-    pub fn prove_next_step(&mut self) -> Result<StepProof<R>, Error> {
-        self.machine.prove_next_step()
+    pub fn run_until_step_with_store<S: StepCommitmentStore>(
+        &mut self,
+        step_num: u64,
+        store: &mut S,
+    ) -> Result<u64, Error> {
+        let interval = self.checkpoint_interval;
+        let checkpoints = &mut self.checkpoints;
+
+        self.machine.run_until_step_with(step_num, |machine| {
+            store.append(machine.commit_step()?)?;
+            take_checkpoint(machine, interval, checkpoints)
+        })
+    }
+
+    /// Run from current step to the end, committing only steps selected by
+    /// policy. Checkpoints are taken as in `run`.
+    pub fn run_with_commit_policy(&mut self, policy: &CommitPolicy) -> Result<RunResult, Error> {
+        if let CommitPolicy::Every(0) = policy {
+            return Err(Error::InvalidInterval);
+        }
+
+        let interval = self.checkpoint_interval;
+        let checkpoints = &mut self.checkpoints;
+        let mut step_commitments = Vec::new();
+
+        let step_count = self.machine.run_until_step_with(u64::MAX, |machine| {
+            if policy.commits(machine.step_num()) {
+                step_commitments.push(machine.commit_step()?);
+            }
+            take_checkpoint(machine, interval, checkpoints)
+        })?;
+        if let CommitPolicy::Final = policy {
+            step_commitments.push(self.machine.commit_step()?);
+        }
+
+        let ret = RunResult {
+            step_count,
+            step_commitments,
+        };
+        Ok(ret)
+    }
+
+    /// Move to given step, restore from nearest checkpoint if it saves
+    /// replaying. Returns reached step, which is less than given one if
+    /// program exits before.
+    pub fn run_to_step(&mut self, step_num: u64) -> Result<u64, Error> {
+        let current = self.machine.step_num();
+        if let Some((_, snapshot)) = self.checkpoints.range(..=step_num).next_back() {
+            if snapshot.step > current || current > step_num {
+                self.machine.restore_snapshot(snapshot);
+            }
+        }
+        if self.machine.step_num() > step_num {
+            return Err(Error::StepBehind(step_num));
+        }
+
+        let interval = self.checkpoint_interval;
+        let checkpoints = &mut self.checkpoints;
+        self.machine.run_until_step_with(step_num, |machine| {
+            take_checkpoint(machine, interval, checkpoints)
+        })
+    }
+
+    pub fn snapshot(&mut self) -> Result<MachineSnapshot<R, M>, Error> {
+        self.machine.snapshot()
+    }
+
+    pub fn restore(&mut self, snapshot: &MachineSnapshot<R, M>) {
+        self.machine.restore_snapshot(snapshot)
     }
 
     pub fn reset(&mut self) {
         self.machine.reset()
     }
 }
+
+impl<R: Register + 'static, M: SMTProve + SMTOps + 'static> Prover<R, M>
+where
+    StepProof<R>: MoleculeSize,
+{
+    pub fn prove_next_step(&mut self) -> Result<StepProof<R>, Error> {
+        self.prove_steps(1)
+    }
+
+    /// Prove at most `count` steps from current one in a single proof
+    pub fn prove_steps(&mut self, count: u64) -> Result<StepProof<R>, Error> {
+        if self.budget.is_none() {
+            return self.machine.prove_steps(count);
+        }
+
+        let (proof, _) = self.prove_steps_with_estimate(count)?;
+        Ok(proof)
+    }
+
+    /// Prove next step along with its estimated witness size and verifier
+    /// cycles, for callers flagging steps instead of rejecting them
+    pub fn prove_next_step_with_estimate(
+        &mut self,
+    ) -> Result<(StepProof<R>, ProofEstimate), Error> {
+        self.prove_steps_with_estimate(1)
+    }
+
+    pub fn prove_steps_with_estimate(
+        &mut self,
+        count: u64,
+    ) -> Result<(StepProof<R>, ProofEstimate), Error> {
+        // Size of a proof is only known after executing its steps
+        let snapshot = match self.budget {
+            Some(_) => Some(self.machine.snapshot()?),
+            None => None,
+        };
+        let proof = self.machine.prove_steps(count)?;
+        let budget = self.budget.unwrap_or_default();
+        let estimate = budget.estimate(&proof);
+        if let Some(snapshot) = snapshot {
+            if let Err(err) = budget.check(&estimate) {
+                self.machine.restore_snapshot(&snapshot);
+                return Err(err);
+            }
+        }
+
+        Ok((proof, estimate))
+    }
+
+    /// Prove the step from `step_num` to `step_num + 1`
+    pub fn prove_step(&mut self, step_num: u64) -> Result<StepProof<R>, Error> {
+        let reached = self.run_to_step(step_num)?;
+        if reached != step_num {
+            return Err(Error::ProgramExited(reached));
+        }
+
+        self.prove_next_step()
+    }
+}
+
+impl<R: Register + 'static> Prover<R, ProverSMT> {
+    /// Persist current state and checkpoints, so a restarted prover resumes
+    /// through `load_checkpoint` without re-executing the program. Syscalls
+    /// are not saved, the restarted prover must register the same ones.
+    pub fn save_checkpoint<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let current = self.machine.snapshot()?;
+        let commitment = self.machine.commit()?;
+
+        // Commit every checkpoint so `load_checkpoint` can check them too
+        let machine = &mut self.machine;
+        let checkpoints: Result<Vec<_>, Error> = self
+            .checkpoints
+            .values()
+            .map(|snapshot| {
+                machine.restore_snapshot(snapshot);
+                Ok((snapshot, machine.commit()?))
+            })
+            .collect();
+        self.machine.restore_snapshot(&current);
+        let checkpoints = checkpoints?;
+
+        // Write to a temporary file then rename, a crash in between keeps the
+        // previous checkpoint intact
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let file = File::create(&tmp_path)?;
+        let mut writer = BufWriter::new(file);
+        write_checkpoint(&mut writer, &current, &commitment, &checkpoints)?;
+
+        let file = writer.into_inner().map_err(|err| err.into_error())?;
+        file.sync_all()?;
+        Ok(fs::rename(&tmp_path, path)?)
+    }
+
+    /// Resume from file written by `save_checkpoint`. Fails and keeps current
+    /// state if any saved snapshot doesn't match its commitment, e.g. file is
+    /// corrupted or this prover's configuration differs.
+    pub fn load_checkpoint<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let file = File::open(path)?;
+        let checkpoint = read_checkpoint::<R, _>(&mut BufReader::new(file))?;
+
+        // Current snapshot goes last so the machine ends up in its state
+        let previous = self.machine.snapshot()?;
+        let machine = &mut self.machine;
+        let checked = checkpoint
+            .checkpoints
+            .iter()
+            .map(|(snapshot, commitment)| (snapshot, commitment))
+            .chain(iter::once((&checkpoint.current, &checkpoint.commitment)))
+            .try_for_each(|(snapshot, commitment)| {
+                machine.restore_snapshot(snapshot);
+                match machine.commit()? == *commitment {
+                    true => Ok(()),
+                    false => Err(Error::CommitmentMismatch),
+                }
+            });
+        if let Err(err) = checked {
+            self.machine.restore_snapshot(&previous);
+            return Err(err);
+        }
+
+        self.checkpoints = checkpoint
+            .checkpoints
+            .into_iter()
+            .map(|(s, _)| (s.step, s))
+            .collect();
+
+        Ok(())
+    }
+}
+
+fn take_checkpoint<R: Register, M: SMTProve + SMTOps>(
+    machine: &mut Machine<R, M>,
+    interval: Option<u64>,
+    checkpoints: &mut BTreeMap<u64, MachineSnapshot<R, M>>,
+) -> Result<(), Error> {
+    let step_num = machine.step_num();
+    match interval {
+        Some(interval) if step_num % interval == 0 => {
+            if let btree_map::Entry::Vacant(entry) = checkpoints.entry(step_num) {
+                entry.insert(machine.snapshot()?);
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use ckb_vm::Bytes;
+
+    use super::{CommitPolicy, Prover};
+    use crate::{budget::ProofBudget, Error};
+
+    const MEMORY_SIZE: usize = 4 << 20;
+
+    // https://github.com/nervosnetwork/ckb-vm/blob/develop/tests/programs/simple
+    fn simple_program() -> Bytes {
+        fs::read("../../../simple").unwrap().into()
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let program = simple_program();
+        let mut prover = Prover::<u32>::new(MEMORY_SIZE);
+        prover.load_program(&program).unwrap();
+        let result = prover.run().unwrap();
+
+        let mut prover = Prover::<u32>::new(MEMORY_SIZE);
+        prover.load_program(&program).unwrap();
+        let step_num = result.step_count / 2;
+        prover.run_until_step(step_num).unwrap();
+        let snapshot = prover.snapshot().unwrap();
+
+        // Continue to the end, then go back to snapshot
+        let tail = prover.run().unwrap();
+        assert_eq!(
+            &tail.step_commitments[..],
+            &result.step_commitments[step_num as usize..]
+        );
+
+        prover.restore(&snapshot);
+        assert_eq!(prover.step_num(), step_num);
+        assert_eq!(prover.initial_commitment(), snapshot.initial);
+        let tail = prover.run().unwrap();
+        assert_eq!(
+            &tail.step_commitments[..],
+            &result.step_commitments[step_num as usize..]
+        );
+    }
+
+    #[test]
+    fn test_prove_step_from_checkpoints() {
+        let program = simple_program();
+        let mut replay = Prover::<u32>::new(MEMORY_SIZE);
+        replay.load_program(&program).unwrap();
+        let result = replay.run().unwrap();
+
+        let mut prover = Prover::<u32>::new(MEMORY_SIZE).checkpoint_interval(10);
+        prover.load_program(&program).unwrap();
+        prover.run().unwrap();
+        assert_eq!(prover.checkpoints.len() as u64, result.step_count / 10 + 1);
+
+        // Checkpoints survive reloading the same program
+        prover.reset();
+        prover.load_program(&program).unwrap();
+        assert!(!prover.checkpoints.is_empty());
+
+        // Backward, then forward
+        let count = result.step_count;
+        let steps = [count - 1, 1, count / 2, 7, count * 3 / 4];
+        for step_num in steps {
+            let proof = prover.prove_step(step_num).unwrap();
+
+            replay.reset();
+            replay.load_program(&program).unwrap();
+            replay.run_until_step(step_num).unwrap();
+            let expected = replay.prove_next_step().unwrap();
+
+            assert_eq!(proof.step_num, step_num);
+            assert_eq!(proof.registers, expected.registers);
+            assert_eq!(proof.memory.root, expected.memory.root);
+            assert_eq!(proof.memory.kvs, expected.memory.kvs);
+        }
+
+        assert!(prover.prove_step(result.step_count + 1).is_err());
+
+        // Other args invalidate checkpoints
+        prover.reset();
+        prover
+            .load_program_with_args(&program, &["other".into()])
+            .unwrap();
+        assert!(prover.checkpoints.is_empty());
+
+        let mut prover = Prover::<u32>::new(MEMORY_SIZE).checkpoint_interval(0);
+        prover.load_program(&program).unwrap();
+        prover.run().unwrap();
+        assert!(prover.checkpoints.is_empty());
+    }
+
+    #[test]
+    fn test_save_load_checkpoint() {
+        let program = simple_program();
+        let mut replay = Prover::<u32>::new(MEMORY_SIZE);
+        replay.load_program(&program).unwrap();
+        let result = replay.run().unwrap();
+
+        let mut prover = Prover::<u32>::new(MEMORY_SIZE).checkpoint_interval(10);
+        prover.load_program(&program).unwrap();
+        let step_num = result.step_count / 2;
+        prover.run_until_step(step_num).unwrap();
+
+        let path = env::temp_dir().join(format!("woss-checkpoint-{}", std::process::id()));
+        prover.save_checkpoint(&path).unwrap();
+
+        // Memory shared by checkpoints is written once, otherwise file grows
+        // by a whole tree per checkpoint
+        let mut single = Prover::<u32>::new(MEMORY_SIZE);
+        single.load_program(&program).unwrap();
+        single.run_until_step(step_num).unwrap();
+        single.save_checkpoint(&path).unwrap();
+        let single_len = fs::metadata(&path).unwrap().len();
+        prover.save_checkpoint(&path).unwrap();
+        let count = prover.checkpoints.len() as u64;
+        assert!(fs::metadata(&path).unwrap().len() < single_len * count / 4);
+
+        // Restarted prover resumes from saved step
+        let mut restarted = Prover::<u32>::new(MEMORY_SIZE);
+        restarted.load_checkpoint(&path).unwrap();
+        assert_eq!(restarted.step_num(), step_num);
+        assert_eq!(restarted.initial_commitment(), prover.initial_commitment());
+        assert_eq!(restarted.checkpoints.len(), prover.checkpoints.len());
+
+        let tail = restarted.run().unwrap();
+        assert_eq!(
+            &tail.step_commitments[..],
+            &result.step_commitments[step_num as usize..]
+        );
+
+        // Loaded checkpoints answer earlier steps
+        let proof = restarted.prove_step(step_num / 2).unwrap();
+        replay.reset();
+        replay.load_program(&program).unwrap();
+        replay.run_until_step(step_num / 2).unwrap();
+        let expected = replay.prove_next_step().unwrap();
+        assert_eq!(proof.registers, expected.registers);
+        assert_eq!(proof.memory.kvs, expected.memory.kvs);
+
+        // Other machine configuration is rejected and keeps its state
+        let mut other = Prover::<u32>::new(MEMORY_SIZE * 2);
+        other.load_program(&program).unwrap();
+        assert!(other.load_checkpoint(&path).is_err());
+        assert_eq!(other.step_num(), 0);
+
+        // So do other register width and corrupted file
+        assert!(Prover::<u64>::new(MEMORY_SIZE)
+            .load_checkpoint(&path)
+            .is_err());
+        let mut bytes = fs::read(&path).unwrap();
+
+        // Header is 21 bytes, flip a register of the first checkpoint
+        bytes[21 + 8 + 8] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        let mut tampered = Prover::<u32>::new(MEMORY_SIZE);
+        tampered.load_program(&program).unwrap();
+        assert_eq!(
+            tampered.load_checkpoint(&path),
+            Err(Error::CommitmentMismatch)
+        );
+        assert_eq!(tampered.step_num(), 0);
+
+        bytes[0] = 0;
+        fs::write(&path, &bytes).unwrap();
+        assert!(Prover::<u32>::new(MEMORY_SIZE)
+            .load_checkpoint(&path)
+            .is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_proof_budget() {
+        let program = simple_program();
+        let mut prover = Prover::<u32>::new(MEMORY_SIZE);
+        prover.load_program(&program).unwrap();
+        let (proof, estimate) = prover.prove_next_step_with_estimate().unwrap();
+
+        // Budget below the first step proof rejects it and keeps prover at
+        // step 0, then it is proved with a larger one
+        let budget = ProofBudget {
+            max_witness_size: Some(estimate.witness_size - 1),
+            ..Default::default()
+        };
+        let mut prover = Prover::<u32>::new(MEMORY_SIZE).proof_budget(budget);
+        prover.load_program(&program).unwrap();
+        assert_eq!(
+            prover.prove_next_step().err(),
+            Some(Error::BudgetExceeded(estimate))
+        );
+        assert_eq!(prover.step_num(), 0);
+
+        let mut prover = prover.proof_budget(ProofBudget {
+            max_witness_size: Some(estimate.witness_size),
+            ..Default::default()
+        });
+        let budgeted = prover.prove_next_step().unwrap();
+        assert_eq!(budgeted.step_num, 0);
+        assert_eq!(budgeted.registers, proof.registers);
+        assert_eq!(budgeted.memory.kvs, proof.memory.kvs);
+    }
+
+    #[test]
+    fn test_run_with_commit_policy() {
+        let program = simple_program();
+        let mut prover = Prover::<u32>::new(MEMORY_SIZE);
+        prover.load_program(&program).unwrap();
+        let result = prover.run().unwrap();
+
+        let run = |policy: CommitPolicy| {
+            let mut prover = Prover::<u32>::new(MEMORY_SIZE);
+            prover.load_program(&program).unwrap();
+            let ret = prover.run_with_commit_policy(&policy).unwrap();
+            assert_eq!(ret.step_count, result.step_count);
+            ret.step_commitments
+        };
+
+        assert!(run(CommitPolicy::None).is_empty());
+        assert_eq!(
+            run(CommitPolicy::Final),
+            vec![*result.step_commitments.last().unwrap()]
+        );
+
+        let every: Vec<_> = result
+            .step_commitments
+            .iter()
+            .filter(|s| s.step_num % 7 == 0)
+            .cloned()
+            .collect();
+        assert_eq!(run(CommitPolicy::Every(7)), every);
+
+        let steps = [0, 3, result.step_count, result.step_count + 1];
+        let expected = vec![
+            result.step_commitments[0],
+            result.step_commitments[3],
+            *result.step_commitments.last().unwrap(),
+        ];
+        assert_eq!(
+            run(CommitPolicy::Steps(steps.into_iter().collect())),
+            expected
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use crate::{machine::StepCommitment, vec::Vec, Error};
+
+#[cfg(feature = "std")]
+pub use self::std_store::{FileStepStore, SparseStepStore};
+
+/// Storage of step commitments produced by a run, queried during dissection
+pub trait StepCommitmentStore {
+    /// Append commitment of next step
+    fn append(&mut self, step: StepCommitment) -> Result<(), Error>;
+    fn get(&mut self, step_num: u64) -> Result<Option<StepCommitment>, Error>;
+    fn last_step_num(&self) -> Option<u64>;
+}
+
+/// In-memory store, keeps every step commitment
+impl StepCommitmentStore for Vec<StepCommitment> {
+    fn append(&mut self, step: StepCommitment) -> Result<(), Error> {
+        self.push(step);
+        Ok(())
+    }
+
+    fn get(&mut self, step_num: u64) -> Result<Option<StepCommitment>, Error> {
+        let step = self
+            .binary_search_by_key(&step_num, |s| s.step_num)
+            .ok()
+            .map(|idx| self[idx]);
+        Ok(step)
+    }
+
+    fn last_step_num(&self) -> Option<u64> {
+        self.last().map(|s| s.step_num)
+    }
+}
+
+/// Keeps step commitments from `from` step, drops earlier ones
+pub struct StepWindow {
+    from: u64,
+    steps: Vec<StepCommitment>,
+}
+
+impl StepWindow {
+    pub fn new(from: u64) -> Self {
+        Self {
+            from,
+            steps: Vec::new(),
+        }
+    }
+}
+
+impl StepCommitmentStore for StepWindow {
+    fn append(&mut self, step: StepCommitment) -> Result<(), Error> {
+        if step.step_num >= self.from {
+            self.steps.push(step);
+        }
+        Ok(())
+    }
+
+    fn get(&mut self, step_num: u64) -> Result<Option<StepCommitment>, Error> {
+        StepCommitmentStore::get(&mut self.steps, step_num)
+    }
+
+    fn last_step_num(&self) -> Option<u64> {
+        self.steps.last_step_num()
+    }
+}
+
+#[cfg(feature = "std")]
+mod std_store {
+    use std::{
+        fs::{File, OpenOptions},
+        io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+        path::Path,
+    };
+
+    use ckb_vm::{Bytes, Register};
+
+    use super::{StepCommitmentStore, StepWindow};
+    use crate::{machine::StepCommitment, prover::Prover, types::Bytes32, Error};
+
+    const COMMITMENT_SIZE: u64 = 32;
+
+    /// Keeps every `interval` step commitment, re-executes program to answer
+    /// queries on steps in between.
+    ///
+    /// Replayer snapshots machine every `interval` steps it passes, a query
+    /// restores the nearest snapshot and re-executes at most one interval.
+    /// Steps of the last queried interval are cached.
+    pub struct SparseStepStore<R> {
+        interval: u64,
+        checkpoints: Vec<StepCommitment>,
+        last: Option<StepCommitment>,
+        replayer: Prover<R>,
+        window: StepWindow,
+    }
+
+    impl<R: Register + 'static> SparseStepStore<R> {
+        /// `replayer` must be built with the same configuration and syscalls as
+        /// the prover which produces the step commitments.
+        pub fn new(
+            interval: u64,
+            replayer: Prover<R>,
+            program: Bytes,
+            args: Vec<Bytes>,
+        ) -> Result<Self, Error> {
+            if interval == 0 {
+                return Err(Error::InvalidInterval);
+            }
+
+            let mut replayer = replayer.checkpoint_interval(interval);
+            replayer.reset();
+            replayer.load_program_with_args(&program, &args)?;
+
+            let store = Self {
+                interval,
+                checkpoints: Vec::new(),
+                last: None,
+                replayer,
+                window: StepWindow::new(u64::MAX),
+            };
+            Ok(store)
+        }
+
+        fn replay(&mut self, from: u64, to: u64) -> Result<(), Error> {
+            let mut window = StepWindow::new(from);
+
+            self.replayer.run_to_step(from)?;
+            self.replayer.run_until_step_with_store(to, &mut window)?;
+
+            self.window = window;
+            Ok(())
+        }
+    }
+
+    impl<R: Register + 'static> StepCommitmentStore for SparseStepStore<R> {
+        fn append(&mut self, step: StepCommitment) -> Result<(), Error> {
+            if step.step_num % self.interval == 0 {
+                self.checkpoints.push(step);
+            }
+            self.last = Some(step);
+            Ok(())
+        }
+
+        fn get(&mut self, step_num: u64) -> Result<Option<StepCommitment>, Error> {
+            let last = match self.last {
+                Some(last) if step_num <= last.step_num => last,
+                _ => return Ok(None),
+            };
+            if step_num == last.step_num {
+                return Ok(Some(last));
+            }
+            if step_num % self.interval == 0 {
+                return StepCommitmentStore::get(&mut self.checkpoints, step_num);
+            }
+
+            if let Some(step) = self.window.get(step_num)? {
+                return Ok(Some(step));
+            }
+
+            let from = step_num - step_num % self.interval;
+            let to = from.saturating_add(self.interval).min(last.step_num);
+            self.replay(from, to)?;
+
+            let step = self.window.get(step_num)?;
+            if step.is_none() {
+                return Err(Error::ProgramExited(self.replayer.step_num()));
+            }
+            Ok(step)
+        }
+
+        fn last_step_num(&self) -> Option<u64> {
+            self.last.map(|s| s.step_num)
+        }
+    }
+
+    /// Append-only file of 32 bytes commitments, indexed by step number
+    /// starting from 0
+    pub struct FileStepStore {
+        writer: BufWriter<File>,
+        reader: File,
+        len: u64,
+    }
+
+    impl FileStepStore {
+        pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+            File::create(&path)?;
+            Self::open(path)
+        }
+
+        /// Open existing store to resume
+        pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+            let file = OpenOptions::new().read(true).append(true).open(path)?;
+            let len = file.metadata()?.len() / COMMITMENT_SIZE;
+            // Both handles share file offset, writes always go to the end in
+            // append mode and reads seek before reading
+            let reader = file.try_clone()?;
+
+            Ok(Self {
+                writer: BufWriter::new(file),
+                reader,
+                len,
+            })
+        }
+    }
+
+    impl StepCommitmentStore for FileStepStore {
+        fn append(&mut self, step: StepCommitment) -> Result<(), Error> {
+            if step.step_num != self.len {
+                let msg = format!("expect step {}, got {}", self.len, step.step_num);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, msg).into());
+            }
+
+            self.writer.write_all(step.commitment.as_ref())?;
+            self.len += 1;
+            Ok(())
+        }
+
+        fn get(&mut self, step_num: u64) -> Result<Option<StepCommitment>, Error> {
+            if step_num >= self.len {
+                return Ok(None);
+            }
+
+            self.writer.flush()?;
+            self.reader
+                .seek(SeekFrom::Start(step_num * COMMITMENT_SIZE))?;
+            let mut buf = [0u8; COMMITMENT_SIZE as usize];
+            self.reader.read_exact(&mut buf)?;
+
+            let step = StepCommitment {
+                step_num,
+                commitment: Bytes32::from(buf),
+            };
+            Ok(Some(step))
+        }
+
+        fn last_step_num(&self) -> Option<u64> {
+            self.len.checked_sub(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use ckb_vm::Bytes;
+
+    use super::{FileStepStore, SparseStepStore, StepCommitmentStore};
+    use crate::{
+        dissection::{Dissection, Party, StepDiffFinder},
+        machine::StepCommitment,
+        prover::Prover,
+        Error,
+    };
+
+    const MEMORY_SIZE: usize = 4 << 20;
+
+    // https://github.com/nervosnetwork/ckb-vm/blob/develop/tests/programs/simple
+    fn simple_program() -> Bytes {
+        fs::read("../../../simple").unwrap().into()
+    }
+
+    fn run_with_store<S: StepCommitmentStore>(store: &mut S) -> u64 {
+        let mut prover = Prover::<u32>::new(MEMORY_SIZE);
+        prover.load_program(&simple_program()).unwrap();
+        prover.run_until_step_with_store(u64::MAX, store).unwrap()
+    }
+
+    fn sparse_store(interval: u64) -> SparseStepStore<u32> {
+        let replayer = Prover::new(MEMORY_SIZE);
+        SparseStepStore::new(interval, replayer, simple_program(), vec![]).unwrap()
+    }
+
+    #[test]
+    fn test_stores_match_full_run() {
+        let mut steps: Vec<StepCommitment> = Vec::new();
+        let step_count = run_with_store(&mut steps);
+        assert_eq!(steps.len() as u64, step_count + 1);
+
+        let mut sparse = sparse_store(7);
+        assert_eq!(run_with_store(&mut sparse), step_count);
+
+        let path = env::temp_dir().join(format!("woss-steps-{}", std::process::id()));
+        let mut file = FileStepStore::create(&path).unwrap();
+        assert_eq!(run_with_store(&mut file), step_count);
+
+        let replayer = Prover::<u32>::new(MEMORY_SIZE);
+        assert!(matches!(
+            SparseStepStore::new(0, replayer, simple_program(), vec![]),
+            Err(Error::InvalidInterval)
+        ));
+
+        for store in [&mut sparse as &mut dyn StepCommitmentStore, &mut file] {
+            assert_eq!(store.last_step_num(), Some(step_count));
+            // Query backward to force replay
+            for step in steps.iter().rev() {
+                assert_eq!(store.get(step.step_num).unwrap(), Some(*step));
+            }
+            assert_eq!(store.get(step_count + 1).unwrap(), None);
+        }
+
+        // Resume from existing file
+        let mut file = FileStepStore::open(&path).unwrap();
+        assert_eq!(file.last_step_num(), Some(step_count));
+        assert_eq!(file.get(step_count).unwrap(), steps.last().cloned());
+        assert!(file.append(steps[0]).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_dissection_with_sparse_store() {
+        let mut steps: Vec<StepCommitment> = Vec::new();
+        let step_count = run_with_store(&mut steps);
+
+        let diff_step = step_count / 2;
+        let mut forged = steps.clone();
+        forged[diff_step as usize..]
+            .iter_mut()
+            .for_each(|sc| sc.commitment = Default::default());
+
+        let mut sparse = sparse_store(5);
+        run_with_store(&mut sparse);
+
+        let mut producer = StepDiffFinder::new(forged);
+        let mut challenger = StepDiffFinder::new(sparse);
+
+        let opening = producer.step_range(0, step_count as usize).unwrap();
+        let mut dissection = Dissection::new(steps[0], opening, 0, 1).unwrap();
+        while let Some(turn) = dissection.turn() {
+            let mv = match turn {
+                Party::Producer => producer.next_move(dissection.steps()),
+                Party::Challenger => challenger.next_move(dissection.steps()),
+            };
+            dissection
+                .submit(turn, mv.unwrap(), dissection.round())
+                .unwrap();
+        }
+
+        assert_eq!(dissection.step_to_prove(), Some(diff_step - 1));
+    }
+}
@@ -0,0 +1,504 @@
+use crate::{boxed::Box, ops::Range, sync::Arc, vec::Vec};
+
+use ckb_vm::{
+    registers::{A0, A1, A2, A7},
+    Bytes, Memory, Register, SupportMachine, Syscalls,
+};
+use serde::{Deserialize, Serialize};
+use spin::Mutex;
+
+use crate::{
+    common::hash,
+    memory::shallow::{compute_root, merge, prove_indices},
+    types::Bytes32,
+    Error,
+};
+
+/// Syscall number of `LoadInput`
+pub const LOAD_INPUT_SYSCALL_NUMBER: u64 = 2200;
+
+pub const SUCCESS: u8 = 0;
+pub const INDEX_OUT_OF_BOUND: u8 = 1;
+
+/// `LoadInput` commits to input in chunks of this size, its witness carries
+/// only chunks covering the loaded range
+pub const INPUT_CHUNK_SIZE: usize = 256;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct SyscallCommitment {
+    pub number: u64,
+    pub commitment: Bytes32,
+}
+
+/// Syscall whose effects can be disputed.
+///
+/// Memory and register changes made by `ecall` are traced like any other
+/// instruction. Data the syscall serves from outside the machine must be bound
+/// by `commitment`, and `witness` must return enough of it to replay the last
+/// `ecall` through `restore_syscall`. `Send` lets the set be shared with ckb-vm,
+/// which requires `Syscalls: Send + Sync`.
+pub trait ProvableSyscall<Mac>: Send {
+    fn number(&self) -> u64;
+    fn commitment(&self) -> Bytes32;
+    fn witness(&self) -> Bytes;
+    fn ecall(&mut self, machine: &mut Mac) -> Result<(), Error>;
+}
+
+/// Rebuild a syscall from witness recorded in step proof
+pub fn restore_syscall<Mac: SupportMachine>(
+    number: u64,
+    witness: Bytes,
+) -> Result<Box<dyn ProvableSyscall<Mac>>, Error> {
+    match number {
+        LOAD_INPUT_SYSCALL_NUMBER => Ok(Box::new(ProvenInput::restore(witness)?)),
+        _ => Err(Error::InvalidEcall(number)),
+    }
+}
+
+/// Load input blob into memory.
+///
+/// Arguments: `a0` destination address, `a1` address of a u64 holding the
+/// max length to load, `a2` offset in input. On return, the u64 at `a1` holds
+/// the remaining input length from offset, and `a0` holds the result code.
+///
+/// Input is committed as the merkle root of its `INPUT_CHUNK_SIZE` chunks,
+/// witness of a call carries the loaded chunks and their proof.
+pub struct LoadInput {
+    input: Bytes,
+    tree: InputTree,
+    loaded: Range<usize>,
+}
+
+impl LoadInput {
+    pub fn new(input: Bytes) -> Self {
+        let tree = InputTree::new(&input);
+        Self {
+            input,
+            tree,
+            loaded: 0..0,
+        }
+    }
+
+    pub fn hash_input(input: &[u8]) -> Bytes32 {
+        commit_input(input.len() as u64, &InputTree::new(input).root())
+    }
+}
+
+impl<Mac: SupportMachine> ProvableSyscall<Mac> for LoadInput {
+    fn number(&self) -> u64 {
+        LOAD_INPUT_SYSCALL_NUMBER
+    }
+
+    fn commitment(&self) -> Bytes32 {
+        commit_input(self.input.len() as u64, &self.tree.root())
+    }
+
+    fn witness(&self) -> Bytes {
+        let chunks = chunk_range(&self.loaded);
+        let start = chunks.start * INPUT_CHUNK_SIZE;
+        let end = (chunks.end * INPUT_CHUNK_SIZE).min(self.input.len());
+        let indices = (chunks.start as u64..chunks.end as u64).collect();
+        let proof = prove_indices(self.tree.depth(), indices, |height, index| {
+            self.tree.node(height, index)
+        });
+
+        InputWitness {
+            len: self.input.len() as u64,
+            first_chunk: chunks.start as u64,
+            data: self.input.slice(start.min(end)..end),
+            proof: proof.into(),
+        }
+        .encode()
+    }
+
+    fn ecall(&mut self, machine: &mut Mac) -> Result<(), Error> {
+        let input = &self.input;
+        self.loaded = load_input(machine, input.len(), |range| Ok(input.slice(range)))?;
+        Ok(())
+    }
+}
+
+/// `LoadInput` restored from witness, serves only the proven chunks
+struct ProvenInput {
+    witness: Bytes,
+    len: usize,
+    /// Input offset of `data`
+    start: usize,
+    data: Bytes,
+    root: Bytes32,
+}
+
+impl ProvenInput {
+    fn restore(witness: Bytes) -> Result<Self, Error> {
+        let InputWitness {
+            len,
+            first_chunk,
+            data,
+            proof,
+        } = InputWitness::decode(&witness)?;
+        let mismatch = || Error::SyscallWitnessMismatch;
+
+        let len = usize::try_from(len).map_err(|_| mismatch())?;
+        let start = usize::try_from(first_chunk)
+            .ok()
+            .and_then(|chunk| chunk.checked_mul(INPUT_CHUNK_SIZE))
+            .ok_or_else(mismatch)?;
+        let end = start.checked_add(data.len()).ok_or_else(mismatch)?;
+        // Every chunk is whole except the last one of input
+        if end > len || (end != len && data.len() % INPUT_CHUNK_SIZE != 0) {
+            return Err(mismatch());
+        }
+
+        let leaves = data
+            .chunks(INPUT_CHUNK_SIZE)
+            .zip(first_chunk..)
+            .map(|(chunk, index)| (index, hash_input_chunk(chunk)))
+            .collect();
+        let depth = input_tree_depth(len);
+        let root = compute_root(depth, &leaves, &proof).map_err(|_| mismatch())?;
+
+        Ok(Self {
+            witness,
+            len,
+            start,
+            data,
+            root,
+        })
+    }
+}
+
+impl<Mac: SupportMachine> ProvableSyscall<Mac> for ProvenInput {
+    fn number(&self) -> u64 {
+        LOAD_INPUT_SYSCALL_NUMBER
+    }
+
+    fn commitment(&self) -> Bytes32 {
+        commit_input(self.len as u64, &self.root)
+    }
+
+    fn witness(&self) -> Bytes {
+        self.witness.clone()
+    }
+
+    fn ecall(&mut self, machine: &mut Mac) -> Result<(), Error> {
+        let (start, data) = (self.start, &self.data);
+        load_input(machine, self.len, |range| {
+            if range.is_empty() {
+                return Ok(Bytes::new());
+            }
+            if range.start < start || range.end > start + data.len() {
+                return Err(Error::SyscallWitnessMismatch);
+            }
+            Ok(data.slice(range.start - start..range.end - start))
+        })?;
+        Ok(())
+    }
+}
+
+/// Run `LoadInput` against machine, `read` serves the requested input range.
+/// Returns loaded range, empty if nothing is loaded.
+fn load_input<Mac: SupportMachine>(
+    machine: &mut Mac,
+    input_len: usize,
+    read: impl FnOnce(Range<usize>) -> Result<Bytes, Error>,
+) -> Result<Range<usize>, Error> {
+    let addr = machine.registers()[A0].to_u64();
+    let size_addr = machine.registers()[A1].clone();
+    let offset = machine.registers()[A2].to_u64();
+
+    if offset > input_len as u64 {
+        machine.set_register(A0, Mac::REG::from_u8(INDEX_OUT_OF_BOUND));
+        return Ok(0..0);
+    }
+
+    let size = machine.memory_mut().load64(&size_addr)?.to_u64();
+    let remain = input_len as u64 - offset;
+    let real_size = size.min(remain);
+
+    let start = offset as usize;
+    let range = start..start + real_size as usize;
+    let data = read(range.clone())?;
+    machine.memory_mut().store_bytes(addr, &data)?;
+    machine
+        .memory_mut()
+        .store64(&size_addr, &Mac::REG::from_u64(remain))?;
+    machine.set_register(A0, Mac::REG::from_u8(SUCCESS));
+
+    Ok(range)
+}
+
+/// Commitment of input, binds its length and chunk tree root
+pub fn commit_input(len: u64, root: &Bytes32) -> Bytes32 {
+    hash([b"Input", len.to_le_bytes().as_slice(), root.as_ref()]).into()
+}
+
+fn hash_input_chunk(chunk: &[u8]) -> Bytes32 {
+    hash([b"Input_Chunk", chunk]).into()
+}
+
+/// Smallest depth holding every chunk of input
+fn input_tree_depth(len: usize) -> u8 {
+    let chunks = chunk_range(&(0..len)).end as u64;
+    let mut depth = 0;
+    while (1u64 << depth) < chunks {
+        depth += 1;
+    }
+    depth
+}
+
+/// Chunks covering input range
+fn chunk_range(range: &Range<usize>) -> Range<usize> {
+    if range.is_empty() {
+        return 0..0;
+    }
+    range.start / INPUT_CHUNK_SIZE..(range.end - 1) / INPUT_CHUNK_SIZE + 1
+}
+
+/// Merkle tree of input chunks, nodes hash and proofs encode like
+/// `memory::shallow`
+struct InputTree {
+    /// Nodes of each height, leaves first
+    levels: Vec<Vec<Bytes32>>,
+}
+
+impl InputTree {
+    fn new(input: &[u8]) -> Self {
+        let mut level: Vec<Bytes32> = input
+            .chunks(INPUT_CHUNK_SIZE)
+            .map(hash_input_chunk)
+            .collect();
+        let mut levels = Vec::new();
+        for _ in 0..input_tree_depth(input.len()) {
+            let parents = level
+                .chunks(2)
+                .map(|pair| merge(&pair[0], pair.get(1).unwrap_or(&Bytes32::default())))
+                .collect();
+            levels.push(level);
+            level = parents;
+        }
+        levels.push(level);
+
+        Self { levels }
+    }
+
+    fn depth(&self) -> u8 {
+        (self.levels.len() - 1) as u8
+    }
+
+    fn root(&self) -> Bytes32 {
+        self.node(self.depth(), 0)
+    }
+
+    fn node(&self, height: u8, index: u64) -> Bytes32 {
+        let level = &self.levels[height as usize];
+        level.get(index as usize).cloned().unwrap_or_default()
+    }
+}
+
+/// Witness of a `LoadInput` call, encoded as `len | first_chunk | data len
+/// | data | proof` with u64 little endian integers
+struct InputWitness {
+    len: u64,
+    first_chunk: u64,
+    /// Chunks covering loaded range
+    data: Bytes,
+    /// Proof of chunks for `compute_root`
+    proof: Bytes,
+}
+
+impl InputWitness {
+    fn encode(&self) -> Bytes {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&self.len.to_le_bytes());
+        buf.extend_from_slice(&self.first_chunk.to_le_bytes());
+        buf.extend_from_slice(&(self.data.len() as u64).to_le_bytes());
+        buf.extend_from_slice(&self.data);
+        buf.extend_from_slice(&self.proof);
+        buf.into()
+    }
+
+    fn decode(witness: &Bytes) -> Result<Self, Error> {
+        let mismatch = || Error::SyscallWitnessMismatch;
+        let read_u64 = |offset: usize| -> Result<u64, Error> {
+            let bytes = witness.get(offset..offset + 8).ok_or_else(mismatch)?;
+            Ok(u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+        };
+
+        let len = read_u64(0)?;
+        let first_chunk = read_u64(8)?;
+        let data_end = usize::try_from(read_u64(16)?)
+            .ok()
+            .and_then(|data_len| data_len.checked_add(24))
+            .filter(|end| *end <= witness.len())
+            .ok_or_else(mismatch)?;
+
+        Ok(Self {
+            len,
+            first_chunk,
+            data: witness.slice(24..data_end),
+            proof: witness.slice(data_end..),
+        })
+    }
+}
+
+/// Syscall restored from step proof, rebuilt from witness when invoked
+struct ReplaySyscall {
+    number: u64,
+    commitment: Bytes32,
+    witness: Bytes,
+}
+
+impl<Mac: SupportMachine> ProvableSyscall<Mac> for ReplaySyscall {
+    fn number(&self) -> u64 {
+        self.number
+    }
+
+    fn commitment(&self) -> Bytes32 {
+        self.commitment
+    }
+
+    fn witness(&self) -> Bytes {
+        self.witness.clone()
+    }
+
+    fn ecall(&mut self, machine: &mut Mac) -> Result<(), Error> {
+        let mut syscall = restore_syscall::<Mac>(self.number, self.witness.clone())?;
+        if syscall.commitment() != self.commitment {
+            return Err(Error::SyscallWitnessMismatch);
+        }
+
+        syscall.ecall(machine)?;
+
+        Ok(())
+    }
+}
+
+pub struct SyscallSet<Mac> {
+    syscalls: Vec<Box<dyn ProvableSyscall<Mac>>>,
+    witness: Option<Bytes>,
+    /// Error of the last failed ecall, see `take_error`
+    error: Option<Error>,
+}
+
+impl<Mac: SupportMachine> SyscallSet<Mac> {
+    pub fn new(syscalls: Vec<Box<dyn ProvableSyscall<Mac>>>) -> Self {
+        Self {
+            syscalls,
+            witness: None,
+            error: None,
+        }
+    }
+
+    pub fn restore(commitments: Vec<SyscallCommitment>, witness: Bytes) -> Self {
+        let syscalls = commitments
+            .into_iter()
+            .map(|c| -> Box<dyn ProvableSyscall<Mac>> {
+                Box::new(ReplaySyscall {
+                    number: c.number,
+                    commitment: c.commitment,
+                    witness: witness.clone(),
+                })
+            })
+            .collect();
+
+        Self::new(syscalls)
+    }
+
+    pub fn commitments(&self) -> Vec<SyscallCommitment> {
+        self.syscalls
+            .iter()
+            .map(|s| SyscallCommitment {
+                number: s.number(),
+                commitment: s.commitment(),
+            })
+            .collect()
+    }
+
+    /// Witness of syscall invoked since last call
+    pub fn take_witness(&mut self) -> Option<Bytes> {
+        self.witness.take()
+    }
+
+    /// Typed error behind the `ckb_vm::Error` returned by last failed ecall
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    fn ecall(&mut self, machine: &mut Mac) -> Result<bool, ckb_vm::Error> {
+        let number = machine.registers()[A7].to_u64();
+        let syscall = match self.syscalls.iter_mut().find(|s| s.number() == number) {
+            Some(syscall) => syscall,
+            None => return Ok(false),
+        };
+
+        if let Err(err) = syscall.ecall(machine) {
+            let vm_err = err.to_vm();
+            self.error = Some(err);
+            return Err(vm_err);
+        }
+        self.witness = Some(syscall.witness());
+
+        Ok(true)
+    }
+}
+
+/// Shares `SyscallSet` between `Machine` and the ckb-vm machine it drives
+pub(crate) struct SyscallsAdapter<Mac>(Arc<Mutex<SyscallSet<Mac>>>);
+
+impl<Mac> SyscallsAdapter<Mac> {
+    pub(crate) fn new(syscalls: Arc<Mutex<SyscallSet<Mac>>>) -> Self {
+        SyscallsAdapter(syscalls)
+    }
+}
+
+impl<Mac: SupportMachine> Syscalls<Mac> for SyscallsAdapter<Mac> {
+    fn initialize(&mut self, _machine: &mut Mac) -> Result<(), ckb_vm::Error> {
+        Ok(())
+    }
+
+    fn ecall(&mut self, machine: &mut Mac) -> Result<bool, ckb_vm::Error> {
+        self.0.lock().ecall(machine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckb_vm::{Bytes, DefaultCoreMachine, SparseMemory};
+    use proptest::prelude::*;
+
+    use super::{LoadInput, ProvableSyscall, ProvenInput, INPUT_CHUNK_SIZE};
+
+    type Mac = DefaultCoreMachine<u64, SparseMemory<u64>>;
+
+    proptest! {
+        #[test]
+        fn test_input_witness(
+            input in prop::collection::vec(any::<u8>(), 0..4 * INPUT_CHUNK_SIZE),
+            start in 0..4 * INPUT_CHUNK_SIZE,
+            size in 0..2 * INPUT_CHUNK_SIZE,
+        ) {
+            let input = Bytes::from(input);
+            let start = start.min(input.len());
+            let end = (start + size).min(input.len());
+
+            let mut syscall = LoadInput::new(input.clone());
+            syscall.loaded = start..end;
+            let witness = ProvableSyscall::<Mac>::witness(&syscall);
+            let proven = ProvenInput::restore(witness).unwrap();
+            prop_assert_eq!(
+                ProvableSyscall::<Mac>::commitment(&proven),
+                ProvableSyscall::<Mac>::commitment(&syscall)
+            );
+
+            // Only chunks covering loaded range are carried
+            let data_end = proven.start + proven.data.len();
+            prop_assert_eq!(&proven.data[..], &input[proven.start..data_end]);
+            prop_assert!(proven.data.len() < end - start + 2 * INPUT_CHUNK_SIZE);
+            if start < end {
+                prop_assert!(proven.start <= start && end <= data_end);
+            } else {
+                prop_assert!(proven.data.is_empty());
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Per-step execution traces, for locating where two runs of a disputed
+//! program diverge without relying on commitments.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{string::String, vec::Vec, Error};
+
+/// Bytes read or written at `addr` by a step
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub addr: u64,
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StepTrace {
+    pub step_num: u64,
+    pub pc: u64,
+    /// Decoded instruction, and name of its opcode
+    pub instruction: u64,
+    pub opcode: String,
+    /// Index and new value of registers changed by this step
+    pub register_writes: Vec<(usize, u64)>,
+    /// Data accesses, instruction fetch excluded
+    pub memory_reads: Vec<MemoryAccess>,
+    pub memory_writes: Vec<MemoryAccess>,
+    /// Cycles after this step
+    pub cycles: u64,
+}
+
+/// Data accesses of a step, in order
+#[derive(Debug, Clone, Default)]
+pub struct AccessLog {
+    pub reads: Vec<MemoryAccess>,
+    pub writes: Vec<MemoryAccess>,
+}
+
+/// Receives a record for each step executed by `Machine`
+pub trait StepTracer {
+    fn trace(&mut self, step: &StepTrace) -> Result<(), Error>;
+}
+
+#[cfg(feature = "std")]
+pub use self::std_trace::{diff_traces, read_trace, Divergence, JsonLinesTracer};
+
+#[cfg(feature = "std")]
+mod std_trace {
+    use std::io::{self, BufRead, Write};
+
+    use super::{StepTrace, StepTracer};
+    use crate::Error;
+
+    /// Writes one JSON record per line
+    pub struct JsonLinesTracer<W> {
+        writer: W,
+    }
+
+    impl<W: Write> JsonLinesTracer<W> {
+        pub fn new(writer: W) -> Self {
+            Self { writer }
+        }
+
+        pub fn into_inner(self) -> W {
+            self.writer
+        }
+    }
+
+    impl<W: Write> StepTracer for JsonLinesTracer<W> {
+        fn trace(&mut self, step: &StepTrace) -> Result<(), Error> {
+            serde_json::to_writer(&mut self.writer, step).map_err(io::Error::from)?;
+            Ok(self.writer.write_all(b"\n")?)
+        }
+    }
+
+    pub fn read_trace<R: BufRead>(reader: R) -> impl Iterator<Item = Result<StepTrace, Error>> {
+        reader
+            .lines()
+            .filter(|line| !matches!(line, Ok(l) if l.trim().is_empty()))
+            .map(|line| {
+                let line = line?;
+                Ok(serde_json::from_str(&line).map_err(io::Error::from)?)
+            })
+    }
+
+    /// First step where two traces differ, `None` record if that trace ends
+    /// before it
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Divergence {
+        pub index: u64,
+        pub left: Option<StepTrace>,
+        pub right: Option<StepTrace>,
+    }
+
+    pub fn diff_traces<L: BufRead, R: BufRead>(
+        left: L,
+        right: R,
+    ) -> Result<Option<Divergence>, Error> {
+        let mut left = read_trace(left);
+        let mut right = read_trace(right);
+
+        for index in 0.. {
+            let (l, r) = (left.next().transpose()?, right.next().transpose()?);
+            if l.is_none() && r.is_none() {
+                break;
+            }
+            if l != r {
+                return Ok(Some(Divergence {
+                    index,
+                    left: l,
+                    right: r,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{self, File},
+        io::BufWriter,
+    };
+
+    use ckb_vm::Bytes;
+
+    use super::{diff_traces, read_trace, JsonLinesTracer, StepTrace, StepTracer};
+    use crate::{machine::Machine, memory::prover::ProverSMT};
+
+    const MEMORY_SIZE: usize = 4 << 20;
+
+    #[test]
+    fn test_trace_and_diff() {
+        let program: Bytes = fs::read("../../../simple").unwrap().into();
+        let path = env::temp_dir().join(format!("woss-trace-{}.jsonl", std::process::id()));
+
+        let mut machine = Machine::<u32, ProverSMT>::new(MEMORY_SIZE);
+        machine.load_program(&program).unwrap();
+        let file = BufWriter::new(File::create(&path).unwrap());
+        machine.set_step_tracer(Some(Box::new(JsonLinesTracer::new(file))));
+        let result = machine.run().unwrap();
+        // Flush writer
+        machine.set_step_tracer(None);
+
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let trace: Vec<StepTrace> = read_trace(bytes.as_slice())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(trace.len() as u64, result.step_count);
+        for (idx, step) in trace.iter().enumerate() {
+            assert_eq!(step.step_num, idx as u64);
+            assert!(!step.opcode.is_empty());
+        }
+        assert!(trace.iter().any(|s| !s.register_writes.is_empty()));
+        assert!(trace.iter().any(|s| !s.memory_writes.is_empty()));
+
+        assert_eq!(
+            diff_traces(bytes.as_slice(), bytes.as_slice()).unwrap(),
+            None
+        );
+
+        // Diverged step
+        let mut other = trace.clone();
+        other[5].register_writes.push((31, 1));
+        let mut tracer = JsonLinesTracer::new(Vec::new());
+        for step in other.iter() {
+            tracer.trace(step).unwrap();
+        }
+        let other_bytes = tracer.into_inner();
+        let divergence = diff_traces(bytes.as_slice(), other_bytes.as_slice())
+            .unwrap()
+            .unwrap();
+        assert_eq!(divergence.index, 5);
+        assert_eq!(divergence.left.as_ref(), Some(&trace[5]));
+
+        // Shorter trace
+        let len = bytes.iter().position(|b| *b == b'\n').unwrap() + 1;
+        let divergence = diff_traces(bytes.as_slice(), &bytes[..len])
+            .unwrap()
+            .unwrap();
+        assert_eq!(divergence.index, 1);
+        assert_eq!(divergence.right, None);
+    }
+}
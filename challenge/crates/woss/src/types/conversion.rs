@@ -1,4 +1,8 @@
-use crate::{borrow::ToOwned, memory::MemoryProof, vec::Vec};
+use crate::{
+    borrow::ToOwned,
+    memory::{KeyHint, MemoryProof},
+    vec::Vec,
+};
 
 use ckb_vm::RISCV_GENERAL_REGISTER_NUMBER;
 use molecule::bytes::Bytes;
@@ -6,7 +10,9 @@ pub use molecule::prelude::{Builder, Entity, Reader};
 
 use crate::{
     machine::StepProof,
+    syscalls::SyscallCommitment,
     types::{packed, Bytes32},
+    Error,
 };
 
 #[macro_use]
@@ -16,6 +22,12 @@ pub trait Unpack<T> {
     fn unpack(&self) -> T;
 }
 
+/// Unpack of entities holding values molecule itself doesn't validate, fails
+/// with `Error::Decode` on them
+pub trait TryUnpack<T> {
+    fn try_unpack(&self) -> Result<T, Error>;
+}
+
 pub trait Pack<T: Entity> {
     fn pack(&self) -> T;
 }
@@ -24,6 +36,11 @@ pub trait PackVec<T: Entity, I: Entity>: IntoIterator<Item = I> {
     fn pack(self) -> T;
 }
 
+/// Size of molecule encoding, in bytes
+pub trait MoleculeSize {
+    fn molecule_size(&self) -> usize;
+}
+
 impl_conversion_for_step_proof!(StepProof<u32>, StepProof32, StepProof32Reader);
 impl_conversion_for_step_proof!(StepProof<u64>, StepProof64, StepProof64Reader);
 
@@ -34,22 +51,24 @@ impl Pack<packed::MemoryProof> for MemoryProof {
             .root(self.root.pack())
             .kvs(self.kvs.iter().map(|(k, v)| (*k, *v).pack()).pack())
             .merkle_proof(self.merkle_proof.pack())
+            .hints(self.hints.pack())
             .build()
     }
 }
 
-impl<'r> Unpack<MemoryProof> for packed::MemoryProofReader<'r> {
+impl<'r> TryUnpack<MemoryProof> for packed::MemoryProofReader<'r> {
     #[inline]
-    fn unpack(&self) -> MemoryProof {
-        MemoryProof {
+    fn try_unpack(&self) -> Result<MemoryProof, Error> {
+        Ok(MemoryProof {
             memory_size: Unpack::<u64>::unpack(&self.memory_size()) as usize,
             root: self.root().unpack(),
             kvs: self.kvs().iter().map(|kv| kv.unpack()).collect(),
             merkle_proof: self.merkle_proof().unpack(),
-        }
+            hints: self.hints().try_unpack()?,
+        })
     }
 }
-impl_conversion_for_entity_unpack!(MemoryProof, MemoryProof);
+impl_conversion_for_entity_try_unpack!(MemoryProof, MemoryProof);
 
 impl_conversion_for_registers!(u32, Uint32Reader, Registers32, Registers32Reader);
 impl_conversion_for_registers!(u64, Uint64Reader, Registers64, Registers64Reader);
@@ -146,3 +165,58 @@ impl<'r> Unpack<(Bytes32, Bytes32)> for packed::KVPairReader<'r> {
 }
 impl_conversion_for_vector!((Bytes32, Bytes32), KVPairVec, KVPairVecReader);
 impl_conversion_for_packed_iterator_pack!(KVPair, KVPairVec);
+
+impl Pack<packed::SyscallCommitment> for SyscallCommitment {
+    fn pack(&self) -> packed::SyscallCommitment {
+        packed::SyscallCommitment::new_builder()
+            .number(self.number.pack())
+            .commitment(self.commitment.pack())
+            .build()
+    }
+}
+
+impl<'r> Unpack<SyscallCommitment> for packed::SyscallCommitmentReader<'r> {
+    fn unpack(&self) -> SyscallCommitment {
+        SyscallCommitment {
+            number: self.number().unpack(),
+            commitment: self.commitment().unpack(),
+        }
+    }
+}
+impl_conversion_for_entity_unpack!(SyscallCommitment, SyscallCommitment);
+impl_conversion_for_vector!(
+    SyscallCommitment,
+    SyscallCommitmentVec,
+    SyscallCommitmentVecReader
+);
+
+const DATA_CHUNK_HINT: u8 = 0;
+const FLAG_HINT: u8 = 1;
+
+impl Pack<packed::KeyHint> for KeyHint {
+    fn pack(&self) -> packed::KeyHint {
+        let (kind, index) = match *self {
+            KeyHint::DataChunk(chunk) => (DATA_CHUNK_HINT, chunk),
+            KeyHint::Flag(page) => (FLAG_HINT, page),
+        };
+
+        packed::KeyHint::new_builder()
+            .kind(kind.into())
+            .index(index.pack())
+            .build()
+    }
+}
+
+impl<'r> TryUnpack<KeyHint> for packed::KeyHintReader<'r> {
+    fn try_unpack(&self) -> Result<KeyHint, Error> {
+        let index: u64 = self.index().unpack();
+        match Into::<u8>::into(self.kind()) {
+            DATA_CHUNK_HINT => Ok(KeyHint::DataChunk(index)),
+            FLAG_HINT => Ok(KeyHint::Flag(index)),
+            _ => Err(Error::Decode),
+        }
+    }
+}
+impl_conversion_for_entity_try_unpack!(KeyHint, KeyHint);
+impl_conversion_for_vector_pack!(KeyHint, KeyHintVec);
+impl_conversion_for_vector_try_unpack!(KeyHint, KeyHintVec, KeyHintVecReader);
@@ -8,6 +8,16 @@ macro_rules! impl_conversion_for_entity_unpack {
     };
 }
 
+macro_rules! impl_conversion_for_entity_try_unpack {
+    ($original:ty, $entity:ident) => {
+        impl TryUnpack<$original> for packed::$entity {
+            fn try_unpack(&self) -> Result<$original, Error> {
+                self.as_reader().try_unpack()
+            }
+        }
+    };
+}
+
 macro_rules! impl_conversion_for_vector_pack {
     ($original:ty, $entity:ident) => {
         impl Pack<packed::$entity> for [$original] {
@@ -31,6 +41,17 @@ macro_rules! impl_conversion_for_vector_unpack {
     };
 }
 
+macro_rules! impl_conversion_for_vector_try_unpack {
+    ($original:ty, $entity:ident, $reader:ident) => {
+        impl<'r> TryUnpack<Vec<$original>> for packed::$reader<'r> {
+            fn try_unpack(&self) -> Result<Vec<$original>, Error> {
+                self.iter().map(|x| x.try_unpack()).collect()
+            }
+        }
+        impl_conversion_for_entity_try_unpack!(Vec<$original>, $entity);
+    };
+}
+
 macro_rules! impl_conversion_for_vector {
     ($original:ty, $entity:ident, $reader:ident) => {
         impl_conversion_for_vector_pack!($original, $entity);
@@ -111,32 +132,42 @@ macro_rules! impl_conversion_for_step_proof_pack {
                     .running(u8::from(self.running).into())
                     .isa(self.isa.into())
                     .version(self.version.pack())
+                    .syscalls(self.syscalls.pack())
+                    .syscall_witness(self.syscall_witness.pack())
                     .build()
             }
         }
+
+        impl MoleculeSize for $original {
+            fn molecule_size(&self) -> usize {
+                Pack::<packed::$entity>::pack(self).as_slice().len()
+            }
+        }
     };
 }
 
 macro_rules! impl_conversion_for_step_proof_unpack {
     ($original:ty, $entity:ident, $reader:ident) => {
-        impl<'r> Unpack<$original> for packed::$reader<'r> {
+        impl<'r> TryUnpack<$original> for packed::$reader<'r> {
             #[inline]
-            fn unpack(&self) -> $original {
-                StepProof {
+            fn try_unpack(&self) -> Result<$original, Error> {
+                Ok(StepProof {
                     step_num: self.step_num().unpack(),
                     registers: self.registers().unpack(),
                     pc: self.pc().unpack(),
                     next_pc: self.next_pc().unpack(),
-                    memory: self.memory().unpack(),
+                    memory: self.memory().try_unpack()?,
                     cycles: self.cycles().unpack(),
                     max_cycles: self.max_cycles().unpack(),
                     running: (Into::<u8>::into(self.running()) == 1),
                     isa: self.isa().into(),
                     version: self.version().unpack(),
-                }
+                    syscalls: self.syscalls().unpack(),
+                    syscall_witness: self.syscall_witness().unpack(),
+                })
             }
         }
-        impl_conversion_for_entity_unpack!($original, $entity);
+        impl_conversion_for_entity_try_unpack!($original, $entity);
     };
 }
 
@@ -1715,6 +1715,882 @@ impl<'t: 'r, 'r> ::core::iter::ExactSizeIterator for KVPairVecReaderIterator<'t,
     }
 }
 #[derive(Clone)]
+pub struct SyscallCommitment(molecule::bytes::Bytes);
+impl ::core::fmt::LowerHex for SyscallCommitment {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl ::core::fmt::Debug for SyscallCommitment {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl ::core::fmt::Display for SyscallCommitment {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} {{ ", Self::NAME)?;
+        write!(f, "{}: {}", "number", self.number())?;
+        write!(f, ", {}: {}", "commitment", self.commitment())?;
+        write!(f, " }}")
+    }
+}
+impl ::core::default::Default for SyscallCommitment {
+    fn default() -> Self {
+        let v: Vec<u8> = vec![
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        SyscallCommitment::new_unchecked(v.into())
+    }
+}
+impl SyscallCommitment {
+    pub const TOTAL_SIZE: usize = 40;
+    pub const FIELD_SIZES: [usize; 2] = [8, 32];
+    pub const FIELD_COUNT: usize = 2;
+    pub fn number(&self) -> Uint64 {
+        Uint64::new_unchecked(self.0.slice(0..8))
+    }
+    pub fn commitment(&self) -> Bytes32 {
+        Bytes32::new_unchecked(self.0.slice(8..40))
+    }
+    pub fn as_reader<'r>(&'r self) -> SyscallCommitmentReader<'r> {
+        SyscallCommitmentReader::new_unchecked(self.as_slice())
+    }
+}
+impl molecule::prelude::Entity for SyscallCommitment {
+    type Builder = SyscallCommitmentBuilder;
+    const NAME: &'static str = "SyscallCommitment";
+    fn new_unchecked(data: molecule::bytes::Bytes) -> Self {
+        SyscallCommitment(data)
+    }
+    fn as_bytes(&self) -> molecule::bytes::Bytes {
+        self.0.clone()
+    }
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+    fn from_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        SyscallCommitmentReader::from_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn from_compatible_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        SyscallCommitmentReader::from_compatible_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn new_builder() -> Self::Builder {
+        ::core::default::Default::default()
+    }
+    fn as_builder(self) -> Self::Builder {
+        Self::new_builder()
+            .number(self.number())
+            .commitment(self.commitment())
+    }
+}
+#[derive(Clone, Copy)]
+pub struct SyscallCommitmentReader<'r>(&'r [u8]);
+impl<'r> ::core::fmt::LowerHex for SyscallCommitmentReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl<'r> ::core::fmt::Debug for SyscallCommitmentReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl<'r> ::core::fmt::Display for SyscallCommitmentReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} {{ ", Self::NAME)?;
+        write!(f, "{}: {}", "number", self.number())?;
+        write!(f, ", {}: {}", "commitment", self.commitment())?;
+        write!(f, " }}")
+    }
+}
+impl<'r> SyscallCommitmentReader<'r> {
+    pub const TOTAL_SIZE: usize = 40;
+    pub const FIELD_SIZES: [usize; 2] = [8, 32];
+    pub const FIELD_COUNT: usize = 2;
+    pub fn number(&self) -> Uint64Reader<'r> {
+        Uint64Reader::new_unchecked(&self.as_slice()[0..8])
+    }
+    pub fn commitment(&self) -> Bytes32Reader<'r> {
+        Bytes32Reader::new_unchecked(&self.as_slice()[8..40])
+    }
+}
+impl<'r> molecule::prelude::Reader<'r> for SyscallCommitmentReader<'r> {
+    type Entity = SyscallCommitment;
+    const NAME: &'static str = "SyscallCommitmentReader";
+    fn to_entity(&self) -> Self::Entity {
+        Self::Entity::new_unchecked(self.as_slice().to_owned().into())
+    }
+    fn new_unchecked(slice: &'r [u8]) -> Self {
+        SyscallCommitmentReader(slice)
+    }
+    fn as_slice(&self) -> &'r [u8] {
+        self.0
+    }
+    fn verify(slice: &[u8], _compatible: bool) -> molecule::error::VerificationResult<()> {
+        use molecule::verification_error as ve;
+        let slice_len = slice.len();
+        if slice_len != Self::TOTAL_SIZE {
+            return ve!(Self, TotalSizeNotMatch, Self::TOTAL_SIZE, slice_len);
+        }
+        Ok(())
+    }
+}
+#[derive(Debug, Default)]
+pub struct SyscallCommitmentBuilder {
+    pub(crate) number: Uint64,
+    pub(crate) commitment: Bytes32,
+}
+impl SyscallCommitmentBuilder {
+    pub const TOTAL_SIZE: usize = 40;
+    pub const FIELD_SIZES: [usize; 2] = [8, 32];
+    pub const FIELD_COUNT: usize = 2;
+    pub fn number(mut self, v: Uint64) -> Self {
+        self.number = v;
+        self
+    }
+    pub fn commitment(mut self, v: Bytes32) -> Self {
+        self.commitment = v;
+        self
+    }
+}
+impl molecule::prelude::Builder for SyscallCommitmentBuilder {
+    type Entity = SyscallCommitment;
+    const NAME: &'static str = "SyscallCommitmentBuilder";
+    fn expected_length(&self) -> usize {
+        Self::TOTAL_SIZE
+    }
+    fn write<W: molecule::io::Write>(&self, writer: &mut W) -> molecule::io::Result<()> {
+        writer.write_all(self.number.as_slice())?;
+        writer.write_all(self.commitment.as_slice())?;
+        Ok(())
+    }
+    fn build(&self) -> Self::Entity {
+        let mut inner = Vec::with_capacity(self.expected_length());
+        self.write(&mut inner)
+            .unwrap_or_else(|_| panic!("{} build should be ok", Self::NAME));
+        SyscallCommitment::new_unchecked(inner.into())
+    }
+}
+#[derive(Clone)]
+pub struct SyscallCommitmentVec(molecule::bytes::Bytes);
+impl ::core::fmt::LowerHex for SyscallCommitmentVec {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl ::core::fmt::Debug for SyscallCommitmentVec {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl ::core::fmt::Display for SyscallCommitmentVec {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} [", Self::NAME)?;
+        for i in 0..self.len() {
+            if i == 0 {
+                write!(f, "{}", self.get_unchecked(i))?;
+            } else {
+                write!(f, ", {}", self.get_unchecked(i))?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+impl ::core::default::Default for SyscallCommitmentVec {
+    fn default() -> Self {
+        let v: Vec<u8> = vec![0, 0, 0, 0];
+        SyscallCommitmentVec::new_unchecked(v.into())
+    }
+}
+impl SyscallCommitmentVec {
+    pub const ITEM_SIZE: usize = 40;
+    pub fn total_size(&self) -> usize {
+        molecule::NUMBER_SIZE + Self::ITEM_SIZE * self.item_count()
+    }
+    pub fn item_count(&self) -> usize {
+        molecule::unpack_number(self.as_slice()) as usize
+    }
+    pub fn len(&self) -> usize {
+        self.item_count()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn get(&self, idx: usize) -> Option<SyscallCommitment> {
+        if idx >= self.len() {
+            None
+        } else {
+            Some(self.get_unchecked(idx))
+        }
+    }
+    pub fn get_unchecked(&self, idx: usize) -> SyscallCommitment {
+        let start = molecule::NUMBER_SIZE + Self::ITEM_SIZE * idx;
+        let end = start + Self::ITEM_SIZE;
+        SyscallCommitment::new_unchecked(self.0.slice(start..end))
+    }
+    pub fn as_reader<'r>(&'r self) -> SyscallCommitmentVecReader<'r> {
+        SyscallCommitmentVecReader::new_unchecked(self.as_slice())
+    }
+}
+impl molecule::prelude::Entity for SyscallCommitmentVec {
+    type Builder = SyscallCommitmentVecBuilder;
+    const NAME: &'static str = "SyscallCommitmentVec";
+    fn new_unchecked(data: molecule::bytes::Bytes) -> Self {
+        SyscallCommitmentVec(data)
+    }
+    fn as_bytes(&self) -> molecule::bytes::Bytes {
+        self.0.clone()
+    }
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+    fn from_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        SyscallCommitmentVecReader::from_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn from_compatible_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        SyscallCommitmentVecReader::from_compatible_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn new_builder() -> Self::Builder {
+        ::core::default::Default::default()
+    }
+    fn as_builder(self) -> Self::Builder {
+        Self::new_builder().extend(self.into_iter())
+    }
+}
+#[derive(Clone, Copy)]
+pub struct SyscallCommitmentVecReader<'r>(&'r [u8]);
+impl<'r> ::core::fmt::LowerHex for SyscallCommitmentVecReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl<'r> ::core::fmt::Debug for SyscallCommitmentVecReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl<'r> ::core::fmt::Display for SyscallCommitmentVecReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} [", Self::NAME)?;
+        for i in 0..self.len() {
+            if i == 0 {
+                write!(f, "{}", self.get_unchecked(i))?;
+            } else {
+                write!(f, ", {}", self.get_unchecked(i))?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+impl<'r> SyscallCommitmentVecReader<'r> {
+    pub const ITEM_SIZE: usize = 40;
+    pub fn total_size(&self) -> usize {
+        molecule::NUMBER_SIZE + Self::ITEM_SIZE * self.item_count()
+    }
+    pub fn item_count(&self) -> usize {
+        molecule::unpack_number(self.as_slice()) as usize
+    }
+    pub fn len(&self) -> usize {
+        self.item_count()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn get(&self, idx: usize) -> Option<SyscallCommitmentReader<'r>> {
+        if idx >= self.len() {
+            None
+        } else {
+            Some(self.get_unchecked(idx))
+        }
+    }
+    pub fn get_unchecked(&self, idx: usize) -> SyscallCommitmentReader<'r> {
+        let start = molecule::NUMBER_SIZE + Self::ITEM_SIZE * idx;
+        let end = start + Self::ITEM_SIZE;
+        SyscallCommitmentReader::new_unchecked(&self.as_slice()[start..end])
+    }
+}
+impl<'r> molecule::prelude::Reader<'r> for SyscallCommitmentVecReader<'r> {
+    type Entity = SyscallCommitmentVec;
+    const NAME: &'static str = "SyscallCommitmentVecReader";
+    fn to_entity(&self) -> Self::Entity {
+        Self::Entity::new_unchecked(self.as_slice().to_owned().into())
+    }
+    fn new_unchecked(slice: &'r [u8]) -> Self {
+        SyscallCommitmentVecReader(slice)
+    }
+    fn as_slice(&self) -> &'r [u8] {
+        self.0
+    }
+    fn verify(slice: &[u8], _compatible: bool) -> molecule::error::VerificationResult<()> {
+        use molecule::verification_error as ve;
+        let slice_len = slice.len();
+        if slice_len < molecule::NUMBER_SIZE {
+            return ve!(Self, HeaderIsBroken, molecule::NUMBER_SIZE, slice_len);
+        }
+        let item_count = molecule::unpack_number(slice) as usize;
+        if item_count == 0 {
+            if slice_len != molecule::NUMBER_SIZE {
+                return ve!(Self, TotalSizeNotMatch, molecule::NUMBER_SIZE, slice_len);
+            }
+            return Ok(());
+        }
+        let total_size = molecule::NUMBER_SIZE + Self::ITEM_SIZE * item_count;
+        if slice_len != total_size {
+            return ve!(Self, TotalSizeNotMatch, total_size, slice_len);
+        }
+        Ok(())
+    }
+}
+#[derive(Debug, Default)]
+pub struct SyscallCommitmentVecBuilder(pub(crate) Vec<SyscallCommitment>);
+impl SyscallCommitmentVecBuilder {
+    pub const ITEM_SIZE: usize = 40;
+    pub fn set(mut self, v: Vec<SyscallCommitment>) -> Self {
+        self.0 = v;
+        self
+    }
+    pub fn push(mut self, v: SyscallCommitment) -> Self {
+        self.0.push(v);
+        self
+    }
+    pub fn extend<T: ::core::iter::IntoIterator<Item = SyscallCommitment>>(
+        mut self,
+        iter: T,
+    ) -> Self {
+        for elem in iter {
+            self.0.push(elem);
+        }
+        self
+    }
+    pub fn replace(&mut self, index: usize, v: SyscallCommitment) -> Option<SyscallCommitment> {
+        self.0
+            .get_mut(index)
+            .map(|item| ::core::mem::replace(item, v))
+    }
+}
+impl molecule::prelude::Builder for SyscallCommitmentVecBuilder {
+    type Entity = SyscallCommitmentVec;
+    const NAME: &'static str = "SyscallCommitmentVecBuilder";
+    fn expected_length(&self) -> usize {
+        molecule::NUMBER_SIZE + Self::ITEM_SIZE * self.0.len()
+    }
+    fn write<W: molecule::io::Write>(&self, writer: &mut W) -> molecule::io::Result<()> {
+        writer.write_all(&molecule::pack_number(self.0.len() as molecule::Number))?;
+        for inner in &self.0[..] {
+            writer.write_all(inner.as_slice())?;
+        }
+        Ok(())
+    }
+    fn build(&self) -> Self::Entity {
+        let mut inner = Vec::with_capacity(self.expected_length());
+        self.write(&mut inner)
+            .unwrap_or_else(|_| panic!("{} build should be ok", Self::NAME));
+        SyscallCommitmentVec::new_unchecked(inner.into())
+    }
+}
+pub struct SyscallCommitmentVecIterator(SyscallCommitmentVec, usize, usize);
+impl ::core::iter::Iterator for SyscallCommitmentVecIterator {
+    type Item = SyscallCommitment;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.1 >= self.2 {
+            None
+        } else {
+            let ret = self.0.get_unchecked(self.1);
+            self.1 += 1;
+            Some(ret)
+        }
+    }
+}
+impl ::core::iter::ExactSizeIterator for SyscallCommitmentVecIterator {
+    fn len(&self) -> usize {
+        self.2 - self.1
+    }
+}
+impl ::core::iter::IntoIterator for SyscallCommitmentVec {
+    type Item = SyscallCommitment;
+    type IntoIter = SyscallCommitmentVecIterator;
+    fn into_iter(self) -> Self::IntoIter {
+        let len = self.len();
+        SyscallCommitmentVecIterator(self, 0, len)
+    }
+}
+impl<'r> SyscallCommitmentVecReader<'r> {
+    pub fn iter<'t>(&'t self) -> SyscallCommitmentVecReaderIterator<'t, 'r> {
+        SyscallCommitmentVecReaderIterator(&self, 0, self.len())
+    }
+}
+pub struct SyscallCommitmentVecReaderIterator<'t, 'r>(
+    &'t SyscallCommitmentVecReader<'r>,
+    usize,
+    usize,
+);
+impl<'t: 'r, 'r> ::core::iter::Iterator for SyscallCommitmentVecReaderIterator<'t, 'r> {
+    type Item = SyscallCommitmentReader<'t>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.1 >= self.2 {
+            None
+        } else {
+            let ret = self.0.get_unchecked(self.1);
+            self.1 += 1;
+            Some(ret)
+        }
+    }
+}
+impl<'t: 'r, 'r> ::core::iter::ExactSizeIterator for SyscallCommitmentVecReaderIterator<'t, 'r> {
+    fn len(&self) -> usize {
+        self.2 - self.1
+    }
+}
+#[derive(Clone)]
+pub struct KeyHint(molecule::bytes::Bytes);
+impl ::core::fmt::LowerHex for KeyHint {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl ::core::fmt::Debug for KeyHint {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl ::core::fmt::Display for KeyHint {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} {{ ", Self::NAME)?;
+        write!(f, "{}: {}", "kind", self.kind())?;
+        write!(f, ", {}: {}", "index", self.index())?;
+        write!(f, " }}")
+    }
+}
+impl ::core::default::Default for KeyHint {
+    fn default() -> Self {
+        let v: Vec<u8> = vec![0, 0, 0, 0, 0, 0, 0, 0, 0];
+        KeyHint::new_unchecked(v.into())
+    }
+}
+impl KeyHint {
+    pub const TOTAL_SIZE: usize = 9;
+    pub const FIELD_SIZES: [usize; 2] = [1, 8];
+    pub const FIELD_COUNT: usize = 2;
+    pub fn kind(&self) -> Byte {
+        Byte::new_unchecked(self.0.slice(0..1))
+    }
+    pub fn index(&self) -> Uint64 {
+        Uint64::new_unchecked(self.0.slice(1..9))
+    }
+    pub fn as_reader<'r>(&'r self) -> KeyHintReader<'r> {
+        KeyHintReader::new_unchecked(self.as_slice())
+    }
+}
+impl molecule::prelude::Entity for KeyHint {
+    type Builder = KeyHintBuilder;
+    const NAME: &'static str = "KeyHint";
+    fn new_unchecked(data: molecule::bytes::Bytes) -> Self {
+        KeyHint(data)
+    }
+    fn as_bytes(&self) -> molecule::bytes::Bytes {
+        self.0.clone()
+    }
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+    fn from_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        KeyHintReader::from_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn from_compatible_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        KeyHintReader::from_compatible_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn new_builder() -> Self::Builder {
+        ::core::default::Default::default()
+    }
+    fn as_builder(self) -> Self::Builder {
+        Self::new_builder().kind(self.kind()).index(self.index())
+    }
+}
+#[derive(Clone, Copy)]
+pub struct KeyHintReader<'r>(&'r [u8]);
+impl<'r> ::core::fmt::LowerHex for KeyHintReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl<'r> ::core::fmt::Debug for KeyHintReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl<'r> ::core::fmt::Display for KeyHintReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} {{ ", Self::NAME)?;
+        write!(f, "{}: {}", "kind", self.kind())?;
+        write!(f, ", {}: {}", "index", self.index())?;
+        write!(f, " }}")
+    }
+}
+impl<'r> KeyHintReader<'r> {
+    pub const TOTAL_SIZE: usize = 9;
+    pub const FIELD_SIZES: [usize; 2] = [1, 8];
+    pub const FIELD_COUNT: usize = 2;
+    pub fn kind(&self) -> ByteReader<'r> {
+        ByteReader::new_unchecked(&self.as_slice()[0..1])
+    }
+    pub fn index(&self) -> Uint64Reader<'r> {
+        Uint64Reader::new_unchecked(&self.as_slice()[1..9])
+    }
+}
+impl<'r> molecule::prelude::Reader<'r> for KeyHintReader<'r> {
+    type Entity = KeyHint;
+    const NAME: &'static str = "KeyHintReader";
+    fn to_entity(&self) -> Self::Entity {
+        Self::Entity::new_unchecked(self.as_slice().to_owned().into())
+    }
+    fn new_unchecked(slice: &'r [u8]) -> Self {
+        KeyHintReader(slice)
+    }
+    fn as_slice(&self) -> &'r [u8] {
+        self.0
+    }
+    fn verify(slice: &[u8], _compatible: bool) -> molecule::error::VerificationResult<()> {
+        use molecule::verification_error as ve;
+        let slice_len = slice.len();
+        if slice_len != Self::TOTAL_SIZE {
+            return ve!(Self, TotalSizeNotMatch, Self::TOTAL_SIZE, slice_len);
+        }
+        Ok(())
+    }
+}
+#[derive(Debug, Default)]
+pub struct KeyHintBuilder {
+    pub(crate) kind: Byte,
+    pub(crate) index: Uint64,
+}
+impl KeyHintBuilder {
+    pub const TOTAL_SIZE: usize = 9;
+    pub const FIELD_SIZES: [usize; 2] = [1, 8];
+    pub const FIELD_COUNT: usize = 2;
+    pub fn kind(mut self, v: Byte) -> Self {
+        self.kind = v;
+        self
+    }
+    pub fn index(mut self, v: Uint64) -> Self {
+        self.index = v;
+        self
+    }
+}
+impl molecule::prelude::Builder for KeyHintBuilder {
+    type Entity = KeyHint;
+    const NAME: &'static str = "KeyHintBuilder";
+    fn expected_length(&self) -> usize {
+        Self::TOTAL_SIZE
+    }
+    fn write<W: molecule::io::Write>(&self, writer: &mut W) -> molecule::io::Result<()> {
+        writer.write_all(self.kind.as_slice())?;
+        writer.write_all(self.index.as_slice())?;
+        Ok(())
+    }
+    fn build(&self) -> Self::Entity {
+        let mut inner = Vec::with_capacity(self.expected_length());
+        self.write(&mut inner)
+            .unwrap_or_else(|_| panic!("{} build should be ok", Self::NAME));
+        KeyHint::new_unchecked(inner.into())
+    }
+}
+#[derive(Clone)]
+pub struct KeyHintVec(molecule::bytes::Bytes);
+impl ::core::fmt::LowerHex for KeyHintVec {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl ::core::fmt::Debug for KeyHintVec {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl ::core::fmt::Display for KeyHintVec {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} [", Self::NAME)?;
+        for i in 0..self.len() {
+            if i == 0 {
+                write!(f, "{}", self.get_unchecked(i))?;
+            } else {
+                write!(f, ", {}", self.get_unchecked(i))?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+impl ::core::default::Default for KeyHintVec {
+    fn default() -> Self {
+        let v: Vec<u8> = vec![0, 0, 0, 0];
+        KeyHintVec::new_unchecked(v.into())
+    }
+}
+impl KeyHintVec {
+    pub const ITEM_SIZE: usize = 9;
+    pub fn total_size(&self) -> usize {
+        molecule::NUMBER_SIZE + Self::ITEM_SIZE * self.item_count()
+    }
+    pub fn item_count(&self) -> usize {
+        molecule::unpack_number(self.as_slice()) as usize
+    }
+    pub fn len(&self) -> usize {
+        self.item_count()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn get(&self, idx: usize) -> Option<KeyHint> {
+        if idx >= self.len() {
+            None
+        } else {
+            Some(self.get_unchecked(idx))
+        }
+    }
+    pub fn get_unchecked(&self, idx: usize) -> KeyHint {
+        let start = molecule::NUMBER_SIZE + Self::ITEM_SIZE * idx;
+        let end = start + Self::ITEM_SIZE;
+        KeyHint::new_unchecked(self.0.slice(start..end))
+    }
+    pub fn as_reader<'r>(&'r self) -> KeyHintVecReader<'r> {
+        KeyHintVecReader::new_unchecked(self.as_slice())
+    }
+}
+impl molecule::prelude::Entity for KeyHintVec {
+    type Builder = KeyHintVecBuilder;
+    const NAME: &'static str = "KeyHintVec";
+    fn new_unchecked(data: molecule::bytes::Bytes) -> Self {
+        KeyHintVec(data)
+    }
+    fn as_bytes(&self) -> molecule::bytes::Bytes {
+        self.0.clone()
+    }
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+    fn from_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        KeyHintVecReader::from_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn from_compatible_slice(slice: &[u8]) -> molecule::error::VerificationResult<Self> {
+        KeyHintVecReader::from_compatible_slice(slice).map(|reader| reader.to_entity())
+    }
+    fn new_builder() -> Self::Builder {
+        ::core::default::Default::default()
+    }
+    fn as_builder(self) -> Self::Builder {
+        Self::new_builder().extend(self.into_iter())
+    }
+}
+#[derive(Clone, Copy)]
+pub struct KeyHintVecReader<'r>(&'r [u8]);
+impl<'r> ::core::fmt::LowerHex for KeyHintVecReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use molecule::hex_string;
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", hex_string(self.as_slice()))
+    }
+}
+impl<'r> ::core::fmt::Debug for KeyHintVecReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{}({:#x})", Self::NAME, self)
+    }
+}
+impl<'r> ::core::fmt::Display for KeyHintVecReader<'r> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "{} [", Self::NAME)?;
+        for i in 0..self.len() {
+            if i == 0 {
+                write!(f, "{}", self.get_unchecked(i))?;
+            } else {
+                write!(f, ", {}", self.get_unchecked(i))?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+impl<'r> KeyHintVecReader<'r> {
+    pub const ITEM_SIZE: usize = 9;
+    pub fn total_size(&self) -> usize {
+        molecule::NUMBER_SIZE + Self::ITEM_SIZE * self.item_count()
+    }
+    pub fn item_count(&self) -> usize {
+        molecule::unpack_number(self.as_slice()) as usize
+    }
+    pub fn len(&self) -> usize {
+        self.item_count()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn get(&self, idx: usize) -> Option<KeyHintReader<'r>> {
+        if idx >= self.len() {
+            None
+        } else {
+            Some(self.get_unchecked(idx))
+        }
+    }
+    pub fn get_unchecked(&self, idx: usize) -> KeyHintReader<'r> {
+        let start = molecule::NUMBER_SIZE + Self::ITEM_SIZE * idx;
+        let end = start + Self::ITEM_SIZE;
+        KeyHintReader::new_unchecked(&self.as_slice()[start..end])
+    }
+}
+impl<'r> molecule::prelude::Reader<'r> for KeyHintVecReader<'r> {
+    type Entity = KeyHintVec;
+    const NAME: &'static str = "KeyHintVecReader";
+    fn to_entity(&self) -> Self::Entity {
+        Self::Entity::new_unchecked(self.as_slice().to_owned().into())
+    }
+    fn new_unchecked(slice: &'r [u8]) -> Self {
+        KeyHintVecReader(slice)
+    }
+    fn as_slice(&self) -> &'r [u8] {
+        self.0
+    }
+    fn verify(slice: &[u8], _compatible: bool) -> molecule::error::VerificationResult<()> {
+        use molecule::verification_error as ve;
+        let slice_len = slice.len();
+        if slice_len < molecule::NUMBER_SIZE {
+            return ve!(Self, HeaderIsBroken, molecule::NUMBER_SIZE, slice_len);
+        }
+        let item_count = molecule::unpack_number(slice) as usize;
+        if item_count == 0 {
+            if slice_len != molecule::NUMBER_SIZE {
+                return ve!(Self, TotalSizeNotMatch, molecule::NUMBER_SIZE, slice_len);
+            }
+            return Ok(());
+        }
+        let total_size = molecule::NUMBER_SIZE + Self::ITEM_SIZE * item_count;
+        if slice_len != total_size {
+            return ve!(Self, TotalSizeNotMatch, total_size, slice_len);
+        }
+        Ok(())
+    }
+}
+#[derive(Debug, Default)]
+pub struct KeyHintVecBuilder(pub(crate) Vec<KeyHint>);
+impl KeyHintVecBuilder {
+    pub const ITEM_SIZE: usize = 9;
+    pub fn set(mut self, v: Vec<KeyHint>) -> Self {
+        self.0 = v;
+        self
+    }
+    pub fn push(mut self, v: KeyHint) -> Self {
+        self.0.push(v);
+        self
+    }
+    pub fn extend<T: ::core::iter::IntoIterator<Item = KeyHint>>(mut self, iter: T) -> Self {
+        for elem in iter {
+            self.0.push(elem);
+        }
+        self
+    }
+    pub fn replace(&mut self, index: usize, v: KeyHint) -> Option<KeyHint> {
+        self.0
+            .get_mut(index)
+            .map(|item| ::core::mem::replace(item, v))
+    }
+}
+impl molecule::prelude::Builder for KeyHintVecBuilder {
+    type Entity = KeyHintVec;
+    const NAME: &'static str = "KeyHintVecBuilder";
+    fn expected_length(&self) -> usize {
+        molecule::NUMBER_SIZE + Self::ITEM_SIZE * self.0.len()
+    }
+    fn write<W: molecule::io::Write>(&self, writer: &mut W) -> molecule::io::Result<()> {
+        writer.write_all(&molecule::pack_number(self.0.len() as molecule::Number))?;
+        for inner in &self.0[..] {
+            writer.write_all(inner.as_slice())?;
+        }
+        Ok(())
+    }
+    fn build(&self) -> Self::Entity {
+        let mut inner = Vec::with_capacity(self.expected_length());
+        self.write(&mut inner)
+            .unwrap_or_else(|_| panic!("{} build should be ok", Self::NAME));
+        KeyHintVec::new_unchecked(inner.into())
+    }
+}
+pub struct KeyHintVecIterator(KeyHintVec, usize, usize);
+impl ::core::iter::Iterator for KeyHintVecIterator {
+    type Item = KeyHint;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.1 >= self.2 {
+            None
+        } else {
+            let ret = self.0.get_unchecked(self.1);
+            self.1 += 1;
+            Some(ret)
+        }
+    }
+}
+impl ::core::iter::ExactSizeIterator for KeyHintVecIterator {
+    fn len(&self) -> usize {
+        self.2 - self.1
+    }
+}
+impl ::core::iter::IntoIterator for KeyHintVec {
+    type Item = KeyHint;
+    type IntoIter = KeyHintVecIterator;
+    fn into_iter(self) -> Self::IntoIter {
+        let len = self.len();
+        KeyHintVecIterator(self, 0, len)
+    }
+}
+impl<'r> KeyHintVecReader<'r> {
+    pub fn iter<'t>(&'t self) -> KeyHintVecReaderIterator<'t, 'r> {
+        KeyHintVecReaderIterator(&self, 0, self.len())
+    }
+}
+pub struct KeyHintVecReaderIterator<'t, 'r>(&'t KeyHintVecReader<'r>, usize, usize);
+impl<'t: 'r, 'r> ::core::iter::Iterator for KeyHintVecReaderIterator<'t, 'r> {
+    type Item = KeyHintReader<'t>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.1 >= self.2 {
+            None
+        } else {
+            let ret = self.0.get_unchecked(self.1);
+            self.1 += 1;
+            Some(ret)
+        }
+    }
+}
+impl<'t: 'r, 'r> ::core::iter::ExactSizeIterator for KeyHintVecReaderIterator<'t, 'r> {
+    fn len(&self) -> usize {
+        self.2 - self.1
+    }
+}
+#[derive(Clone)]
 pub struct Registers32(molecule::bytes::Bytes);
 impl ::core::fmt::LowerHex for Registers32 {
     fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
@@ -3007,6 +3883,7 @@ impl ::core::fmt::Display for MemoryProof {
         write!(f, ", {}: {}", "root", self.root())?;
         write!(f, ", {}: {}", "kvs", self.kvs())?;
         write!(f, ", {}: {}", "merkle_proof", self.merkle_proof())?;
+        write!(f, ", {}: {}", "hints", self.hints())?;
         let extra_count = self.count_extra_fields();
         if extra_count != 0 {
             write!(f, ", .. ({} fields)", extra_count)?;
@@ -3017,15 +3894,15 @@ impl ::core::fmt::Display for MemoryProof {
 impl ::core::default::Default for MemoryProof {
     fn default() -> Self {
         let v: Vec<u8> = vec![
-            68, 0, 0, 0, 20, 0, 0, 0, 28, 0, 0, 0, 60, 0, 0, 0, 64, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            76, 0, 0, 0, 24, 0, 0, 0, 32, 0, 0, 0, 64, 0, 0, 0, 68, 0, 0, 0, 72, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         ];
         MemoryProof::new_unchecked(v.into())
     }
 }
 impl MemoryProof {
-    pub const FIELD_COUNT: usize = 4;
+    pub const FIELD_COUNT: usize = 5;
     pub fn total_size(&self) -> usize {
         molecule::unpack_number(self.as_slice()) as usize
     }
@@ -3063,11 +3940,17 @@ impl MemoryProof {
     pub fn merkle_proof(&self) -> Bytes {
         let slice = self.as_slice();
         let start = molecule::unpack_number(&slice[16..]) as usize;
+        let end = molecule::unpack_number(&slice[20..]) as usize;
+        Bytes::new_unchecked(self.0.slice(start..end))
+    }
+    pub fn hints(&self) -> KeyHintVec {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[20..]) as usize;
         if self.has_extra_fields() {
-            let end = molecule::unpack_number(&slice[20..]) as usize;
-            Bytes::new_unchecked(self.0.slice(start..end))
+            let end = molecule::unpack_number(&slice[24..]) as usize;
+            KeyHintVec::new_unchecked(self.0.slice(start..end))
         } else {
-            Bytes::new_unchecked(self.0.slice(start..))
+            KeyHintVec::new_unchecked(self.0.slice(start..))
         }
     }
     pub fn as_reader<'r>(&'r self) -> MemoryProofReader<'r> {
@@ -3101,6 +3984,7 @@ impl molecule::prelude::Entity for MemoryProof {
             .root(self.root())
             .kvs(self.kvs())
             .merkle_proof(self.merkle_proof())
+            .hints(self.hints())
     }
 }
 #[derive(Clone, Copy)]
@@ -3126,6 +4010,7 @@ impl<'r> ::core::fmt::Display for MemoryProofReader<'r> {
         write!(f, ", {}: {}", "root", self.root())?;
         write!(f, ", {}: {}", "kvs", self.kvs())?;
         write!(f, ", {}: {}", "merkle_proof", self.merkle_proof())?;
+        write!(f, ", {}: {}", "hints", self.hints())?;
         let extra_count = self.count_extra_fields();
         if extra_count != 0 {
             write!(f, ", .. ({} fields)", extra_count)?;
@@ -3134,7 +4019,7 @@ impl<'r> ::core::fmt::Display for MemoryProofReader<'r> {
     }
 }
 impl<'r> MemoryProofReader<'r> {
-    pub const FIELD_COUNT: usize = 4;
+    pub const FIELD_COUNT: usize = 5;
     pub fn total_size(&self) -> usize {
         molecule::unpack_number(self.as_slice()) as usize
     }
@@ -3172,11 +4057,17 @@ impl<'r> MemoryProofReader<'r> {
     pub fn merkle_proof(&self) -> BytesReader<'r> {
         let slice = self.as_slice();
         let start = molecule::unpack_number(&slice[16..]) as usize;
+        let end = molecule::unpack_number(&slice[20..]) as usize;
+        BytesReader::new_unchecked(&self.as_slice()[start..end])
+    }
+    pub fn hints(&self) -> KeyHintVecReader<'r> {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[20..]) as usize;
         if self.has_extra_fields() {
-            let end = molecule::unpack_number(&slice[20..]) as usize;
-            BytesReader::new_unchecked(&self.as_slice()[start..end])
+            let end = molecule::unpack_number(&slice[24..]) as usize;
+            KeyHintVecReader::new_unchecked(&self.as_slice()[start..end])
         } else {
-            BytesReader::new_unchecked(&self.as_slice()[start..])
+            KeyHintVecReader::new_unchecked(&self.as_slice()[start..])
         }
     }
 }
@@ -3233,6 +4124,7 @@ impl<'r> molecule::prelude::Reader<'r> for MemoryProofReader<'r> {
         Bytes32Reader::verify(&slice[offsets[1]..offsets[2]], compatible)?;
         KVPairVecReader::verify(&slice[offsets[2]..offsets[3]], compatible)?;
         BytesReader::verify(&slice[offsets[3]..offsets[4]], compatible)?;
+        KeyHintVecReader::verify(&slice[offsets[4]..offsets[5]], compatible)?;
         Ok(())
     }
 }
@@ -3242,9 +4134,10 @@ pub struct MemoryProofBuilder {
     pub(crate) root: Bytes32,
     pub(crate) kvs: KVPairVec,
     pub(crate) merkle_proof: Bytes,
+    pub(crate) hints: KeyHintVec,
 }
 impl MemoryProofBuilder {
-    pub const FIELD_COUNT: usize = 4;
+    pub const FIELD_COUNT: usize = 5;
     pub fn memory_size(mut self, v: Uint64) -> Self {
         self.memory_size = v;
         self
@@ -3261,6 +4154,10 @@ impl MemoryProofBuilder {
         self.merkle_proof = v;
         self
     }
+    pub fn hints(mut self, v: KeyHintVec) -> Self {
+        self.hints = v;
+        self
+    }
 }
 impl molecule::prelude::Builder for MemoryProofBuilder {
     type Entity = MemoryProof;
@@ -3271,6 +4168,7 @@ impl molecule::prelude::Builder for MemoryProofBuilder {
             + self.root.as_slice().len()
             + self.kvs.as_slice().len()
             + self.merkle_proof.as_slice().len()
+            + self.hints.as_slice().len()
     }
     fn write<W: molecule::io::Write>(&self, writer: &mut W) -> molecule::io::Result<()> {
         let mut total_size = molecule::NUMBER_SIZE * (Self::FIELD_COUNT + 1);
@@ -3283,6 +4181,8 @@ impl molecule::prelude::Builder for MemoryProofBuilder {
         total_size += self.kvs.as_slice().len();
         offsets.push(total_size);
         total_size += self.merkle_proof.as_slice().len();
+        offsets.push(total_size);
+        total_size += self.hints.as_slice().len();
         writer.write_all(&molecule::pack_number(total_size as molecule::Number))?;
         for offset in offsets.into_iter() {
             writer.write_all(&molecule::pack_number(offset as molecule::Number))?;
@@ -3291,6 +4191,7 @@ impl molecule::prelude::Builder for MemoryProofBuilder {
         writer.write_all(self.root.as_slice())?;
         writer.write_all(self.kvs.as_slice())?;
         writer.write_all(self.merkle_proof.as_slice())?;
+        writer.write_all(self.hints.as_slice())?;
         Ok(())
     }
     fn build(&self) -> Self::Entity {
@@ -3329,6 +4230,8 @@ impl ::core::fmt::Display for StepProof32 {
         write!(f, ", {}: {}", "running", self.running())?;
         write!(f, ", {}: {}", "isa", self.isa())?;
         write!(f, ", {}: {}", "version", self.version())?;
+        write!(f, ", {}: {}", "syscalls", self.syscalls())?;
+        write!(f, ", {}: {}", "syscall_witness", self.syscall_witness())?;
         let extra_count = self.count_extra_fields();
         if extra_count != 0 {
             write!(f, ", .. ({} fields)", extra_count)?;
@@ -3339,22 +4242,23 @@ impl ::core::fmt::Display for StepProof32 {
 impl ::core::default::Default for StepProof32 {
     fn default() -> Self {
         let v: Vec<u8> = vec![
-            22, 1, 0, 0, 44, 0, 0, 0, 52, 0, 0, 0, 180, 0, 0, 0, 184, 0, 0, 0, 188, 0, 0, 0, 0, 1,
-            0, 0, 8, 1, 0, 0, 16, 1, 0, 0, 17, 1, 0, 0, 18, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            46, 1, 0, 0, 52, 0, 0, 0, 60, 0, 0, 0, 188, 0, 0, 0, 192, 0, 0, 0, 196, 0, 0, 0, 16, 1,
+            0, 0, 24, 1, 0, 0, 32, 1, 0, 0, 33, 1, 0, 0, 34, 1, 0, 0, 38, 1, 0, 0, 42, 1, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 76, 0,
+            0, 0, 24, 0, 0, 0, 32, 0, 0, 0, 64, 0, 0, 0, 68, 0, 0, 0, 72, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 68, 0, 0, 0, 20, 0, 0, 0, 28, 0,
-            0, 0, 60, 0, 0, 0, 64, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         ];
         StepProof32::new_unchecked(v.into())
     }
 }
 impl StepProof32 {
-    pub const FIELD_COUNT: usize = 10;
+    pub const FIELD_COUNT: usize = 12;
     pub fn total_size(&self) -> usize {
         molecule::unpack_number(self.as_slice()) as usize
     }
@@ -3428,11 +4332,23 @@ impl StepProof32 {
     pub fn version(&self) -> Uint32 {
         let slice = self.as_slice();
         let start = molecule::unpack_number(&slice[40..]) as usize;
+        let end = molecule::unpack_number(&slice[44..]) as usize;
+        Uint32::new_unchecked(self.0.slice(start..end))
+    }
+    pub fn syscalls(&self) -> SyscallCommitmentVec {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[44..]) as usize;
+        let end = molecule::unpack_number(&slice[48..]) as usize;
+        SyscallCommitmentVec::new_unchecked(self.0.slice(start..end))
+    }
+    pub fn syscall_witness(&self) -> Bytes {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[48..]) as usize;
         if self.has_extra_fields() {
-            let end = molecule::unpack_number(&slice[44..]) as usize;
-            Uint32::new_unchecked(self.0.slice(start..end))
+            let end = molecule::unpack_number(&slice[52..]) as usize;
+            Bytes::new_unchecked(self.0.slice(start..end))
         } else {
-            Uint32::new_unchecked(self.0.slice(start..))
+            Bytes::new_unchecked(self.0.slice(start..))
         }
     }
     pub fn as_reader<'r>(&'r self) -> StepProof32Reader<'r> {
@@ -3472,6 +4388,8 @@ impl molecule::prelude::Entity for StepProof32 {
             .running(self.running())
             .isa(self.isa())
             .version(self.version())
+            .syscalls(self.syscalls())
+            .syscall_witness(self.syscall_witness())
     }
 }
 #[derive(Clone, Copy)]
@@ -3503,6 +4421,8 @@ impl<'r> ::core::fmt::Display for StepProof32Reader<'r> {
         write!(f, ", {}: {}", "running", self.running())?;
         write!(f, ", {}: {}", "isa", self.isa())?;
         write!(f, ", {}: {}", "version", self.version())?;
+        write!(f, ", {}: {}", "syscalls", self.syscalls())?;
+        write!(f, ", {}: {}", "syscall_witness", self.syscall_witness())?;
         let extra_count = self.count_extra_fields();
         if extra_count != 0 {
             write!(f, ", .. ({} fields)", extra_count)?;
@@ -3511,7 +4431,7 @@ impl<'r> ::core::fmt::Display for StepProof32Reader<'r> {
     }
 }
 impl<'r> StepProof32Reader<'r> {
-    pub const FIELD_COUNT: usize = 10;
+    pub const FIELD_COUNT: usize = 12;
     pub fn total_size(&self) -> usize {
         molecule::unpack_number(self.as_slice()) as usize
     }
@@ -3585,11 +4505,23 @@ impl<'r> StepProof32Reader<'r> {
     pub fn version(&self) -> Uint32Reader<'r> {
         let slice = self.as_slice();
         let start = molecule::unpack_number(&slice[40..]) as usize;
+        let end = molecule::unpack_number(&slice[44..]) as usize;
+        Uint32Reader::new_unchecked(&self.as_slice()[start..end])
+    }
+    pub fn syscalls(&self) -> SyscallCommitmentVecReader<'r> {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[44..]) as usize;
+        let end = molecule::unpack_number(&slice[48..]) as usize;
+        SyscallCommitmentVecReader::new_unchecked(&self.as_slice()[start..end])
+    }
+    pub fn syscall_witness(&self) -> BytesReader<'r> {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[48..]) as usize;
         if self.has_extra_fields() {
-            let end = molecule::unpack_number(&slice[44..]) as usize;
-            Uint32Reader::new_unchecked(&self.as_slice()[start..end])
+            let end = molecule::unpack_number(&slice[52..]) as usize;
+            BytesReader::new_unchecked(&self.as_slice()[start..end])
         } else {
-            Uint32Reader::new_unchecked(&self.as_slice()[start..])
+            BytesReader::new_unchecked(&self.as_slice()[start..])
         }
     }
 }
@@ -3652,6 +4584,8 @@ impl<'r> molecule::prelude::Reader<'r> for StepProof32Reader<'r> {
         ByteReader::verify(&slice[offsets[7]..offsets[8]], compatible)?;
         ByteReader::verify(&slice[offsets[8]..offsets[9]], compatible)?;
         Uint32Reader::verify(&slice[offsets[9]..offsets[10]], compatible)?;
+        SyscallCommitmentVecReader::verify(&slice[offsets[10]..offsets[11]], compatible)?;
+        BytesReader::verify(&slice[offsets[11]..offsets[12]], compatible)?;
         Ok(())
     }
 }
@@ -3667,9 +4601,11 @@ pub struct StepProof32Builder {
     pub(crate) running: Byte,
     pub(crate) isa: Byte,
     pub(crate) version: Uint32,
+    pub(crate) syscalls: SyscallCommitmentVec,
+    pub(crate) syscall_witness: Bytes,
 }
 impl StepProof32Builder {
-    pub const FIELD_COUNT: usize = 10;
+    pub const FIELD_COUNT: usize = 12;
     pub fn step_num(mut self, v: Uint64) -> Self {
         self.step_num = v;
         self
@@ -3710,6 +4646,14 @@ impl StepProof32Builder {
         self.version = v;
         self
     }
+    pub fn syscalls(mut self, v: SyscallCommitmentVec) -> Self {
+        self.syscalls = v;
+        self
+    }
+    pub fn syscall_witness(mut self, v: Bytes) -> Self {
+        self.syscall_witness = v;
+        self
+    }
 }
 impl molecule::prelude::Builder for StepProof32Builder {
     type Entity = StepProof32;
@@ -3726,6 +4670,8 @@ impl molecule::prelude::Builder for StepProof32Builder {
             + self.running.as_slice().len()
             + self.isa.as_slice().len()
             + self.version.as_slice().len()
+            + self.syscalls.as_slice().len()
+            + self.syscall_witness.as_slice().len()
     }
     fn write<W: molecule::io::Write>(&self, writer: &mut W) -> molecule::io::Result<()> {
         let mut total_size = molecule::NUMBER_SIZE * (Self::FIELD_COUNT + 1);
@@ -3750,6 +4696,10 @@ impl molecule::prelude::Builder for StepProof32Builder {
         total_size += self.isa.as_slice().len();
         offsets.push(total_size);
         total_size += self.version.as_slice().len();
+        offsets.push(total_size);
+        total_size += self.syscalls.as_slice().len();
+        offsets.push(total_size);
+        total_size += self.syscall_witness.as_slice().len();
         writer.write_all(&molecule::pack_number(total_size as molecule::Number))?;
         for offset in offsets.into_iter() {
             writer.write_all(&molecule::pack_number(offset as molecule::Number))?;
@@ -3764,6 +4714,8 @@ impl molecule::prelude::Builder for StepProof32Builder {
         writer.write_all(self.running.as_slice())?;
         writer.write_all(self.isa.as_slice())?;
         writer.write_all(self.version.as_slice())?;
+        writer.write_all(self.syscalls.as_slice())?;
+        writer.write_all(self.syscall_witness.as_slice())?;
         Ok(())
     }
     fn build(&self) -> Self::Entity {
@@ -3802,6 +4754,8 @@ impl ::core::fmt::Display for StepProof64 {
         write!(f, ", {}: {}", "running", self.running())?;
         write!(f, ", {}: {}", "isa", self.isa())?;
         write!(f, ", {}: {}", "version", self.version())?;
+        write!(f, ", {}: {}", "syscalls", self.syscalls())?;
+        write!(f, ", {}: {}", "syscall_witness", self.syscall_witness())?;
         let extra_count = self.count_extra_fields();
         if extra_count != 0 {
             write!(f, ", .. ({} fields)", extra_count)?;
@@ -3812,8 +4766,8 @@ impl ::core::fmt::Display for StepProof64 {
 impl ::core::default::Default for StepProof64 {
     fn default() -> Self {
         let v: Vec<u8> = vec![
-            158, 1, 0, 0, 44, 0, 0, 0, 52, 0, 0, 0, 52, 1, 0, 0, 60, 1, 0, 0, 68, 1, 0, 0, 136, 1,
-            0, 0, 144, 1, 0, 0, 152, 1, 0, 0, 153, 1, 0, 0, 154, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            182, 1, 0, 0, 52, 0, 0, 0, 60, 0, 0, 0, 60, 1, 0, 0, 68, 1, 0, 0, 76, 1, 0, 0, 152, 1,
+            0, 0, 160, 1, 0, 0, 168, 1, 0, 0, 169, 1, 0, 0, 170, 1, 0, 0, 174, 1, 0, 0, 178, 1, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -3823,16 +4777,17 @@ impl ::core::default::Default for StepProof64 {
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 68, 0, 0, 0, 20, 0, 0, 0, 28, 0, 0, 0, 60, 0, 0, 0,
-            64, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 76, 0, 0, 0, 24, 0, 0, 0,
+            32, 0, 0, 0, 64, 0, 0, 0, 68, 0, 0, 0, 72, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
         ];
         StepProof64::new_unchecked(v.into())
     }
 }
 impl StepProof64 {
-    pub const FIELD_COUNT: usize = 10;
+    pub const FIELD_COUNT: usize = 12;
     pub fn total_size(&self) -> usize {
         molecule::unpack_number(self.as_slice()) as usize
     }
@@ -3906,11 +4861,23 @@ impl StepProof64 {
     pub fn version(&self) -> Uint32 {
         let slice = self.as_slice();
         let start = molecule::unpack_number(&slice[40..]) as usize;
+        let end = molecule::unpack_number(&slice[44..]) as usize;
+        Uint32::new_unchecked(self.0.slice(start..end))
+    }
+    pub fn syscalls(&self) -> SyscallCommitmentVec {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[44..]) as usize;
+        let end = molecule::unpack_number(&slice[48..]) as usize;
+        SyscallCommitmentVec::new_unchecked(self.0.slice(start..end))
+    }
+    pub fn syscall_witness(&self) -> Bytes {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[48..]) as usize;
         if self.has_extra_fields() {
-            let end = molecule::unpack_number(&slice[44..]) as usize;
-            Uint32::new_unchecked(self.0.slice(start..end))
+            let end = molecule::unpack_number(&slice[52..]) as usize;
+            Bytes::new_unchecked(self.0.slice(start..end))
         } else {
-            Uint32::new_unchecked(self.0.slice(start..))
+            Bytes::new_unchecked(self.0.slice(start..))
         }
     }
     pub fn as_reader<'r>(&'r self) -> StepProof64Reader<'r> {
@@ -3950,6 +4917,8 @@ impl molecule::prelude::Entity for StepProof64 {
             .running(self.running())
             .isa(self.isa())
             .version(self.version())
+            .syscalls(self.syscalls())
+            .syscall_witness(self.syscall_witness())
     }
 }
 #[derive(Clone, Copy)]
@@ -3981,6 +4950,8 @@ impl<'r> ::core::fmt::Display for StepProof64Reader<'r> {
         write!(f, ", {}: {}", "running", self.running())?;
         write!(f, ", {}: {}", "isa", self.isa())?;
         write!(f, ", {}: {}", "version", self.version())?;
+        write!(f, ", {}: {}", "syscalls", self.syscalls())?;
+        write!(f, ", {}: {}", "syscall_witness", self.syscall_witness())?;
         let extra_count = self.count_extra_fields();
         if extra_count != 0 {
             write!(f, ", .. ({} fields)", extra_count)?;
@@ -3989,7 +4960,7 @@ impl<'r> ::core::fmt::Display for StepProof64Reader<'r> {
     }
 }
 impl<'r> StepProof64Reader<'r> {
-    pub const FIELD_COUNT: usize = 10;
+    pub const FIELD_COUNT: usize = 12;
     pub fn total_size(&self) -> usize {
         molecule::unpack_number(self.as_slice()) as usize
     }
@@ -4063,11 +5034,23 @@ impl<'r> StepProof64Reader<'r> {
     pub fn version(&self) -> Uint32Reader<'r> {
         let slice = self.as_slice();
         let start = molecule::unpack_number(&slice[40..]) as usize;
+        let end = molecule::unpack_number(&slice[44..]) as usize;
+        Uint32Reader::new_unchecked(&self.as_slice()[start..end])
+    }
+    pub fn syscalls(&self) -> SyscallCommitmentVecReader<'r> {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[44..]) as usize;
+        let end = molecule::unpack_number(&slice[48..]) as usize;
+        SyscallCommitmentVecReader::new_unchecked(&self.as_slice()[start..end])
+    }
+    pub fn syscall_witness(&self) -> BytesReader<'r> {
+        let slice = self.as_slice();
+        let start = molecule::unpack_number(&slice[48..]) as usize;
         if self.has_extra_fields() {
-            let end = molecule::unpack_number(&slice[44..]) as usize;
-            Uint32Reader::new_unchecked(&self.as_slice()[start..end])
+            let end = molecule::unpack_number(&slice[52..]) as usize;
+            BytesReader::new_unchecked(&self.as_slice()[start..end])
         } else {
-            Uint32Reader::new_unchecked(&self.as_slice()[start..])
+            BytesReader::new_unchecked(&self.as_slice()[start..])
         }
     }
 }
@@ -4130,6 +5113,8 @@ impl<'r> molecule::prelude::Reader<'r> for StepProof64Reader<'r> {
         ByteReader::verify(&slice[offsets[7]..offsets[8]], compatible)?;
         ByteReader::verify(&slice[offsets[8]..offsets[9]], compatible)?;
         Uint32Reader::verify(&slice[offsets[9]..offsets[10]], compatible)?;
+        SyscallCommitmentVecReader::verify(&slice[offsets[10]..offsets[11]], compatible)?;
+        BytesReader::verify(&slice[offsets[11]..offsets[12]], compatible)?;
         Ok(())
     }
 }
@@ -4145,9 +5130,11 @@ pub struct StepProof64Builder {
     pub(crate) running: Byte,
     pub(crate) isa: Byte,
     pub(crate) version: Uint32,
+    pub(crate) syscalls: SyscallCommitmentVec,
+    pub(crate) syscall_witness: Bytes,
 }
 impl StepProof64Builder {
-    pub const FIELD_COUNT: usize = 10;
+    pub const FIELD_COUNT: usize = 12;
     pub fn step_num(mut self, v: Uint64) -> Self {
         self.step_num = v;
         self
@@ -4188,6 +5175,14 @@ impl StepProof64Builder {
         self.version = v;
         self
     }
+    pub fn syscalls(mut self, v: SyscallCommitmentVec) -> Self {
+        self.syscalls = v;
+        self
+    }
+    pub fn syscall_witness(mut self, v: Bytes) -> Self {
+        self.syscall_witness = v;
+        self
+    }
 }
 impl molecule::prelude::Builder for StepProof64Builder {
     type Entity = StepProof64;
@@ -4204,6 +5199,8 @@ impl molecule::prelude::Builder for StepProof64Builder {
             + self.running.as_slice().len()
             + self.isa.as_slice().len()
             + self.version.as_slice().len()
+            + self.syscalls.as_slice().len()
+            + self.syscall_witness.as_slice().len()
     }
     fn write<W: molecule::io::Write>(&self, writer: &mut W) -> molecule::io::Result<()> {
         let mut total_size = molecule::NUMBER_SIZE * (Self::FIELD_COUNT + 1);
@@ -4228,6 +5225,10 @@ impl molecule::prelude::Builder for StepProof64Builder {
         total_size += self.isa.as_slice().len();
         offsets.push(total_size);
         total_size += self.version.as_slice().len();
+        offsets.push(total_size);
+        total_size += self.syscalls.as_slice().len();
+        offsets.push(total_size);
+        total_size += self.syscall_witness.as_slice().len();
         writer.write_all(&molecule::pack_number(total_size as molecule::Number))?;
         for offset in offsets.into_iter() {
             writer.write_all(&molecule::pack_number(offset as molecule::Number))?;
@@ -4242,6 +5243,8 @@ impl molecule::prelude::Builder for StepProof64Builder {
         writer.write_all(self.running.as_slice())?;
         writer.write_all(self.isa.as_slice())?;
         writer.write_all(self.version.as_slice())?;
+        writer.write_all(self.syscalls.as_slice())?;
+        writer.write_all(self.syscall_witness.as_slice())?;
         Ok(())
     }
     fn build(&self) -> Self::Entity {
@@ -0,0 +1,160 @@
+//! Commitment scheme conformance vectors.
+//!
+//! Expected digests are computed independently from the encoding in
+//! docs/vm-steps-commit-and-verify-design.md. They are checked by std tests
+//! and by the `commitment-vectors` contract in contract tests, so host and
+//! `no_std` builds are held to the same scheme.
+
+use ckb_vm::{Bytes, Register, RISCV_GENERAL_REGISTER_NUMBER};
+
+use crate::{
+    machine::{hash_args, hash_program, InitialCommitment, MachineState, StepCommitment},
+    memory::{hash_data_chunk_key, hash_flag_key, MemoryCommitment},
+    syscalls::{LoadInput, SyscallCommitment, LOAD_INPUT_SYSCALL_NUMBER},
+    types::Bytes32,
+};
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "sha256")] {
+        const EXPECTED: [(&str, &str); 8] = [
+            ("program", "5c30b8f26d94adcacbe8228473ac997bcae32180de24f9b8b0a19b9aa624cd4c"),
+            ("args", "fb3ded2cd93f8b93ac1efdc2fcc1bde979eded8e27a307c277d40e75faf1eafa"),
+            ("input", "c560a169bd1385ab42bdc8154fc6267666f35be12795ae2441998bc2c2136d77"),
+            ("flag_key", "33c6682a8a88ade25d73cb38d42886926866f52cc3d3cd29c7b8c0252cd900a2"),
+            ("data_chunk_key", "1140caf50cc6ef7f8f36f63ab626a2f2e4318aa2fa23af96dbe6cb17b489d94c"),
+            ("initial", "4d2e62c85825d01b77818d197e3fdcc5a2e2212edb0990663401995ea10b3231"),
+            ("machine32", "9a18928e54c555ccd500d2cd46bdd9c8dbb465d86127f5b119827509f8521f6a"),
+            ("machine64", "66df159bd6658c2d9ff5ed4e5609460361a39857e7db8d3e3d48ec74f12597f6"),
+        ];
+    } else {
+        const EXPECTED: [(&str, &str); 8] = [
+            ("program", "302ff2d86732faac9a0276e434a51ca8af45f88d05793fad28c5345be2a2b7a5"),
+            ("args", "595c36c9aacf888cf30e37681d996076e6d3004f26ff76afd68c111281634b52"),
+            ("input", "4833cd1d6630392c51316c15462f84f465355949c538c156dbbc4bc52a41eb53"),
+            ("flag_key", "0fdfa1fe69523a91ba282ed8134198b1d316d0aab06842c53ab5e88c5b23f53c"),
+            ("data_chunk_key", "82c3be3bee1b7291fadb37a4461328fe0696c2979216c6c519dd62b34097465b"),
+            ("initial", "3aa88d1ba669522dd0ea1750013a6f3a8e3f4e54ce165258a8f226820979c132"),
+            ("machine32", "16def04cf777f4aff5e7272a7b29eb0992c3b817a37c7cf4b5b6913e9625c299"),
+            ("machine64", "692e8cff7e2bacb04b4a159daa6dc736113a3a2c7a91a538e5b5359d2651fba1"),
+        ];
+    }
+}
+
+/// Check every vector, error names the first mismatched one
+pub fn check_test_vectors() -> Result<(), &'static str> {
+    let actual = [
+        hash_program(&Bytes::from_static(b"woss")),
+        hash_args(&[Bytes::from_static(b"a"), Bytes::from_static(b"bc")]),
+        LoadInput::hash_input(b"input"),
+        hash_flag_key(3),
+        hash_data_chunk_key(5),
+        initial_commitment(),
+        machine_commitment::<u32>(|idx| idx * 3),
+        machine_commitment::<u64>(|idx| idx << 33),
+    ];
+
+    for ((name, expected), actual) in EXPECTED.iter().zip(actual.iter()) {
+        if decode_hex(expected) != *actual {
+            return Err(name);
+        }
+    }
+
+    Ok(())
+}
+
+fn initial_commitment() -> Bytes32 {
+    let initial = InitialCommitment {
+        program_hash: Bytes32::from_u8(1),
+        args_hash: Bytes32::from_u8(2),
+        step: StepCommitment {
+            step_num: 0,
+            commitment: Bytes32::from_u8(3),
+        },
+    };
+    initial.commitment()
+}
+
+fn machine_commitment<R: Register>(reg: impl Fn(u64) -> u64) -> Bytes32 {
+    let mut registers: [R; RISCV_GENERAL_REGISTER_NUMBER] = Default::default();
+    for (idx, r) in registers.iter_mut().enumerate() {
+        *r = R::from_u64(reg(idx as u64));
+    }
+    let syscalls = [SyscallCommitment {
+        number: LOAD_INPUT_SYSCALL_NUMBER,
+        commitment: Bytes32::from_u8(9),
+    }];
+
+    let state = MachineState {
+        registers: &registers,
+        pc: &R::from_u64(0x1000),
+        next_pc: &R::from_u64(0x1004),
+        memory: MemoryCommitment {
+            size: 4 << 20,
+            root: Bytes32::from_u8(7),
+        },
+        cycles: 100,
+        max_cycles: 1000,
+        running: true,
+        isa: 1,
+        version: 1,
+        syscalls: &syscalls,
+    };
+    state.commitment()
+}
+
+fn decode_hex(hex: &str) -> Bytes32 {
+    let mut buf = [0u8; 32];
+    for (idx, b) in buf.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16).expect("valid hex");
+    }
+    buf.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        collections::BTreeMap,
+        memory::{
+            hash_data_chunk_key, prover::ProverSMT, verifier::VerifierSMT, MemoryProof, SMTOps,
+            SMTProve, SMTRestore,
+        },
+        types::Bytes32,
+    };
+
+    use super::{check_test_vectors, decode_hex};
+
+    // Root of memory tree holding `Bytes32::from_u8(1)` at data chunk 5
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "sha256")] {
+            const MEMORY_ROOT: &str = "97117b42026a43f36a2f70a4085dc46931aa7730249ff42b16494311d890b69b";
+        } else {
+            const MEMORY_ROOT: &str = "a448358dadbcbb6c89fc82faab05faa6b7830b600f9290039cd480a8eae04d42";
+        }
+    }
+
+    #[test]
+    fn test_commitment_vectors() {
+        check_test_vectors().unwrap();
+    }
+
+    #[test]
+    fn test_memory_root_vector() {
+        let key = hash_data_chunk_key(5);
+        let value = Bytes32::from_u8(1);
+        let mut prover = ProverSMT::default();
+        SMTOps::update(&mut prover, key, value).unwrap();
+        let root = SMTOps::root(&prover).unwrap();
+        assert_eq!(root, decode_hex(MEMORY_ROOT));
+
+        // Verifier calculates the same root from proof
+        let proof = MemoryProof {
+            memory_size: 4 << 20,
+            root,
+            kvs: BTreeMap::from([(key, value)]),
+            merkle_proof: prover.prove(&[key]).unwrap(),
+            hints: Vec::new(),
+        };
+        let verifier = VerifierSMT::restore_from_proof(proof).unwrap();
+        assert_eq!(verifier.root().unwrap(), root);
+    }
+}
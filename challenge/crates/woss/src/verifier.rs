@@ -1,15 +1,16 @@
-use ckb_vm::{Error, Register};
+use ckb_vm::{instructions::Instruction, Register};
 
 use crate::{
+    error::Error,
     machine::{Machine, StepCommitment, StepProof},
-    memory::verifier::VerifierSMT,
+    memory::{verifier::VerifierSMT, SMTOps, SMTRestore},
 };
 
-pub struct Verifier<R> {
-    machine: Machine<R, VerifierSMT>,
+pub struct Verifier<R, M = VerifierSMT> {
+    machine: Machine<R, M>,
 }
 
-impl<R: Register> Verifier<R> {
+impl<R: Register + 'static, M: SMTRestore + SMTOps + 'static> Verifier<R, M> {
     pub fn from_proof(proof: StepProof<R>) -> Result<Self, Error> {
         let machine = Machine::restore_from_proof(proof)?;
         Ok(Self { machine })
@@ -19,8 +20,32 @@ impl<R: Register> Verifier<R> {
         self.machine.commit_step()
     }
 
+    /// Fails with `CommitmentMismatch` unless current step commits to
+    /// `expected`
+    pub fn check_commitment(&mut self, expected: &StepCommitment) -> Result<(), Error> {
+        if self.commit_step()? != *expected {
+            return Err(Error::CommitmentMismatch);
+        }
+        Ok(())
+    }
+
+    /// Instruction executed by next step, its memory is covered by proof
+    pub fn current_instruction(&mut self) -> Result<Instruction, Error> {
+        self.machine.current_instruction()
+    }
+
+    /// Fails if step doesn't access every key of proof, or accesses a key
+    /// not in proof
     pub fn execute_next_step(&mut self) -> Result<StepCommitment, Error> {
         self.machine.execute_next_step()?;
+        self.machine.check_proof_keys()?;
+        self.commit_step()
+    }
+
+    /// Execute steps proved by `prove_steps`, stops early if program exits
+    pub fn execute_steps(&mut self, count: u64) -> Result<StepCommitment, Error> {
+        self.machine.execute_steps(count)?;
+        self.machine.check_proof_keys()?;
         self.commit_step()
     }
 }
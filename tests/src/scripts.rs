@@ -1,2 +1,118 @@
+pub mod commitment_vectors;
 pub mod step_verifier_lock;
 pub mod tx_dataloader;
+
+use std::{fs, io::Read, path::PathBuf};
+
+use ckb_script::TransactionScriptsVerifier;
+use ckb_types::{
+    bytes::Bytes,
+    core::ScriptHashType,
+    packed::{
+        CellDep, CellInput, CellOutput, OutPoint, RawTransaction, Script, Transaction, Uint64,
+        WitnessArgs,
+    },
+    prelude::{Builder, Entity, Pack, PackVec},
+};
+
+use self::tx_dataloader::{CellInfo, InputCellInfo, TxDataLoader};
+
+const SCRIPT_DIR: &str = "../challenge/build/release";
+
+/// Contract binary built by capsule
+pub struct ScriptProgram {
+    code: Bytes,
+    code_hash: [u8; 32],
+}
+
+impl ScriptProgram {
+    pub fn load(name: &str) -> Self {
+        let mut buf = Vec::new();
+        let mut path = PathBuf::new();
+        path.push(SCRIPT_DIR);
+        path.push(name);
+        let mut f = fs::File::open(&path).expect("load program");
+        f.read_to_end(&mut buf).expect("read program");
+        let code = Bytes::from(buf);
+
+        const CKB_PERSONALIZATION: &[u8] = b"ckb-default-hash";
+
+        let mut code_hash = [0u8; 32];
+        let mut hasher = blake2b_ref::Blake2bBuilder::new(32)
+            .personal(CKB_PERSONALIZATION)
+            .build();
+        hasher.update(&code);
+        hasher.finalize(&mut code_hash);
+
+        Self { code, code_hash }
+    }
+}
+
+/// Unlock a cell locked by `program` with given args, `lock_witness` goes to
+/// `WitnessArgs.lock`. Returns consumed cycles.
+pub fn run_lock_script(
+    program: &ScriptProgram,
+    args: &[u8],
+    lock_witness: Bytes,
+) -> Result<u64, ckb_error::Error> {
+    let script_cell = {
+        let cell = CellInfo {
+            output: CellOutput::new_builder()
+                .capacity(Pack::pack(&u64::MAX))
+                .build(),
+            data: program.code.clone(),
+            data_hash: program.code_hash.pack(),
+        };
+        let out_point = OutPoint::new_builder()
+            .tx_hash(rand::random::<[u8; 32]>().pack())
+            .build();
+        InputCellInfo {
+            input: CellInput::new_builder().previous_output(out_point).build(),
+            cell,
+        }
+    };
+
+    let test_input_cell = {
+        let args = Pack::pack(args);
+        // Enable vm version1
+        let lock = Script::new_builder()
+            .code_hash(program.code_hash.pack())
+            .hash_type(ScriptHashType::Data1.into())
+            .args(args)
+            .build();
+
+        let cell = CellInfo {
+            output: CellOutput::new_builder()
+                .capacity(Pack::<Uint64>::pack(&u64::MAX))
+                .lock(lock)
+                .build(),
+            ..Default::default()
+        };
+        let out_point = OutPoint::new_builder()
+            .tx_hash(rand::random::<[u8; 32]>().pack())
+            .build();
+        InputCellInfo {
+            input: CellInput::new_builder().previous_output(out_point).build(),
+            cell,
+        }
+    };
+
+    let raw_tx = RawTransaction::new_builder()
+        .cell_deps(vec![CellDep::from(&script_cell)].pack())
+        .inputs(vec![test_input_cell.input.clone()].pack())
+        .build();
+    let witness = WitnessArgs::new_builder()
+        .lock(Some(lock_witness).pack())
+        .build();
+    let tx = Transaction::new_builder()
+        .raw(raw_tx)
+        .witnesses([witness.as_bytes()].pack())
+        .build();
+
+    let mut tx_dataloader = TxDataLoader::default();
+    tx_dataloader.extend_cell_deps(vec![script_cell]);
+    tx_dataloader.extend_inputs(vec![test_input_cell]);
+
+    let resolved_tx = tx_dataloader.resolve_tx(&tx);
+    TransactionScriptsVerifier::new(&resolved_tx, &tx_dataloader).verify(u64::MAX)
+}
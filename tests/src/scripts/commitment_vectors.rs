@@ -0,0 +1,18 @@
+use ckb_types::bytes::Bytes;
+use once_cell::sync::Lazy;
+
+use super::{run_lock_script, ScriptProgram};
+
+const COMMITMENT_VECTORS: &str = "commitment-vectors";
+
+static COMMITMENT_VECTORS_PROGRAM: Lazy<ScriptProgram> =
+    Lazy::new(|| ScriptProgram::load(COMMITMENT_VECTORS));
+
+#[test]
+fn test_commitment_vectors_in_contract() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    // Vectors computed by `no_std` build on ckb-vm
+    let cycles = run_lock_script(&COMMITMENT_VECTORS_PROGRAM, &[], Bytes::new()).unwrap();
+    println!("cycles {}", cycles);
+}
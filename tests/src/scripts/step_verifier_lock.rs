@@ -1,56 +1,318 @@
-use std::{fs, io::Read, path::PathBuf};
+use std::fs;
 
-use ckb_script::TransactionScriptsVerifier;
 use ckb_types::{
     bytes::Bytes,
-    core::ScriptHashType,
-    packed::{
-        CellDep, CellInput, CellOutput, OutPoint, RawTransaction, Script, Transaction, Uint64,
-        WitnessArgs,
-    },
-    prelude::{Builder, Entity, Pack, PackVec},
+    packed::WitnessArgs,
+    prelude::{Builder, Entity, Pack},
 };
 use once_cell::sync::Lazy;
 use rand::Rng;
-use woss::{prover::Prover, types::conversion::Pack as WossPack, verifier::Verifier};
-
-use crate::scripts::tx_dataloader::TxDataLoader;
+use woss::{
+    budget::{estimate_proof, CycleModel},
+    ckb_vm::Register,
+    machine::{Machine, StepCommitment, StepProof},
+    memory::{hash_data_chunk_key, prover::ProverSMT, KeyHint, SMTProve},
+    prover::Prover,
+    syscalls::LoadInput,
+    types::{
+        conversion::{MoleculeSize, Pack as WossPack, TryUnpack},
+        packed, Bytes32,
+    },
+    verifier::Verifier,
+    Error as WossError,
+};
 
-use super::tx_dataloader::{CellInfo, InputCellInfo};
+use super::{run_lock_script, ScriptProgram};
 
-const SCRIPT_DIR: &str = "../challenge/build/release";
 const STEP_VERIFIER_LOCK: &str = "step-verifier-lock";
 
-static STEP_VERIFIER_LOCK_PROGRAM: Lazy<Bytes> = Lazy::new(|| {
-    let mut buf = Vec::new();
-    let mut path = PathBuf::new();
-    path.push(&SCRIPT_DIR);
-    path.push(&STEP_VERIFIER_LOCK);
-    let mut f = fs::File::open(&path).expect("load program");
-    f.read_to_end(&mut buf).expect("read program");
-    Bytes::from(buf.to_vec())
-});
-
-static STEP_VERIFIER_LOCK_CODE_HASH: Lazy<[u8; 32]> = Lazy::new(|| {
-    const CKB_PERSONALIZATION: &[u8] = b"ckb-default-hash";
-
-    let mut buf = [0u8; 32];
-    let mut hasher = blake2b_ref::Blake2bBuilder::new(32)
-        .personal(CKB_PERSONALIZATION)
-        .build();
-    hasher.update(&STEP_VERIFIER_LOCK_PROGRAM);
-    hasher.finalize(&mut buf);
-    buf
-});
+// Error codes of step-verifier-lock, see its `error.rs`
+const WOSS_ERROR_BASE: i8 = 32;
+
+static STEP_VERIFIER_LOCK_PROGRAM: Lazy<ScriptProgram> =
+    Lazy::new(|| ScriptProgram::load(STEP_VERIFIER_LOCK));
 
 #[test]
 fn test_step_verifier_lock() {
     let _ = env_logger::builder().is_test(true).try_init();
 
-    let mut prover = Prover::<u32>::new(4 << 20);
     // https://github.com/nervosnetwork/ckb-vm/blob/develop/tests/programs/simple
+    let (proof, prev_step, random_step) = prove_random_step::<u32>("../simple");
+
+    let args = {
+        let mut com = prev_step.commitment.to_vec();
+        com.extend_from_slice(&random_step.commitment);
+        com
+    };
+    let cycles = verify_in_lock(args, proof.pack().as_bytes()).unwrap();
+    println!("cycles {}", cycles);
+}
+
+#[test]
+fn test_step_verifier_lock_rv64() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    // Assembled from simple64.S
+    let (proof, prev_step, random_step) = prove_random_step::<u64>("../simple64");
+
+    let args = {
+        let mut args = prev_step.commitment.to_vec();
+        args.extend_from_slice(&random_step.commitment);
+        args.push(64);
+        args
+    };
+    let cycles = verify_in_lock(args.clone(), proof.pack().as_bytes()).unwrap();
+    println!("cycles {}", cycles);
+
+    // RV64 proof shouldn't be accepted as RV32 one
+    let rv32_args = args[..64].to_vec();
+    let err = verify_in_lock(rv32_args, proof.pack().as_bytes()).unwrap_err();
+    assert_woss_error(err, WossError::Decode);
+}
+
+#[test]
+fn test_step_verifier_lock_rv64_every_step() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let mut prover = Prover::<u64>::new(4 << 20);
+    let buffer = fs::read("../simple64").unwrap().into();
+    prover.load_program(&buffer).unwrap();
+
+    let result = prover.run().unwrap();
+    prover.reset();
+    prover.load_program(&buffer).unwrap();
+
+    for window in result.step_commitments.windows(2) {
+        let (prev_step, next_step) = (window[0], window[1]);
+        let proof = prover.prove_next_step().unwrap();
+        let mut verifier = Verifier::<u64>::from_proof(proof.clone()).unwrap();
+        assert_eq!(prev_step, verifier.commit_step().unwrap());
+        assert_eq!(next_step, verifier.execute_next_step().unwrap());
+
+        let proof64: StepProof<u64> = packed::StepProof64::from_slice(&proof.pack().as_bytes())
+            .unwrap()
+            .try_unpack()
+            .unwrap();
+        let mut verifier = Verifier::<u64>::from_proof(proof64).unwrap();
+        assert_eq!(next_step, verifier.execute_next_step().unwrap());
+    }
+}
+
+// Default `CycleModel` must stay above cycles measured in lock, run with
+// `--nocapture` to see the margin when recalibrating it
+#[test]
+fn test_step_verifier_lock_cycle_model() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let mut prover = Prover::<u32>::new(4 << 20);
+    let buffer = fs::read("../simple").unwrap().into();
+    prover.load_program(&buffer).unwrap();
+
+    let result = prover.run().unwrap();
+    prover.reset();
+    prover.load_program(&buffer).unwrap();
+
+    let model = CycleModel::default();
+    for (idx, window) in result.step_commitments.windows(2).enumerate() {
+        let proof = prover.prove_next_step().unwrap();
+        if idx % 10 != 0 {
+            continue;
+        }
+
+        let args = {
+            let mut com = window[0].commitment.to_vec();
+            com.extend_from_slice(&window[1].commitment);
+            com
+        };
+        let cycles = verify_in_lock(args, proof.pack().as_bytes()).unwrap();
+        let estimate = estimate_proof(&proof, &model);
+        println!(
+            "step {} kvs {} merkle proof {} witness {} cycles {} estimated {}",
+            window[0].step_num,
+            proof.memory.kvs.len(),
+            proof.memory.merkle_proof.len(),
+            estimate.witness_size,
+            cycles,
+            estimate.verifier_cycles
+        );
+        assert!(estimate.verifier_cycles >= cycles);
+    }
+}
+
+#[test]
+fn test_step_verifier_lock_load_input_syscall() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let input = Bytes::from_static(b"*woss load input");
+    let new_prover = || {
+        let builder = Machine::<u64, ProverSMT>::builder(4 << 20)
+            .syscall(Box::new(LoadInput::new(input.clone())));
+        Prover::from_builder(builder).unwrap()
+    };
+
+    // Assembled from load_input64.S
+    let buffer = fs::read("../load_input64").unwrap().into();
+    let mut prover = new_prover();
+    prover.load_program(&buffer).unwrap();
+    let result = prover.run().unwrap();
+
+    let mut prover = new_prover();
+    prover.load_program(&buffer).unwrap();
+    for window in result.step_commitments.windows(2) {
+        let (prev_step, next_step) = (window[0], window[1]);
+        let proof = prover.prove_next_step().unwrap();
+        if proof.syscall_witness.is_empty() {
+            continue;
+        }
+
+        let args = {
+            let mut args = prev_step.commitment.to_vec();
+            args.extend_from_slice(&next_step.commitment);
+            args.push(64);
+            args
+        };
+        let cycles = verify_in_lock(args.clone(), proof.pack().as_bytes()).unwrap();
+        let estimate = estimate_proof(&proof, &CycleModel::default());
+        println!("cycles {} estimated {}", cycles, estimate.verifier_cycles);
+        assert!(estimate.verifier_cycles >= cycles);
+
+        let witness = WitnessArgs::new_builder()
+            .lock(Some(proof.pack().as_bytes()).pack())
+            .build();
+        assert_eq!(estimate.witness_size, witness.as_slice().len());
+
+        let mut forged = proof.clone();
+        forged.syscall_witness = b"*forged input".to_vec();
+        let err = verify_in_lock(args, forged.pack().as_bytes()).unwrap_err();
+        assert_woss_error(err, WossError::SyscallWitnessMismatch);
+        return;
+    }
+
+    panic!("no syscall step");
+}
+
+#[test]
+fn test_step_verifier_lock_error_codes() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let mut prover = Machine::<u32, ProverSMT>::new(4 << 20);
     let buffer = fs::read("../simple").unwrap().into();
     prover.load_program(&buffer).unwrap();
+    prover.run_until_step(10).unwrap();
+
+    // Find a step whose next step touches more keys, and whose instruction
+    // word fits in one data chunk
+    let (snapshot, proof, broad) = loop {
+        let snapshot = prover.snapshot().unwrap();
+        let broad = prover.prove_steps(2).unwrap();
+        prover.restore_snapshot(&snapshot);
+        let proof = prover.prove_next_step().unwrap();
+        if broad.memory.kvs.len() > proof.memory.kvs.len() && proof.pc % 32 <= 28 {
+            break (snapshot, proof, broad);
+        }
+    };
+
+    let commitment_args = |proof: &StepProof<u32>| {
+        let mut verifier = Verifier::<u32>::from_proof(proof.clone()).unwrap();
+        let mut args = verifier.commit_step().unwrap().commitment.to_vec();
+        let next_step = verifier.execute_next_step().map(|c| c.commitment);
+        args.extend_from_slice(&next_step.unwrap_or_default());
+        args
+    };
+    let args = commitment_args(&proof);
+    verify_in_lock(args.clone(), proof.pack().as_bytes()).unwrap();
+
+    let mut wrong_args = args.clone();
+    wrong_args[0] ^= 1;
+    let err = verify_in_lock(wrong_args, proof.pack().as_bytes()).unwrap_err();
+    assert_woss_error(err, WossError::CommitmentMismatch);
+
+    let mut tampered = proof.clone();
+    let value = tampered.memory.kvs.values_mut().next().unwrap();
+    *value = Bytes32::from_u64(value.to_u64() ^ 1);
+    let err = verify_in_lock(args.clone(), tampered.pack().as_bytes()).unwrap_err();
+    assert_woss_error(err, WossError::InvalidMerkleProof);
+
+    let mut wrong_hint = proof.clone();
+    wrong_hint.memory.hints[0] = match wrong_hint.memory.hints[0] {
+        KeyHint::DataChunk(chunk) => KeyHint::DataChunk(chunk + 1),
+        KeyHint::Flag(page) => KeyHint::Flag(page + 1),
+    };
+    let err = verify_in_lock(args.clone(), wrong_hint.pack().as_bytes()).unwrap_err();
+    assert_woss_error(err, WossError::InvalidKeyHint);
+
+    // Remaining keys still verify against the root
+    let mut omitted = proof.clone();
+    let key = *omitted.memory.kvs.keys().next().unwrap();
+    omitted.memory.kvs.remove(&key);
+    omitted.memory.hints.clear();
+    let keys: Vec<Bytes32> = omitted.memory.kvs.keys().cloned().collect();
+    omitted.memory.merkle_proof = snapshot.memory.prove(&keys).unwrap();
+    let err = verify_in_lock(args.clone(), omitted.pack().as_bytes()).unwrap_err();
+    assert_woss_error(err, WossError::UnprovenKey);
+
+    let err = verify_in_lock(args, broad.pack().as_bytes()).unwrap_err();
+    assert_woss_error(err, WossError::UnusedProofKey);
+
+    // `andn ra, sp, gp` from B extension, machine is IMC only
+    let mut unsupported = proof.clone();
+    let pc = unsupported.pc as u64;
+    let key = hash_data_chunk_key(pc / 32);
+    let mut chunk = unsupported.memory.kvs[&key];
+    chunk.write_value(&0x4031_70b3u32.to_le_bytes(), (pc % 32) as usize);
+    let mut memory = snapshot.memory.snap();
+    memory.update(key.into(), chunk).unwrap();
+    unsupported.memory.kvs.insert(key, chunk);
+    unsupported.memory.root = (*memory.root()).into();
+    let keys: Vec<Bytes32> = unsupported.memory.kvs.keys().cloned().collect();
+    unsupported.memory.merkle_proof = memory.prove(&keys).unwrap();
+    let args = commitment_args(&unsupported);
+    let err = verify_in_lock(args, unsupported.pack().as_bytes()).unwrap_err();
+    assert_woss_error(
+        err,
+        WossError::InvalidInstruction {
+            pc,
+            instruction: 0x4031_70b3,
+        },
+    );
+
+    let mut last_step = proof.clone();
+    last_step.step_num = u64::MAX;
+    let args = commitment_args(&last_step);
+    let err = verify_in_lock(args, last_step.pack().as_bytes()).unwrap_err();
+    assert_woss_error(err, WossError::StepOverflow);
+
+    // Fetch instruction beyond memory
+    let mut out_of_bound = proof;
+    out_of_bound.pc = 4 << 20;
+    let args = commitment_args(&out_of_bound);
+    let err = verify_in_lock(args, out_of_bound.pack().as_bytes()).unwrap_err();
+    assert_woss_error(err, WossError::MemOutOfBound);
+}
+
+fn assert_script_error(err: ckb_error::Error, code: i8) {
+    let err = err.to_string();
+    assert!(
+        err.contains(&format!("error code {} ", code)),
+        "error {}, expected code {}",
+        err,
+        code
+    );
+}
+
+fn assert_woss_error(err: ckb_error::Error, woss_err: WossError) {
+    assert_script_error(err, WOSS_ERROR_BASE + woss_err.code() as i8);
+}
+
+fn prove_random_step<R: Register + 'static>(
+    program: &str,
+) -> (StepProof<R>, StepCommitment, StepCommitment)
+where
+    StepProof<R>: MoleculeSize,
+{
+    let mut prover = Prover::<R>::new(4 << 20);
+    let buffer = fs::read(program).unwrap().into();
+    prover.load_program(&buffer).unwrap();
 
     let result = prover.run().unwrap();
     println!("step count: {}", result.step_count);
@@ -67,79 +329,15 @@ fn test_step_verifier_lock() {
     assert_eq!(result.step_count, prev_step.step_num);
 
     let proof = prover.prove_next_step().unwrap();
-    let mut verifier = Verifier::<u32>::from_proof(proof.clone()).unwrap();
+    let mut verifier = Verifier::<R>::from_proof(proof.clone()).unwrap();
     assert_eq!(prev_step, verifier.commit_step().unwrap());
 
     verifier.execute_next_step().unwrap();
     assert_eq!(random_step, verifier.commit_step().unwrap());
 
-    // Test verifier in lock contract
-    let verifier_lock_cell = {
-        let cell = CellInfo {
-            output: CellOutput::new_builder()
-                .capacity(Pack::pack(&u64::MAX))
-                .build(),
-            data: STEP_VERIFIER_LOCK_PROGRAM.clone(),
-            data_hash: STEP_VERIFIER_LOCK_CODE_HASH.pack(),
-        };
-        let out_point = OutPoint::new_builder()
-            .tx_hash(rand::random::<[u8; 32]>().pack())
-            .build();
-        InputCellInfo {
-            input: CellInput::new_builder().previous_output(out_point).build(),
-            cell,
-        }
-    };
-
-    let test_input_cell = {
-        let args = {
-            let mut com = prev_step.commitment.to_vec();
-            com.extend_from_slice(&random_step.commitment);
-            Pack::pack(com.as_slice())
-        };
-        // Enable vm version1
-        let lock = Script::new_builder()
-            .code_hash(STEP_VERIFIER_LOCK_CODE_HASH.pack())
-            .hash_type(ScriptHashType::Data1.into())
-            .args(args)
-            .build();
-
-        let cell = CellInfo {
-            output: CellOutput::new_builder()
-                .capacity(Pack::<Uint64>::pack(&u64::MAX))
-                .lock(lock)
-                .build(),
-            ..Default::default()
-        };
-        let out_point = OutPoint::new_builder()
-            .tx_hash(rand::random::<[u8; 32]>().pack())
-            .build();
-        InputCellInfo {
-            input: CellInput::new_builder().previous_output(out_point).build(),
-            cell,
-        }
-    };
-
-    let raw_tx = RawTransaction::new_builder()
-        .cell_deps(vec![CellDep::from(&verifier_lock_cell)].pack())
-        .inputs(vec![test_input_cell.input.clone()].pack())
-        .build();
-    let witness = WitnessArgs::new_builder()
-        .lock(Some(proof.pack().as_bytes()).pack())
-        .build();
-    let tx = Transaction::new_builder()
-        .raw(raw_tx)
-        .witnesses(vec![witness.as_bytes()].pack())
-        .build();
-
-    let mut tx_dataloader = TxDataLoader::default();
-    tx_dataloader.extend_cell_deps(vec![verifier_lock_cell]);
-    tx_dataloader.extend_inputs(vec![test_input_cell]);
-
-    let resolved_tx = tx_dataloader.resolve_tx(&tx);
-    let cycles = TransactionScriptsVerifier::new(&resolved_tx, &tx_dataloader)
-        .verify(u64::MAX)
-        .unwrap();
+    (proof, prev_step, random_step)
+}
 
-    println!("cycles {}", cycles);
+fn verify_in_lock(args: Vec<u8>, proof: Bytes) -> Result<u64, ckb_error::Error> {
+    run_lock_script(&STEP_VERIFIER_LOCK_PROGRAM, &args, proof)
 }
@@ -32,22 +32,13 @@ impl From<&InputCellInfo> for CellDep {
     }
 }
 
+#[derive(Default)]
 pub struct TxDataLoader {
     headers: HashMap<Byte32, HeaderView>,
     cell_deps: HashMap<OutPoint, CellInfo>,
     inputs: HashMap<OutPoint, CellInfo>,
 }
 
-impl Default for TxDataLoader {
-    fn default() -> Self {
-        Self {
-            headers: Default::default(),
-            cell_deps: Default::default(),
-            inputs: Default::default(),
-        }
-    }
-}
-
 impl TxDataLoader {
     pub fn extend_inputs(&mut self, inputs: impl IntoIterator<Item = InputCellInfo>) {
         self.inputs.extend(
@@ -67,7 +58,7 @@ impl TxDataLoader {
     pub fn resolve_tx(&self, tx: &Transaction) -> ResolvedTransaction {
         let to_meta = |out_point: OutPoint| -> CellMeta {
             self.get_cell_meta(&out_point)
-                .expect(&format!("resolve tx outpoint {}", out_point))
+                .unwrap_or_else(|| panic!("resolve tx outpoint {}", out_point))
         };
 
         let mut resolved_dep_groups = vec![];